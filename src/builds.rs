@@ -0,0 +1,77 @@
+// The results of `vupdate build`, kept in `$XDG_DATA_HOME/vupdate/builds.jsonl`
+// so that the report can tell which updates were build-tested. Each build
+// appends a JSON object on a line of its own; the last line for a package is
+// its current result.
+
+use crate::config::data_dir;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Build {
+    pub pkg_name: String,
+    pub version: String,
+    // The target architecture, `None` for a native build
+    pub arch: Option<String>,
+    pub ok: bool,
+    // The day of the build, as `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl Build {
+    fn to_json(&self) -> Value {
+        json!({
+            "pkgname": self.pkg_name,
+            "version": self.version,
+            "arch": self.arch,
+            "ok": self.ok,
+            "date": self.date,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Build> {
+        Some(Build {
+            pkg_name: value.get("pkgname")?.as_str()?.to_string(),
+            version: value.get("version")?.as_str()?.to_string(),
+            arch: value.get("arch").and_then(Value::as_str).map(String::from),
+            ok: value.get("ok")?.as_bool()?,
+            date: value.get("date")?.as_str()?.to_string(),
+        })
+    }
+}
+
+pub fn path() -> io::Result<PathBuf> {
+    data_dir()
+        .map(|dir| dir.join("builds.jsonl"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+}
+
+// The last build of each package. A missing file means no builds; lines
+// that cannot be read are skipped.
+pub fn load() -> io::Result<HashMap<String, Build>> {
+    let text = match std::fs::read_to_string(path()?) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter_map(|value| Build::from_json(&value))
+        .map(|build| (build.pkg_name.clone(), build))
+        .collect())
+}
+
+pub fn record(build: &Build) -> io::Result<()> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", build.to_json())
+}
@@ -0,0 +1,72 @@
+// Copies of the fetched updates files, kept under `$XDG_CACHE_HOME/vupdate`
+// (`~/.cache/vupdate` by default) so that unchanged files need not be
+// downloaded again. Each file is stored with a `.meta` file next to it that
+// holds the HTTP validators and the time it was last fetched, as JSON.
+
+use serde_json::{json, Value};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Directory holding vupdate's cached files
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("vupdate"))
+}
+
+pub struct Entry {
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    // When the body was last fetched or confirmed unchanged, in seconds since
+    // the Unix epoch
+    pub fetched: u64,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn meta_path(path: &Path) -> PathBuf {
+    let mut meta = path.as_os_str().to_owned();
+    meta.push(".meta");
+    PathBuf::from(meta)
+}
+
+// Load the cached copy of `file` (a path relative to the mirror, e.g.
+// `void-updates.txt`) from `dir`, if there is one
+pub fn load(dir: &Path, file: &str) -> Option<Entry> {
+    let path = dir.join(file);
+    let body = std::fs::read_to_string(&path).ok()?;
+    let meta: Value = std::fs::read_to_string(meta_path(&path))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or(Value::Null);
+    let string = |key: &str| meta[key].as_str().map(String::from);
+    Some(Entry {
+        body,
+        etag: string("etag"),
+        last_modified: string("last_modified"),
+        fetched: meta["fetched"].as_u64().unwrap_or(0),
+    })
+}
+
+pub fn store(dir: &Path, file: &str, entry: &Entry) -> io::Result<()> {
+    let path = dir.join(file);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let meta = json!({
+        "etag": entry.etag,
+        "last_modified": entry.last_modified,
+        "fetched": entry.fetched,
+    });
+    std::fs::write(&path, &entry.body)?;
+    std::fs::write(meta_path(&path), meta.to_string())
+}
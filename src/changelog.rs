@@ -0,0 +1,195 @@
+// What changed upstream between two versions, for `vupdate changelog`: the
+// release notes of the releases in between, or the commits between the two
+// tags for projects that tag without writing releases. Only projects hosted
+// on GitHub or GitLab can be asked.
+
+use crate::error::Result;
+use crate::{github, gitlab, version};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub tag: String,
+    // The title, often just the tag again
+    pub name: String,
+    // The release notes, usually Markdown
+    pub body: String,
+    // When it was published, as YYYY-MM-DD
+    pub date: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    // The abbreviated hash
+    pub id: String,
+    // The first line of the message
+    pub title: String,
+}
+
+// Where an upstream project is hosted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Forge {
+    // The `<owner>/<name>` of a GitHub repository
+    GitHub(String),
+    // A project on gitlab.com or another GitLab instance
+    GitLab { origin: String, project: String },
+}
+
+impl Forge {
+    // The project a URL points into, e.g. a homepage, a distfile or the URL
+    // the updates list found the version at
+    pub fn from_url(url: &str) -> Option<Forge> {
+        let url = reqwest::Url::parse(url).ok()?;
+        let host = url.host_str()?;
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        if host == "github.com" || host == "www.github.com" || host == "codeload.github.com" {
+            let (owner, name) = (segments.first()?, segments.get(1)?);
+            let name = name.trim_end_matches(".git");
+            return Some(Forge::GitHub(format!("{}/{}", owner, name)));
+        }
+        if host == "gitlab.com" || host.starts_with("gitlab.") {
+            // The project's path ends where GitLab's own pages begin
+            let path: Vec<&str> = segments.into_iter().take_while(|s| *s != "-").collect();
+            if path.len() < 2 {
+                return None;
+            }
+            let project = path.join("/");
+            return Some(Forge::GitLab {
+                origin: format!("{}://{}", url.scheme(), host),
+                project: project.trim_end_matches(".git").to_string(),
+            });
+        }
+        None
+    }
+
+    // The project as shown to the user, e.g. `github.com/vim/vim`
+    pub fn display(&self) -> String {
+        match self {
+            Forge::GitHub(repo) => format!("github.com/{}", repo),
+            Forge::GitLab { origin, project } => {
+                let host = origin.split("://").nth(1).unwrap_or(origin);
+                format!("{}/{}", host, project)
+            }
+        }
+    }
+}
+
+pub enum Changelog {
+    // The releases after the current version up to the new one, newest first
+    Releases(Vec<Release>),
+    // The commits from the current version's tag to the new one's, and where
+    // to see them
+    Commits { commits: Vec<Commit>, url: String },
+}
+
+// The version a tag names, skipping prefixes like `v` or `<name>-`
+pub fn tag_version(tag: &str) -> &str {
+    tag.trim_start_matches(|c: char| !c.is_ascii_digit())
+}
+
+// What changed from `current` to `new` in the project on `forge`, `None` if
+// neither releases nor tags for both versions can be found. `client` should
+// set a User-Agent; `github_token` raises GitHub's rate limit.
+pub async fn changelog(
+    client: &reqwest::Client,
+    github_token: Option<String>,
+    forge: &Forge,
+    current: &str,
+    new: &str,
+) -> Result<Option<Changelog>> {
+    let in_range = |v: &str| {
+        version::compare(v, current) == Ordering::Greater
+            && version::compare(v, new) != Ordering::Greater
+    };
+    let (releases, tags) = match forge {
+        Forge::GitHub(repo) => {
+            let github = github::Client::new(client.clone(), github_token.clone());
+            let releases = github.releases(repo).await?;
+            if releases.iter().any(|r| in_range(tag_version(&r.tag))) {
+                (releases, Vec::new())
+            } else {
+                (Vec::new(), github.tags(repo).await?)
+            }
+        }
+        Forge::GitLab { origin, project } => {
+            let gitlab = gitlab::Client::new(client.clone(), origin);
+            let releases = gitlab.releases(project).await?;
+            if releases.iter().any(|r| in_range(tag_version(&r.tag))) {
+                (releases, Vec::new())
+            } else {
+                (Vec::new(), gitlab.tags(project).await?)
+            }
+        }
+    };
+
+    if !releases.is_empty() {
+        let mut releases: Vec<Release> = releases
+            .into_iter()
+            .filter(|r| in_range(tag_version(&r.tag)))
+            .collect();
+        releases.sort_by(|a, b| version::compare(tag_version(&b.tag), tag_version(&a.tag)));
+        return Ok(Some(Changelog::Releases(releases)));
+    }
+
+    let tag = |v: &str| tags.iter().find(|t| tag_version(t) == v).cloned();
+    let (from, to) = match (tag(current), tag(new)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Ok(None),
+    };
+    let (commits, url) = match forge {
+        Forge::GitHub(repo) => {
+            github::Client::new(client.clone(), github_token)
+                .compare(repo, &from, &to)
+                .await?
+        }
+        Forge::GitLab { origin, project } => {
+            gitlab::Client::new(client.clone(), origin)
+                .compare(project, &from, &to)
+                .await?
+        }
+    };
+    Ok(Some(Changelog::Commits { commits, url }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forge_from_url() {
+        assert_eq!(
+            Forge::from_url("https://github.com/vim/vim/archive/v9.1.0.tar.gz"),
+            Some(Forge::GitHub("vim/vim".to_string()))
+        );
+        assert_eq!(
+            Forge::from_url("https://github.com/testing-cabal/mock.git"),
+            Some(Forge::GitHub("testing-cabal/mock".to_string()))
+        );
+        assert_eq!(
+            Forge::from_url(
+                "https://gitlab.freedesktop.org/xorg/lib/libx11/-/archive/1.8/libx11-1.8.tar.gz"
+            ),
+            Some(Forge::GitLab {
+                origin: "https://gitlab.freedesktop.org".to_string(),
+                project: "xorg/lib/libx11".to_string(),
+            })
+        );
+        assert_eq!(
+            Forge::from_url("https://gitlab.gnome.org/GNOME/gtk/-/tags"),
+            Some(Forge::GitLab {
+                origin: "https://gitlab.gnome.org".to_string(),
+                project: "GNOME/gtk".to_string(),
+            })
+        );
+        assert_eq!(Forge::from_url("https://github.com/vim"), None);
+        assert_eq!(Forge::from_url("https://www.vim.org/"), None);
+    }
+
+    #[test]
+    fn versions_of_tags() {
+        assert_eq!(tag_version("v9.1.0"), "9.1.0");
+        assert_eq!(tag_version("gtk-3.24.42"), "3.24.42");
+        assert_eq!(tag_version("4.0.3"), "4.0.3");
+    }
+}
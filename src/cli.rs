@@ -0,0 +1,1287 @@
+// Command line handling. The accepted commands and options are described by
+// the `COMMANDS` and `OPTIONS` tables, which drive both the parser and the
+// `--help` output.
+//
+// Options given on the command line take precedence over their environment
+// variable, which in turn takes precedence over the configuration file.
+
+use colored::Colorize;
+use std::collections::HashMap;
+
+pub struct Cmd {
+    pub name: &'static str,
+    // Hidden commands are internal helpers left out of the help and man page
+    pub hidden: bool,
+    // Arguments of the command as shown in its usage line
+    pub args: &'static str,
+    pub help: &'static str,
+}
+
+// The command run when none is given
+pub static DEFAULT_COMMAND: &str = "check";
+
+pub static COMMANDS: &[Cmd] = &[
+    Cmd {
+        name: "check",
+        hidden: false,
+        args: "[PATTERN]...",
+        help: "List available updates, limited to packages matching any PATTERN",
+    },
+    Cmd {
+        name: "ignore",
+        hidden: false,
+        args: "add|remove|list [PKG]...",
+        help: "Manage the packages whose updates are not reported",
+    },
+    Cmd {
+        name: "info",
+        hidden: false,
+        args: "<PKG>...",
+        help: "Show what is known about packages",
+    },
+    Cmd {
+        name: "open",
+        hidden: false,
+        args: "<PKG>...",
+        help: "Open the upstream homepages of packages with xdg-open",
+    },
+    Cmd {
+        name: "changelog",
+        hidden: false,
+        args: "<PKG>...",
+        help: "Show the upstream release notes or commits between the current and new version",
+    },
+    Cmd {
+        name: "bump",
+        hidden: false,
+        args: "<PKG>...",
+        help: "Update the templates of packages in the void-packages checkout to the new version",
+    },
+    Cmd {
+        name: "pr",
+        hidden: false,
+        args: "<PKG>",
+        help: "Push the checked out branch to your fork and open a pull request for PKG",
+    },
+    Cmd {
+        name: "build",
+        hidden: false,
+        args: "<PKG>",
+        help: "Build PKG with xbps-src in the void-packages checkout and record the result",
+    },
+    Cmd {
+        name: "revbump",
+        hidden: false,
+        args: "<PKG>",
+        help:
+            "List the packages needing a revbump for the sonames the built PKG no longer provides",
+    },
+    Cmd {
+        name: "report",
+        hidden: false,
+        args: "--html FILE [PATTERN]...",
+        help: "Write the updates matching any PATTERN to FILE as a standalone HTML page",
+    },
+    Cmd {
+        name: "digest",
+        hidden: false,
+        args: "--email-to ADDRESS [PATTERN]...",
+        help:
+            "Mail a summary of the updates matching any PATTERN through the configured SMTP server",
+    },
+    Cmd {
+        name: "watch",
+        hidden: false,
+        args: "[PATTERN]...",
+        help: "Check for updates matching any PATTERN on an interval, recording and notifying them",
+    },
+    Cmd {
+        name: "serve",
+        hidden: false,
+        args: "[PATTERN]...",
+        help:
+            "Check for updates matching any PATTERN on an interval and serve them as JSON over HTTP",
+    },
+    Cmd {
+        name: "pick",
+        hidden: false,
+        args: "[PATTERN]...",
+        help: "Choose among the updates matching any PATTERN with a fuzzy finder and act on them",
+    },
+    Cmd {
+        name: "diff",
+        hidden: false,
+        args: "[FROM] [TO]",
+        help: "Compare two recorded runs: latest, previous or the last one on a YYYY-MM-DD date",
+    },
+    Cmd {
+        name: "stats",
+        hidden: false,
+        args: "",
+        help: "Show figures about the updates recorded in the history",
+    },
+    Cmd {
+        name: "maintainers",
+        hidden: false,
+        args: "",
+        help: "List the maintainers by the number of pending updates in the full updates list",
+    },
+    Cmd {
+        name: "status",
+        hidden: false,
+        args: "set <PKG> <STATUS>|show [PKG]...",
+        help: "Track the work on updates: new, in-progress, bumped, pr-filed or merged",
+    },
+    Cmd {
+        name: "note",
+        hidden: false,
+        args: "[<PKG> [TEXT]...]",
+        help: "Attach a note to PKG, or show the notes",
+    },
+    Cmd {
+        name: "worktree",
+        hidden: false,
+        args: "list|clean [PKG]...",
+        help: "Manage the worktrees made by bump --worktree",
+    },
+    Cmd {
+        name: "systemd-install",
+        hidden: false,
+        args: "",
+        help: "Install and enable a systemd user timer running vupdate check",
+    },
+    Cmd {
+        name: "completions",
+        hidden: false,
+        args: "bash|zsh|fish",
+        help: "Print a shell completion script",
+    },
+    Cmd {
+        name: "mangen",
+        hidden: false,
+        args: "",
+        help: "Print a man page in roff format",
+    },
+    Cmd {
+        name: "__complete",
+        hidden: true,
+        args: "installed|ignored",
+        help: "Print package names for the completion scripts",
+    },
+];
+
+pub struct Opt {
+    pub long: &'static str,
+    pub short: Option<char>,
+    // Placeholder for the option's value, `None` for boolean flags
+    pub value: Option<&'static str>,
+    // Environment variable consulted when the option is not given
+    pub env: Option<&'static str>,
+    // The accepted values, if there is a fixed set of them
+    pub choices: &'static [&'static str],
+    // Commands accepting the option, empty for options shared by all commands
+    pub commands: &'static [&'static str],
+    pub help: &'static str,
+}
+
+pub static OPTIONS: &[Opt] = &[
+    Opt {
+        long: "email",
+        short: Some('e'),
+        value: Some("EMAIL"),
+        env: Some("VUPDATE_EMAIL"),
+        choices: &[],
+        commands: &[],
+        help: "Maintainer email to fetch updates for, may be repeated",
+    },
+    Opt {
+        long: "maintainer",
+        short: None,
+        value: Some("EMAIL"),
+        env: None,
+        choices: &[],
+        commands: &["check"],
+        help: "Show only the updates of another maintainer, may be repeated",
+    },
+    Opt {
+        long: "color",
+        short: None,
+        value: Some("WHEN"),
+        env: None,
+        choices: &["auto", "always", "never"],
+        commands: &[],
+        help: "When to color the output: auto, always or never",
+    },
+    Opt {
+        long: "timeout",
+        short: None,
+        value: Some("DURATION"),
+        env: None,
+        choices: &[],
+        commands: &[],
+        help: "Give up on a request after DURATION, e.g. 10s or 500ms (default 30s)",
+    },
+    Opt {
+        long: "url",
+        short: None,
+        value: Some("URL"),
+        env: Some("VUPDATE_URL"),
+        choices: &[],
+        commands: &[],
+        help: "Fetch the updates lists from URL instead of the configured mirrors",
+    },
+    Opt {
+        long: "offline",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &[],
+        help: "Use the cached updates lists instead of fetching them",
+    },
+    Opt {
+        long: "from-file",
+        short: None,
+        value: Some("PATH"),
+        env: None,
+        choices: &[],
+        commands: &[
+            "check",
+            "report",
+            "digest",
+            "watch",
+            "serve",
+            "pick",
+            "info",
+            "open",
+            "changelog",
+            "bump",
+        ],
+        help: "Read the updates list from PATH (- for stdin) instead of fetching it",
+    },
+    Opt {
+        long: "distdir",
+        short: None,
+        value: Some("PATH"),
+        env: None,
+        choices: &[],
+        commands: &[
+            "check",
+            "report",
+            "digest",
+            "watch",
+            "serve",
+            "pick",
+            "info",
+            "open",
+            "changelog",
+            "bump",
+            "pr",
+            "build",
+            "revbump",
+            "stats",
+            "maintainers",
+            "worktree",
+        ],
+        help: "Cross-reference the templates of the void-packages checkout at PATH",
+    },
+    Opt {
+        long: "to",
+        short: None,
+        value: Some("VERSION"),
+        env: None,
+        choices: &[],
+        commands: &["bump"],
+        help: "Bump to VERSION instead of the version from the updates list",
+    },
+    Opt {
+        long: "no-checksum",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["bump"],
+        help: "Do not download the new distfiles to update the checksums",
+    },
+    Opt {
+        long: "no-lint",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["bump"],
+        help: "Do not check the bumped templates with xlint",
+    },
+    Opt {
+        long: "commit",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["bump", "revbump"],
+        help: "Commit each bump on a new branch named <pkg>-<version>, or each revbump",
+    },
+    Opt {
+        long: "sign",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["bump", "revbump"],
+        help: "Sign the commits made with --commit",
+    },
+    Opt {
+        long: "top",
+        short: None,
+        value: Some("N"),
+        env: None,
+        choices: &[],
+        commands: &["stats", "maintainers"],
+        help: "List the top N: longest pending packages (default 10) or maintainers (default 20)",
+    },
+    Opt {
+        long: "pr",
+        short: None,
+        value: Some("N"),
+        env: None,
+        choices: &[],
+        commands: &["status"],
+        help: "The number of the pull request opened for the update",
+    },
+    Opt {
+        long: "remove",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["note"],
+        help: "Remove the notes of the given packages",
+    },
+    Opt {
+        long: "jobs",
+        short: Some('j'),
+        value: Some("N"),
+        env: None,
+        choices: &[],
+        commands: &["build"],
+        help: "Build with N parallel jobs",
+    },
+    Opt {
+        long: "arch",
+        short: Some('a'),
+        value: Some("ARCH"),
+        env: None,
+        choices: &[],
+        commands: &["build"],
+        help: "Cross compile for ARCH, e.g. aarch64 or x86_64-musl",
+    },
+    Opt {
+        long: "musl",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["build"],
+        help: "Cross compile for the musl variant of the native architecture",
+    },
+    Opt {
+        long: "worktree",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["bump"],
+        help: "Like --commit, with each branch checked out in a worktree of its own",
+    },
+    Opt {
+        long: "tested",
+        short: None,
+        value: Some("HOW"),
+        env: None,
+        choices: &["yes", "briefly", "no"],
+        commands: &["pr"],
+        help: "How the changes were tested, for the pull request description",
+    },
+    Opt {
+        long: "built",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["pr"],
+        help: "State in the pull request that the package was built locally",
+    },
+    Opt {
+        long: "output",
+        short: Some('o'),
+        value: Some("FORMAT"),
+        env: None,
+        choices: &["text", "json", "yaml", "markdown", "atom", "statusbar"],
+        commands: &["check"],
+        help: "Output format: text, json, yaml, markdown, atom or statusbar",
+    },
+    Opt {
+        long: "sort",
+        short: Some('s'),
+        value: Some("KEY"),
+        env: None,
+        choices: &["name", "current", "new", "delta", "impact", "installs"],
+        commands: &["check", "report"],
+        help: "Order updates by name, current, new, delta (largest jump first), impact or installs",
+    },
+    Opt {
+        long: "format",
+        short: Some('f'),
+        value: Some("TEMPLATE"),
+        env: None,
+        choices: &[],
+        commands: &["check"],
+        help: "Print one line per update using TEMPLATE, e.g. '{name} {current} -> {new}'",
+    },
+    Opt {
+        long: "quiet",
+        short: Some('q'),
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check"],
+        help: "Print only package names, one per line, without headers or colors",
+    },
+    Opt {
+        long: "names-only",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check"],
+        help: "Same as --quiet",
+    },
+    Opt {
+        long: "print0",
+        short: Some('0'),
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check"],
+        help: "Print only package names, each followed by a NUL byte, for xargs -0",
+    },
+    Opt {
+        long: "html",
+        short: None,
+        value: Some("FILE"),
+        env: None,
+        choices: &[],
+        commands: &["report"],
+        help: "Write the report to FILE (- for stdout)",
+    },
+    Opt {
+        long: "email-to",
+        short: None,
+        value: Some("ADDRESS"),
+        env: None,
+        choices: &[],
+        commands: &["digest"],
+        help: "Send the digest to ADDRESS, may be repeated",
+    },
+    Opt {
+        long: "interval",
+        short: None,
+        value: Some("DURATION"),
+        env: None,
+        choices: &[],
+        commands: &["watch", "serve"],
+        help: "How long to wait between checks, 6h by default",
+    },
+    Opt {
+        long: "listen",
+        short: None,
+        value: Some("ADDRESS"),
+        env: None,
+        choices: &[],
+        commands: &["watch", "serve"],
+        help: "The address and port to serve on, 127.0.0.1:8642 by default for serve",
+    },
+    Opt {
+        long: "dbus",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["watch"],
+        help: "Serve the updates found on the session bus",
+    },
+    Opt {
+        long: "action",
+        short: None,
+        value: Some("ACTION"),
+        env: None,
+        choices: &["print", "bump", "ignore", "open", "copy"],
+        commands: &["pick"],
+        help: "What to do with the chosen updates, print their names by default",
+    },
+    Opt {
+        long: "open",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["pick"],
+        help: "Open the homepages of the chosen updates, like --action open",
+    },
+    Opt {
+        long: "on-calendar",
+        short: None,
+        value: Some("SPEC"),
+        env: None,
+        choices: &[],
+        commands: &["systemd-install"],
+        help: "When the timer runs, as a systemd calendar event (daily by default)",
+    },
+    Opt {
+        long: "regex",
+        short: Some('r'),
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Treat PATTERNs as regular expressions instead of globs",
+    },
+    Opt {
+        long: "all-installed",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Also report packages installed as dependencies, not only explicitly installed ones",
+    },
+    Opt {
+        long: "pending",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Also list binary updates waiting in the repository (xbps-install -Mun)",
+    },
+    Opt {
+        long: "hide-packaged",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Hide updates of installed packages that the binary repository already ships",
+    },
+    Opt {
+        long: "only-new",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Only list the updates that are new since the previous run",
+    },
+    Opt {
+        long: "since",
+        short: None,
+        value: Some("DATE"),
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Only list the updates first seen on or after DATE (YYYY-MM-DD)",
+    },
+    Opt {
+        long: "min-age",
+        short: None,
+        value: Some("DURATION"),
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Only list the updates pending for at least DURATION, e.g. 7d",
+    },
+    Opt {
+        long: "min-level",
+        short: None,
+        value: Some("LEVEL"),
+        env: None,
+        choices: &["patch", "minor", "major"],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Only list updates changing at least the minor or major version",
+    },
+    Opt {
+        long: "exec",
+        short: None,
+        value: Some("CMD"),
+        env: None,
+        choices: &[],
+        commands: &["check", "watch"],
+        help: "Run CMD with the shell for each new update, with {pkg} and {new} replaced",
+    },
+    Opt {
+        long: "no-history",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Do not record the updates found in the history",
+    },
+    Opt {
+        long: "templates",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Fetch the templates from void-packages' master branch without a distdir",
+    },
+    Opt {
+        long: "check-merged",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Mark updates already made on void-packages' master branch",
+    },
+    Opt {
+        long: "hide-merged",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Hide updates already made on void-packages' master branch",
+    },
+    Opt {
+        long: "impact",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Show how many installed packages depend on each updated package",
+    },
+    Opt {
+        long: "popcorn",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Show how many systems reporting to PopCorn have each updated package",
+    },
+    Opt {
+        long: "describe",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Show the short description of each updated package",
+    },
+    Opt {
+        long: "repology",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Mark updates with how many other distributions ship the new version",
+    },
+    Opt {
+        long: "check-prs",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Mark updates with an open pull request on void-packages",
+    },
+    Opt {
+        long: "check-security",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Mark updates fixing known vulnerabilities, according to OSV",
+    },
+    Opt {
+        long: "security-only",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Only list updates fixing known vulnerabilities",
+    },
+    Opt {
+        long: "check-eol",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Mark updates staying on a release branch past its end of life",
+    },
+    Opt {
+        long: "check-distfiles",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Mark updates whose new distfiles cannot be downloaded yet",
+    },
+    Opt {
+        long: "only-fetchable",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Hide updates whose new distfiles cannot be downloaded yet",
+    },
+    Opt {
+        long: "orphans-only",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Only list updates of installed packages without a maintainer",
+    },
+    Opt {
+        long: "skip-held",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Hide updates of packages on hold instead of marking them",
+    },
+    Opt {
+        long: "include-prereleases",
+        short: None,
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &["check", "report", "digest", "watch", "serve", "pick"],
+        help: "Also report updates to alpha, beta, rc and dev versions",
+    },
+    Opt {
+        long: "until",
+        short: None,
+        value: Some("DATE"),
+        env: None,
+        choices: &[],
+        commands: &["ignore"],
+        help: "With `ignore add`, ignore only until DATE (YYYY-MM-DD)",
+    },
+    Opt {
+        long: "until-version",
+        short: None,
+        value: Some("VERSION"),
+        env: None,
+        choices: &[],
+        commands: &["ignore"],
+        help: "With `ignore add`, ignore only updates up to VERSION",
+    },
+    Opt {
+        long: "help",
+        short: Some('h'),
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &[],
+        help: "Print help and exit",
+    },
+    Opt {
+        long: "version",
+        short: Some('V'),
+        value: None,
+        env: None,
+        choices: &[],
+        commands: &[],
+        help: "Print the version and exit",
+    },
+];
+
+impl Opt {
+    fn accepted_by(&self, command: &str) -> bool {
+        self.commands.is_empty() || self.commands.contains(&command)
+    }
+
+    // Fail on a value that is not one of the option's choices, if it has any
+    fn check_choice(&self, value: &str) -> Result<(), CliError> {
+        if self.choices.is_empty() || self.choices.contains(&value) {
+            Ok(())
+        } else {
+            let choices = self.choices.join(", ");
+            let reason = format!("'{}' is not one of {}", value, choices);
+            Err(CliError::Invalid(self.long, reason))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    UnknownOption(String),
+    // An option that exists but is not accepted by the given command
+    OptionNotAccepted(&'static str, String),
+    MissingValue(&'static str),
+    UnexpectedValue(&'static str),
+    UnexpectedArgument(String),
+    MissingArgument(&'static str),
+    // An option value that was rejected for the given reason
+    Invalid(&'static str, String),
+}
+
+impl CliError {
+    // A value of the option `long` that means nothing to it
+    pub fn invalid_value(long: &'static str, value: impl std::fmt::Display) -> CliError {
+        CliError::Invalid(long, format!("'{}' is not a valid value", value))
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CliError::UnknownOption(opt) => write!(f, "unknown option '{}'", opt),
+            CliError::OptionNotAccepted(long, cmd) => {
+                write!(f, "option '--{}' is not accepted by '{}'", long, cmd)
+            }
+            CliError::MissingValue(long) => write!(f, "option '--{}' requires a value", long),
+            CliError::UnexpectedValue(long) => write!(f, "option '--{}' takes no value", long),
+            CliError::UnexpectedArgument(arg) => write!(f, "unexpected argument '{}'", arg),
+            CliError::MissingArgument(arg) => write!(f, "missing argument {}", arg),
+            CliError::Invalid(long, reason) => write!(f, "invalid '--{}': {}", long, reason),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+// Print a diagnostic to stderr
+pub fn error(msg: impl std::fmt::Display) {
+    eprintln!("{}", msg.to_string().red());
+}
+
+// Print a problem that does not fail the command to stderr
+pub fn warning(msg: impl std::fmt::Display) {
+    eprintln!("{}", msg.to_string().yellow());
+}
+
+// Print an error for a usage mistake on the command line and exit
+pub fn usage_error(e: CliError) -> ! {
+    error(e);
+    eprintln!(
+        "Try '{} --help' for more information.",
+        env!("CARGO_PKG_NAME")
+    );
+    std::process::exit(2);
+}
+
+// The parsed command line
+#[derive(Clone)]
+pub struct Args {
+    values: HashMap<&'static str, Vec<String>>,
+    // The environment variables of the options not given on the command line
+    env: HashMap<&'static str, String>,
+    // The command to run and its arguments
+    pub command: &'static str,
+    pub operands: Vec<String>,
+    // Whether the command was named rather than the default
+    pub explicit_command: bool,
+}
+
+impl Args {
+    pub fn flag(&self, long: &str) -> bool {
+        self.values.contains_key(long)
+    }
+
+    // The last value given for an option, falling back to its environment
+    // variable
+    pub fn value(&self, long: &str) -> Option<String> {
+        if let Some(value) = self.values.get(long).and_then(|v| v.last()) {
+            return Some(value.clone());
+        }
+        self.env.get(long).cloned()
+    }
+
+    // All values given for a repeatable option. The environment variable
+    // fallback is split on commas.
+    pub fn values(&self, long: &str) -> Vec<String> {
+        if let Some(values) = self.values.get(long) {
+            return values.clone();
+        }
+        self.env
+            .get(long)
+            .map_or("", String::as_str)
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+fn find_long(long: &str) -> Option<&'static Opt> {
+    OPTIONS.iter().find(|o| o.long == long)
+}
+
+fn find_short(short: char) -> Option<&'static Opt> {
+    OPTIONS.iter().find(|o| o.short == Some(short))
+}
+
+fn find_command(name: &str) -> Option<&'static Cmd> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+// Parse the program arguments, excluding the program name. Options may appear
+// before or after the command.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, CliError> {
+    parse_with_env(args, |name| std::env::var(name).ok())
+}
+
+// Parse the program arguments, looking the environment variables of the
+// options up with `env`
+fn parse_with_env(
+    args: impl IntoIterator<Item = String>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Args, CliError> {
+    let mut values: HashMap<&'static str, Vec<String>> = HashMap::new();
+    let mut positionals = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positionals.extend(args);
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let opt = find_long(name).ok_or_else(|| CliError::UnknownOption(arg.clone()))?;
+            let value = match (opt.value, inline) {
+                (Some(_), Some(value)) => value,
+                (Some(_), None) => args.next().ok_or(CliError::MissingValue(opt.long))?,
+                (None, Some(_)) => return Err(CliError::UnexpectedValue(opt.long)),
+                (None, None) => String::new(),
+            };
+            opt.check_choice(&value)?;
+            values.entry(opt.long).or_default().push(value);
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            // A cluster of short flags, the last of which may take a value
+            // either attached (`-eme@example.com`) or as the next argument
+            for (i, c) in arg[1..].char_indices() {
+                let opt =
+                    find_short(c).ok_or_else(|| CliError::UnknownOption(format!("-{}", c)))?;
+                if opt.value.is_none() {
+                    values.entry(opt.long).or_default();
+                    continue;
+                }
+                let rest = &arg[1 + i + c.len_utf8()..];
+                let value = if rest.is_empty() {
+                    args.next().ok_or(CliError::MissingValue(opt.long))?
+                } else {
+                    rest.to_string()
+                };
+                opt.check_choice(&value)?;
+                values.entry(opt.long).or_default().push(value);
+                break;
+            }
+        } else {
+            positionals.push(arg);
+        }
+    }
+
+    // A first argument that names no command is an operand of the default
+    // one, e.g. the pattern of `vupdate 'python3-*'`
+    let mut positionals = positionals.into_iter().peekable();
+    let named = positionals.peek().and_then(|name| find_command(name));
+    let explicit_command = named.is_some();
+    if explicit_command {
+        positionals.next();
+    }
+    let command = named.unwrap_or_else(|| find_command(DEFAULT_COMMAND).unwrap());
+    if let Some(opt) = values
+        .keys()
+        .filter_map(|long| find_long(long))
+        .find(|opt| !opt.accepted_by(command.name))
+    {
+        return Err(CliError::OptionNotAccepted(
+            opt.long,
+            command.name.to_string(),
+        ));
+    }
+
+    let env = OPTIONS
+        .iter()
+        .filter(|opt| !values.contains_key(opt.long))
+        .filter_map(|opt| Some((opt.long, env(opt.env?)?)))
+        .filter(|(_, value)| !value.is_empty())
+        .collect();
+
+    Ok(Args {
+        values,
+        env,
+        command: command.name,
+        operands: positionals.collect(),
+        explicit_command,
+    })
+}
+
+// Render the options accepted by `filter` as aligned help lines
+fn options_help(filter: impl Fn(&Opt) -> bool) -> String {
+    let opts: Vec<&Opt> = OPTIONS.iter().filter(|o| filter(o)).collect();
+    let specs: Vec<String> = opts
+        .iter()
+        .map(|o| {
+            let short = match o.short {
+                Some(c) => format!("-{}, ", c),
+                None => "    ".to_string(),
+            };
+            match o.value {
+                Some(value) => format!("{}--{} <{}>", short, o.long, value),
+                None => format!("{}--{}", short, o.long),
+            }
+        })
+        .collect();
+    let width = specs.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    let mut out = String::new();
+    for (opt, spec) in opts.iter().zip(&specs) {
+        out.push_str(&format!("  {:width$}  {}", spec, opt.help, width = width));
+        if let Some(env) = opt.env {
+            out.push_str(&format!(" [env: {}]", env));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Render the `--help` text, for the whole program or for one command
+pub fn help(command: Option<&str>) -> String {
+    let name = env!("CARGO_PKG_NAME");
+    let mut out = format!(
+        "{} {}\nList Void Linux packages with updates available upstream\n\n",
+        name,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    if let Some(cmd) = command.and_then(find_command) {
+        out.push_str(&format!(
+            "{}\n\nUsage: {} {} [OPTIONS] {}\n\nOptions:\n",
+            cmd.help, name, cmd.name, cmd.args
+        ));
+        out.push_str(&options_help(|o| o.commands.contains(&cmd.name)));
+        out.push_str("\nGlobal options:\n");
+        out.push_str(&options_help(|o| o.commands.is_empty()));
+        return out;
+    }
+
+    out.push_str(&format!(
+        "Usage: {} [OPTIONS] [COMMAND]\n\nWithout a command, `{}` is run.\n\nCommands:\n",
+        name, DEFAULT_COMMAND
+    ));
+    let commands: Vec<&Cmd> = COMMANDS.iter().filter(|c| !c.hidden).collect();
+    let usages: Vec<String> = commands
+        .iter()
+        .map(|c| format!("{} {}", c.name, c.args))
+        .collect();
+    let width = usages.iter().map(|u| u.len()).max().unwrap_or(0);
+    for (cmd, usage) in commands.iter().zip(&usages) {
+        out.push_str(&format!(
+            "  {:width$}  {}\n",
+            usage,
+            cmd.help,
+            width = width
+        ));
+    }
+    out.push_str(&format!(
+        "\nGlobal options:\n{}\nSee '{} <COMMAND> --help' for the options of each command.\n",
+        options_help(|o| o.commands.is_empty()),
+        name
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_with_env, Args, CliError};
+
+    fn args_with_env(line: &[&str], env: &[(&str, &str)]) -> Result<Args, CliError> {
+        parse_with_env(line.iter().map(|arg| arg.to_string()), |name| {
+            env.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    fn args(line: &[&str]) -> Result<Args, CliError> {
+        args_with_env(line, &[])
+    }
+
+    // The option and reason of an `Invalid` error
+    fn invalid(line: &[&str]) -> (&'static str, String) {
+        match args(line) {
+            Err(CliError::Invalid(long, reason)) => (long, reason),
+            _ => panic!("{:?} should be rejected", line),
+        }
+    }
+
+    #[test]
+    fn parses_short_flag_clusters() {
+        assert_eq!(invalid(&["-q0ome@x.org"]).0, "output");
+        let parsed = args(&["-q0ojson", "-e", "me@x.org"]).unwrap();
+        assert!(parsed.flag("quiet"));
+        assert!(parsed.flag("print0"));
+        assert_eq!(parsed.value("output").as_deref(), Some("json"));
+        assert_eq!(parsed.values("email"), ["me@x.org"]);
+        let parsed = args(&["build", "-j4", "-a", "aarch64", "foo"]).unwrap();
+        assert_eq!(parsed.command, "build");
+        assert_eq!(parsed.value("jobs").as_deref(), Some("4"));
+        assert_eq!(parsed.value("arch").as_deref(), Some("aarch64"));
+        assert_eq!(parsed.operands, ["foo"]);
+        assert!(matches!(args(&["-qx"]), Err(CliError::UnknownOption(ref o)) if o == "-x"));
+        assert!(matches!(
+            args(&["-qo"]),
+            Err(CliError::MissingValue("output"))
+        ));
+    }
+
+    #[test]
+    fn parses_long_options() {
+        let parsed = args(&["--sort=delta", "--output", "json", "check"]).unwrap();
+        assert!(parsed.explicit_command);
+        assert_eq!(parsed.value("sort").as_deref(), Some("delta"));
+        assert_eq!(parsed.value("output").as_deref(), Some("json"));
+        let parsed = args(&["--timeout=1s", "--timeout=", "-e", "a", "--email=b"]).unwrap();
+        assert!(!parsed.explicit_command);
+        assert_eq!(parsed.command, "check");
+        assert_eq!(parsed.value("timeout").as_deref(), Some(""));
+        assert_eq!(parsed.values("email"), ["a", "b"]);
+        assert!(matches!(
+            args(&["--quiet=yes"]),
+            Err(CliError::UnexpectedValue("quiet"))
+        ));
+        assert!(matches!(
+            args(&["--sort"]),
+            Err(CliError::MissingValue("sort"))
+        ));
+        assert!(matches!(args(&["--nope"]), Err(CliError::UnknownOption(ref o)) if o == "--nope"));
+    }
+
+    #[test]
+    fn rejects_values_outside_the_choices() {
+        assert_eq!(
+            invalid(&["--color=sometimes"]),
+            (
+                "color",
+                "'sometimes' is not one of auto, always, never".to_string()
+            )
+        );
+        assert_eq!(invalid(&["--sort", "size"]).0, "sort");
+        assert_eq!(invalid(&["-shuge"]).0, "sort");
+        assert_eq!(
+            invalid(&["pr", "--tested=maybe", "foo"]),
+            (
+                "tested",
+                "'maybe' is not one of yes, briefly, no".to_string()
+            )
+        );
+        assert!(args(&["pr", "--tested=briefly", "foo"]).is_ok());
+        assert!(args(&["--color", "never"]).is_ok());
+    }
+
+    #[test]
+    fn stops_parsing_options_after_double_dash() {
+        let parsed = args(&["-q", "--", "check", "--sort", "-e"]).unwrap();
+        assert!(parsed.flag("quiet"));
+        assert!(!parsed.flag("sort"));
+        assert_eq!(parsed.command, "check");
+        assert_eq!(parsed.operands, ["--sort", "-e"]);
+        let parsed = args(&["note", "--", "-foo"]).unwrap();
+        assert_eq!(parsed.operands, ["-foo"]);
+        // A lone dash is an argument as well
+        let parsed = args(&["note", "-"]).unwrap();
+        assert_eq!(parsed.operands, ["-"]);
+    }
+
+    #[test]
+    fn falls_back_to_the_environment() {
+        let env = [
+            ("VUPDATE_EMAIL", "a@x.org, b@x.org,,"),
+            ("VUPDATE_URL", "https://mirror.example.com"),
+        ];
+        let parsed = args_with_env(&[], &env).unwrap();
+        assert_eq!(parsed.values("email"), ["a@x.org", "b@x.org"]);
+        assert_eq!(parsed.value("email").as_deref(), Some("a@x.org, b@x.org,,"));
+        assert_eq!(
+            parsed.value("url").as_deref(),
+            Some("https://mirror.example.com")
+        );
+        // Only the options' own variables are looked at, and they do not
+        // count as given
+        assert!(!parsed.flag("email"));
+        assert!(args_with_env(&[], &[("VUPDATE_SORT", "delta")])
+            .unwrap()
+            .value("sort")
+            .is_none());
+    }
+
+    #[test]
+    fn prefers_the_command_line_to_the_environment() {
+        let env = [("VUPDATE_EMAIL", "a@x.org"), ("VUPDATE_URL", "")];
+        let parsed = args_with_env(&["--email=c@x.org", "-e", "d@x.org"], &env).unwrap();
+        assert_eq!(parsed.values("email"), ["c@x.org", "d@x.org"]);
+        assert_eq!(parsed.value("email").as_deref(), Some("d@x.org"));
+        // Empty variables are as good as unset
+        assert_eq!(parsed.value("url"), None);
+        assert!(args(&[]).unwrap().values("email").is_empty());
+    }
+
+    #[test]
+    fn rejects_options_of_other_commands() {
+        assert!(matches!(
+            args(&["-j4", "check"]),
+            Err(CliError::OptionNotAccepted("jobs", ref cmd)) if cmd == "check"
+        ));
+        assert!(matches!(
+            args(&["note", "--quiet", "foo"]),
+            Err(CliError::OptionNotAccepted("quiet", ref cmd)) if cmd == "note"
+        ));
+        // Global options and those of the default command
+        assert!(args(&["note", "--color=never", "foo"]).is_ok());
+        assert!(args(&["--quiet"]).is_ok());
+    }
+
+    #[test]
+    fn takes_other_arguments_for_patterns_of_the_default_command() {
+        let parsed = args(&["python3-*", "foo"]).unwrap();
+        assert_eq!(parsed.command, "check");
+        assert!(!parsed.explicit_command);
+        assert_eq!(parsed.operands, ["python3-*", "foo"]);
+        let parsed = args(&["--regex", "^gtk"]).unwrap();
+        assert_eq!(parsed.command, "check");
+        assert_eq!(parsed.operands, ["^gtk"]);
+        // Only the first argument can name the command
+        let parsed = args(&["foo", "note"]).unwrap();
+        assert_eq!(parsed.command, "check");
+        assert_eq!(parsed.operands, ["foo", "note"]);
+        let parsed = args(&["check", "note"]).unwrap();
+        assert!(parsed.explicit_command);
+        assert_eq!(parsed.operands, ["note"]);
+        // The options are checked against the default command
+        assert!(matches!(
+            args(&["foo", "-j4"]),
+            Err(CliError::OptionNotAccepted("jobs", ref cmd)) if cmd == "check"
+        ));
+    }
+}
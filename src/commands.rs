@@ -0,0 +1,58 @@
+// The subcommands of vupdate, one module each. Every command returns the
+// process exit code.
+
+pub mod build;
+pub mod bump;
+pub mod changelog;
+pub mod check;
+pub mod completions;
+pub mod diff;
+pub mod digest;
+pub mod ignore;
+pub mod info;
+pub mod maintainers;
+pub mod mangen;
+pub mod note;
+pub mod open;
+pub mod pick;
+pub mod pr;
+pub mod report;
+pub mod revbump;
+pub mod serve;
+pub mod stats;
+pub mod status;
+pub mod systemd_install;
+pub mod watch;
+pub mod worktree;
+
+use colored::Colorize;
+use vupdate::date;
+use vupdate::fetch::Fetcher;
+use vupdate::status::{Entry, Status, StatusStore};
+
+// With --offline, tell the user how old the shown data is. This goes to
+// stderr so that it does not end up in machine readable output.
+pub fn offline_notice(fetcher: &Fetcher) {
+    if let Some(time) = fetcher.offline_data_time() {
+        let notice = format!("Offline: data from {}", date::format_timestamp(time));
+        eprintln!("{}", notice.yellow());
+    }
+}
+
+// Move a package along in the status file. This is a side effect of the
+// command, so failing to do it is reported but not fatal.
+pub fn record_status(pkg_name: &str, status: Status, version: &str, pr: Option<u64>) {
+    let result = StatusStore::load().and_then(|mut store| {
+        store.set(Entry {
+            pkg_name: pkg_name.to_string(),
+            status,
+            version: Some(version.to_string()),
+            pr,
+            date: date::today(),
+        });
+        store.save()
+    });
+    if let Err(e) = result {
+        crate::cli::error(format!("Could not update the status file: {}", e));
+    }
+}
@@ -0,0 +1,110 @@
+// `vupdate build <PKG>`: build a package with xbps-src in the void-packages
+// checkout and record whether it built, for the report
+
+use crate::cli::{self, Args};
+use std::process::Command;
+use vupdate::builds::{self, Build};
+use vupdate::config::Config;
+use vupdate::{date, template, xbps};
+
+pub fn run(config: &Config, args: &Args) -> i32 {
+    let pkg_name = match args.operands.as_slice() {
+        [pkg_name] => pkg_name,
+        [] => cli::usage_error(cli::CliError::MissingArgument("<PKG>")),
+        [_, extra, ..] => cli::usage_error(cli::CliError::UnexpectedArgument(extra.clone())),
+    };
+    let jobs = args.value("jobs");
+    if let Some(jobs) = &jobs {
+        if !jobs.parse::<u32>().is_ok_and(|n| n > 0) {
+            cli::usage_error(cli::CliError::invalid_value("jobs", jobs.clone()));
+        }
+    }
+    let arch = match (args.value("arch"), args.flag("musl")) {
+        (Some(_), true) => cli::usage_error(cli::CliError::Invalid(
+            "musl",
+            "cannot be combined with --arch".to_string(),
+        )),
+        (arch, false) => arch,
+        (None, true) => match xbps::native_arch() {
+            // Building natively already targets musl
+            Some(native) if native.ends_with("-musl") => None,
+            Some(native) => Some(format!("{}-musl", native)),
+            None => {
+                cli::error("Could not tell the native architecture with xbps-uhelper");
+                return 1;
+            }
+        },
+    };
+    let distdir = match &config.distdir {
+        Some(distdir) => distdir,
+        None => {
+            cli::error("No void-packages checkout: set 'distdir' or pass --distdir");
+            return 1;
+        }
+    };
+    let template = match template::load(distdir, pkg_name) {
+        Ok(Some(template)) => template,
+        Ok(None) => {
+            cli::error(format!(
+                "No template for {} in {}",
+                pkg_name,
+                distdir.display()
+            ));
+            return 1;
+        }
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    let main = if template.pkgname.is_empty() {
+        pkg_name.clone()
+    } else {
+        template.pkgname.clone()
+    };
+
+    // xbps-src's output goes straight to the terminal, so that the build
+    // can be followed
+    let mut command = Command::new("./xbps-src");
+    if let Some(jobs) = &jobs {
+        command.args(["-j", jobs]);
+    }
+    if let Some(arch) = &arch {
+        command.args(["-a", arch]);
+    }
+    command.args(["pkg", pkg_name]).current_dir(distdir);
+    let status = match command.status() {
+        Ok(status) => status,
+        Err(e) => {
+            cli::error(format!(
+                "Could not run xbps-src in {}: {}",
+                distdir.display(),
+                e
+            ));
+            return 1;
+        }
+    };
+    // A build interrupted by a signal, e.g. ^C, says nothing about the
+    // package
+    if status.code().is_none() {
+        return 1;
+    }
+
+    let build = Build {
+        pkg_name: main,
+        version: template.version,
+        arch,
+        ok: status.success(),
+        date: date::today(),
+    };
+    if let Err(e) = builds::record(&build) {
+        cli::error(format!("Could not record the build: {}", e));
+    }
+    let result = if build.ok { "built" } else { "failed to build" };
+    println!("{} {} {}", build.pkg_name, build.version, result);
+    if build.ok {
+        0
+    } else {
+        1
+    }
+}
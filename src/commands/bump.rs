@@ -0,0 +1,343 @@
+// `vupdate bump <PKG>...`: update templates in the local void-packages
+// checkout to the new upstream version
+
+use crate::cli::{self, Args};
+use colored::Colorize;
+use reqwest::StatusCode;
+use std::cmp::Ordering;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use vupdate::config::Config;
+use vupdate::error::Error;
+use vupdate::fetch::Fetcher;
+use vupdate::parse::UpdateMap;
+use vupdate::sha256::Sha256;
+use vupdate::status::Status;
+use vupdate::template::{self, Template};
+use vupdate::{git, version};
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    if args.operands.is_empty() {
+        cli::usage_error(cli::CliError::MissingArgument("<PKG>"));
+    }
+    let to = args.value("to");
+    if to.is_some() && args.operands.len() > 1 {
+        cli::usage_error(cli::CliError::Invalid(
+            "to",
+            "only one package can be bumped to a given version".to_string(),
+        ));
+    }
+    let distdir = match &config.distdir {
+        Some(distdir) => distdir,
+        None => {
+            cli::error("No void-packages checkout: set 'distdir' or pass --distdir");
+            return 1;
+        }
+    };
+
+    let fetcher = match Fetcher::new(config.fetch.clone()) {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    // Without --to, the new versions come from the updates list
+    let all_updates = if to.is_none() {
+        let result = fetcher.all_updates().await;
+        super::offline_notice(&fetcher);
+        match result {
+            Ok(updates) => updates,
+            Err(e) => {
+                cli::error(e);
+                return 1;
+            }
+        }
+    } else {
+        UpdateMap::new()
+    };
+    let checksums = !args.flag("no-checksum");
+    let lint = !args.flag("no-lint") && in_path("xlint");
+    if !args.flag("no-lint") && !lint {
+        let notice = "xlint is not installed (it comes with xtools), the templates are not checked";
+        eprintln!("{}", notice.yellow());
+    }
+    let worktree_dir = match (args.flag("worktree"), &config.worktree_dir) {
+        (false, _) => None,
+        (true, Some(dir)) => Some(dir.clone()),
+        (true, None) => {
+            cli::error("No directory for the worktrees: set 'worktree_dir'");
+            return 1;
+        }
+    };
+    let commit = if worktree_dir.is_some() || args.flag("commit") || args.flag("sign") {
+        // Each branch starts from the branch checked out now
+        match git::current_branch(distdir) {
+            Ok(Some(base)) => Some(Commit {
+                base,
+                sign: args.flag("sign"),
+                worktree_dir,
+            }),
+            Ok(None) => {
+                cli::error("Check out a branch to start the bump branches from first");
+                return 1;
+            }
+            Err(e) => {
+                cli::error(e);
+                return 1;
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut failed = false;
+    for pkg_name in &args.operands {
+        let template = match template::load(distdir, pkg_name) {
+            Ok(Some(template)) => template,
+            Ok(None) => {
+                cli::error(format!(
+                    "No template for {} in {}",
+                    pkg_name,
+                    distdir.display()
+                ));
+                failed = true;
+                continue;
+            }
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+                continue;
+            }
+        };
+        // A subpackage's directory is a symlink, which git cannot stage
+        // through, and the updates list names the main package, so work on
+        // the main package
+        let main = if template.pkgname.is_empty() {
+            pkg_name.as_str()
+        } else {
+            template.pkgname.as_str()
+        };
+        let new_version = match (&to, all_updates.0.get(main)) {
+            (Some(to), _) => to.clone(),
+            (None, Some(update)) => config.mangled(main, &update.new_version),
+            (None, None) => {
+                cli::error(format!("No update is known for {}", main));
+                failed = true;
+                continue;
+            }
+        };
+        let fetcher = Some(&fetcher).filter(|_| checksums);
+        let result = bump(
+            distdir,
+            main,
+            &template,
+            &new_version,
+            fetcher,
+            commit.as_ref(),
+            lint,
+        );
+        if let Err(e) = result.await {
+            cli::error(e);
+            failed = true;
+        }
+        // The next template is read from the base branch
+        let switched = commit
+            .as_ref()
+            .filter(|c| c.worktree_dir.is_none() && args.operands.len() > 1);
+        if let Some(commit) = switched {
+            if let Err(e) = git::run(distdir, &["switch", &commit.base]) {
+                cli::error(e);
+                return 1;
+            }
+        }
+    }
+
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+// With --commit, how the bumps are committed
+struct Commit {
+    // The branch the bump branches are created from
+    base: String,
+    sign: bool,
+    // With --worktree, the directory the branches are checked out in, each
+    // in a worktree of its own, instead of in the checkout itself
+    worktree_dir: Option<PathBuf>,
+}
+
+// Set the version of the template of package `main` to `new_version`, reset
+// its revision and stage the change with git. With a fetcher, the new
+// distfiles are downloaded to update the checksums, like xgensum does; the
+// template is left alone if any of them cannot be downloaded. With `commit`,
+// the change is committed on a new branch `<main>-<new_version>`, left
+// checked out in the checkout or in a new worktree. With `lint`, the new
+// template is checked with xlint last, and the problems found make it fail.
+async fn bump(
+    distdir: &Path,
+    main: &str,
+    template: &Template,
+    new_version: &str,
+    fetcher: Option<&Fetcher>,
+    commit: Option<&Commit>,
+    lint: bool,
+) -> Result<(), String> {
+    if version::compare(&template.version, new_version) != Ordering::Less {
+        println!("{} is already at {}", main, template.version);
+        return Ok(());
+    }
+
+    let path = template::path(distdir, main);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let text = template::set_field(&text, "version", new_version)
+        .and_then(|text| template::set_field(&text, "revision", "1"))
+        .ok_or_else(|| format!("{} has no version or revision", path.display()))?;
+    let text = match fetcher {
+        Some(fetcher) => update_checksums(fetcher, &text).await?,
+        None => text,
+    };
+    let branch = format!("{}-{}", main, new_version);
+    // The checkout the change is made in
+    let tree = match commit {
+        Some(Commit {
+            base,
+            worktree_dir: Some(dir),
+            ..
+        }) => {
+            let worktree = dir.join(&branch);
+            let worktree_arg = worktree.to_string_lossy();
+            git::run(
+                distdir,
+                &["worktree", "add", "-b", &branch, &worktree_arg, base],
+            )
+            .map_err(|e| e.to_string())?;
+            worktree
+        }
+        Some(_) => {
+            git::run(distdir, &["switch", "--create", &branch]).map_err(|e| e.to_string())?;
+            distdir.to_path_buf()
+        }
+        None => distdir.to_path_buf(),
+    };
+    let path = template::path(&tree, main);
+    std::fs::write(&path, text)
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+
+    let relative = format!("srcpkgs/{}/template", main);
+    git::run(&tree, &["add", "--", &relative]).map_err(|e| e.to_string())?;
+    if let Some(commit) = commit {
+        // Only the template is committed, whatever else is staged
+        let message = format!("{}: update to {}.", main, new_version);
+        let mut args = vec!["commit", "--quiet", "--message", &message];
+        if commit.sign {
+            args.push("--gpg-sign");
+        }
+        args.extend(["--", relative.as_str()]);
+        git::run(&tree, &args).map_err(|e| e.to_string())?;
+        println!(
+            "{}: {} -> {} (committed on {})",
+            main, template.version, new_version, branch
+        );
+        if tree != distdir {
+            println!("  in {}", tree.display());
+        }
+    } else {
+        println!("{}: {} -> {}", main, template.version, new_version);
+    }
+
+    if lint {
+        let problems = xlint(&tree, &relative)?;
+        if !problems.is_empty() {
+            for problem in &problems {
+                eprintln!("  {}", problem);
+            }
+            return Err(format!("xlint found problems in {}", relative));
+        }
+    }
+
+    // Only a template that passed the checks counts as bumped
+    super::record_status(main, Status::Bumped, new_version, None);
+    Ok(())
+}
+
+// Check the template at `relative` in the checkout `tree` with xtools' xlint,
+// returning the problems it reports
+fn xlint(tree: &Path, relative: &str) -> Result<Vec<String>, String> {
+    let output = Command::new("xlint")
+        .arg(relative)
+        .current_dir(tree)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Could not run xlint: {}", e))?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(String::from)
+        .collect())
+}
+
+// Whether `program` can be found in PATH
+fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+// Download the distfiles of the template `text` and set its `checksum=` to
+// their sha256 sums
+async fn update_checksums(fetcher: &Fetcher, text: &str) -> Result<String, String> {
+    let distfiles = template::parse(text).distfiles;
+    if distfiles.is_empty() {
+        return Ok(text.to_string());
+    }
+    let mut sums = Vec::new();
+    for distfile in &distfiles {
+        // A distfile may be saved under another name, given after `>`
+        let url = distfile.split('>').next().unwrap_or(distfile);
+        sums.push(sha256(fetcher, url).await?);
+    }
+    // Several checksums go on lines of their own, like xgensum writes them
+    template::set_field(text, "checksum", &sums.join("\n "))
+        .ok_or_else(|| "the template has distfiles but no checksum".to_string())
+}
+
+// Download `url` and return its sha256 sum, showing the progress on stderr
+async fn sha256(fetcher: &Fetcher, url: &str) -> Result<String, String> {
+    let name = url.rsplit('/').next().unwrap_or(url);
+    let interactive = std::io::stderr().is_terminal();
+    let mut sha = Sha256::new();
+    let mut received = 0;
+    let result = fetcher
+        .download(url, |data, total| {
+            sha.update(data);
+            received += data.len() as u64;
+            if interactive {
+                let total = total.map_or(String::new(), |t| format!(" of {}", kib(t)));
+                eprint!("\r\x1b[K{}: {}{}", name, kib(received), total);
+            }
+        })
+        .await;
+    if interactive {
+        eprint!("\r\x1b[K");
+    }
+    match result {
+        Ok(()) => {
+            eprintln!("{}: {}", name, kib(received));
+            Ok(sha.hex_digest())
+        }
+        Err(Error::Status { status, .. }) if status == StatusCode::NOT_FOUND => Err(format!(
+            "{} does not exist (yet?), the release may not be published",
+            url
+        )),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn kib(bytes: u64) -> String {
+    format!("{} KiB", bytes.div_ceil(1024))
+}
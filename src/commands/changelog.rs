@@ -0,0 +1,157 @@
+// `vupdate changelog <PKG>...`: show what changed upstream between the
+// packaged version and the new one, from the release notes or the commits of
+// projects on GitHub and GitLab
+
+use crate::cli::{self, Args};
+use colored::Colorize;
+use vupdate::changelog::{self, Changelog, Forge};
+use vupdate::config::Config;
+use vupdate::fetch::Fetcher;
+use vupdate::parse::PackageUpdate;
+use vupdate::template::{self, Template};
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    if args.operands.is_empty() {
+        cli::usage_error(cli::CliError::MissingArgument("<PKG>"));
+    }
+    if config.fetch.offline {
+        cli::error("The changelog cannot be fetched offline");
+        return 1;
+    }
+    let fetcher = match Fetcher::new(config.fetch.clone()) {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    let all_updates = match fetcher.all_updates().await {
+        Ok(updates) => updates,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    let token = match config.github_token.token() {
+        Ok(token) => token,
+        Err(e) => {
+            cli::error(e);
+            None
+        }
+    };
+
+    let mut failed = false;
+    for (i, pkg_name) in args.operands.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let update = match all_updates.0.get(pkg_name) {
+            Some(update) => update,
+            None => {
+                cli::error(format!("No update known for {}", pkg_name));
+                failed = true;
+                continue;
+            }
+        };
+
+        let forge = match forge(config, &fetcher, pkg_name, update).await {
+            Some(forge) => forge,
+            None => {
+                cli::error(format!(
+                    "{} is not hosted on GitHub or GitLab, no changelog to fetch",
+                    pkg_name
+                ));
+                failed = true;
+                continue;
+            }
+        };
+
+        println!(
+            "{} {} -> {} ({})",
+            pkg_name.bold(),
+            update.current_version,
+            update.new_version,
+            forge.display()
+        );
+        let result = changelog::changelog(
+            fetcher.client(),
+            token.clone(),
+            &forge,
+            &update.current_version,
+            &update.new_version,
+        )
+        .await;
+        match result {
+            Ok(Some(Changelog::Releases(releases))) => {
+                for release in releases {
+                    let mut heading = release.tag.clone();
+                    if !release.name.is_empty() && release.name != release.tag {
+                        heading.push_str(&format!(": {}", release.name));
+                    }
+                    if let Some(date) = &release.date {
+                        heading.push_str(&format!(" ({})", date));
+                    }
+                    println!("\n{}", heading.bold());
+                    let body = release.body.trim();
+                    if body.is_empty() {
+                        println!("  {}", "No release notes".dimmed());
+                    }
+                    for line in body.lines() {
+                        println!("  {}", line.trim_end());
+                    }
+                    println!("  {}", release.url.dimmed());
+                }
+            }
+            Ok(Some(Changelog::Commits { commits, url })) => {
+                println!("\n{}", format!("{} commits:", commits.len()).bold());
+                for commit in &commits {
+                    println!("  {} {}", commit.id.yellow(), commit.title);
+                }
+                println!("  {}", url.dimmed());
+            }
+            Ok(None) => {
+                println!("No releases or tags found for these versions");
+            }
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+// Where the project behind `pkg_name` is hosted, if on GitHub or GitLab. The
+// URL the version was found at is the likeliest to name the project, then the
+// template's sources and homepage.
+pub async fn forge(
+    config: &Config,
+    fetcher: &Fetcher,
+    pkg_name: &str,
+    update: &PackageUpdate,
+) -> Option<Forge> {
+    if let Some(forge) = update.url.as_deref().and_then(Forge::from_url) {
+        return Some(forge);
+    }
+    let template = match &config.distdir {
+        Some(distdir) => template::load(distdir, pkg_name).ok().flatten(),
+        None => fetcher.master_template(pkg_name).await.ok().flatten(),
+    };
+    template
+        .iter()
+        .flat_map(urls)
+        .find_map(|url| Forge::from_url(url))
+}
+
+// The URLs in a template that may point at the project
+fn urls(template: &Template) -> Vec<&String> {
+    template
+        .distfiles
+        .iter()
+        .chain(std::iter::once(&template.homepage))
+        .collect()
+}
@@ -0,0 +1,1663 @@
+// `vupdate check [PATTERN]...`: list the available updates
+
+use crate::cli::{self, usage_error, Args};
+use futures_util::future::join_all;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use vupdate::config::{Config, Section};
+use vupdate::fetch::Fetcher;
+use vupdate::github::PullRequestState;
+use vupdate::history::{self, Recorded, Run};
+use vupdate::ignore::IgnoreList;
+use vupdate::notes::NoteStore;
+use vupdate::notify::{self, Notice};
+use vupdate::parse::{PackageUpdate, UpdateMap};
+use vupdate::status::{Entry, Status, StatusStore};
+use vupdate::xbps::{self, read_pkgdb, InstalledPackage};
+use vupdate::{
+    builds, date, duration, eol, github, osv, output, pattern, popcorn, repology, template,
+    upstream, version, watchlist,
+};
+
+// Exit codes of the `check` command, for scripts that want to react to new
+// updates. Errors take precedence over reporting updates.
+const EXIT_NO_UPDATES: i32 = 0;
+const EXIT_ERROR: i32 = 1;
+const EXIT_UPDATES: i32 = 10;
+
+// The sections listing updates found upstream, as opposed to builds
+const UPSTREAM_SECTIONS: [Section; 3] = [Section::Maintainer, Section::Installed, Section::Watched];
+
+// How many packages are looked up at once in each of the other sources, or
+// on GitHub
+const SOURCE_REQUESTS: usize = 8;
+
+// List the available updates, returning the exit code
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let format = if args.flag("print0") {
+        output::Format::Names('\0')
+    } else if args.flag("quiet") || args.flag("names-only") {
+        output::Format::Names('\n')
+    } else {
+        match (args.value("format"), args.value("output")) {
+            (Some(template), _) => match output::Template::parse(&template) {
+                Ok(template) => output::Format::Template(template),
+                Err(e) => usage_error(cli::CliError::Invalid("format", e)),
+            },
+            (None, Some(name)) => output::Format::from_name(&name)
+                .unwrap_or_else(|| usage_error(cli::CliError::invalid_value("output", name))),
+            (None, None) => output::Format::Text,
+        }
+    };
+
+    let sort = sort_key(args);
+
+    let (sections, failed) = match updates(config, args, true).await {
+        Some(found) => found,
+        None => return EXIT_ERROR,
+    };
+    let sections: Vec<(Section, &UpdateMap)> = sections
+        .iter()
+        .map(|(section, updates)| (*section, updates))
+        .collect();
+    print!("{}", output::render(&sections, &format, sort));
+
+    if failed {
+        EXIT_ERROR
+    } else if sections.iter().any(|(_, updates)| !updates.0.is_empty()) {
+        EXIT_UPDATES
+    } else {
+        EXIT_NO_UPDATES
+    }
+}
+
+// The order of the updates asked for with --sort
+pub fn sort_key(args: &Args) -> output::SortKey {
+    match args.value("sort") {
+        Some(name) => output::SortKey::from_name(&name)
+            .unwrap_or_else(|| usage_error(cli::CliError::invalid_value("sort", name))),
+        None => output::SortKey::Name,
+    }
+}
+
+// The updates to report by section, narrowed down by the command line, and
+// whether anything failed on the way. `None` if nothing could be fetched at
+// all. Errors are reported as they happen. With `record`, the run is added to
+// the history, as `check` and `watch` do; the other commands only read it.
+pub async fn updates(
+    config: &Config,
+    args: &Args,
+    record: bool,
+) -> Option<(Vec<(Section, UpdateMap)>, bool)> {
+    let filters = Filters::new(args);
+    let fetcher = match Fetcher::new(config.fetch.clone()) {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            cli::error(e);
+            return None;
+        }
+    };
+
+    let mut failed = false;
+    let watchlist = if config.shows(Section::Watched) {
+        watchlist::load().unwrap_or_else(|e| {
+            cli::error(format!("Could not read the watchlist: {}", e));
+            failed = true;
+            HashSet::new()
+        })
+    } else {
+        HashSet::new()
+    };
+    let (maintainer_updates, all_updates, fetch_failed) =
+        fetch_updates(config, &fetcher, &watchlist).await;
+    failed |= fetch_failed;
+
+    let shown = Shown::new(config, args, &all_updates);
+    let pkgdb = if shown.needs_pkgdb() {
+        read_pkgdb(&config.pkgdb).unwrap_or_else(|e| {
+            cli::error(e);
+            failed = true;
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let installed = Installed::new(config, args, &pkgdb);
+
+    let (mut sections, build_failed) = build_sections(
+        config,
+        &shown,
+        maintainer_updates,
+        &all_updates,
+        &watchlist,
+        &installed,
+    );
+    failed |= build_failed;
+
+    // Packages with version checks of their own in the configuration
+    if !config.upstream.is_empty() {
+        let installed: Vec<&InstalledPackage> = pkgdb.iter().filter(|_| shown.installed).collect();
+        if upstream_checks(config, &fetcher, &mut sections, &installed).await {
+            failed = true;
+        }
+    }
+
+    let (history, history_failed) = record_history(config, args, record, failed, &mut sections);
+    failed |= history_failed;
+
+    let today = date::today();
+    let ignored = match IgnoreList::load() {
+        Ok(ignored) => Some(ignored),
+        Err(e) => {
+            cli::error(format!("Could not read the ignore file: {}", e));
+            failed = true;
+            None
+        }
+    };
+    if history.recorded
+        && notify_new(
+            config,
+            &fetcher,
+            &sections,
+            history.runs.last(),
+            ignored.as_ref(),
+            &today,
+        )
+        .await
+    {
+        failed = true;
+    }
+
+    narrow(
+        args,
+        &filters,
+        &mut sections,
+        &installed,
+        &watchlist,
+        &history,
+        ignored.as_ref(),
+    );
+    if annotate(config, args, &fetcher, &mut sections).await {
+        failed = true;
+    }
+
+    if args.flag("orphans-only") {
+        keep_orphans(&mut sections);
+    }
+
+    Some((Vec::from(sections), failed))
+}
+
+// What the command line narrows the updates down to, checked before anything
+// is fetched
+struct Filters {
+    patterns: pattern::Patterns,
+    since: Option<String>,
+    min_level: Option<version::Level>,
+    min_age: Option<std::time::Duration>,
+}
+
+impl Filters {
+    fn new(args: &Args) -> Filters {
+        let patterns = pattern::Patterns::new(&args.operands, args.flag("regex"))
+            .unwrap_or_else(|e| usage_error(cli::CliError::Invalid("regex", e.to_string())));
+
+        let since = args.value("since");
+        if let Some(since) = &since {
+            if !date::is_valid(since) {
+                usage_error(cli::CliError::invalid_value("since", since.clone()));
+            }
+        }
+
+        let min_level = args.value("min-level").map(|name| {
+            version::Level::from_name(&name)
+                .unwrap_or_else(|| usage_error(cli::CliError::invalid_value("min-level", name)))
+        });
+
+        let min_age = args.value("min-age").map(|age| {
+            duration::parse(&age)
+                .unwrap_or_else(|| usage_error(cli::CliError::invalid_value("min-age", age)))
+        });
+
+        Filters {
+            patterns,
+            since,
+            min_level,
+            min_age,
+        }
+    }
+}
+
+// Fetch the updates of the configured emails, merged into one map, and the
+// full updates list if the installed or watched packages need it, returning
+// both and whether anything failed
+async fn fetch_updates(
+    config: &Config,
+    fetcher: &Fetcher,
+    watchlist: &HashSet<String>,
+) -> (UpdateMap, UpdateMap, bool) {
+    // Without a configured email there is no maintainer section to show
+    let emails: &[String] = if config.shows(Section::Maintainer) {
+        &config.emails
+    } else {
+        &[]
+    };
+    let maintainer_fetch = join_all(emails.iter().map(|email| fetcher.maintainer_updates(email)));
+    // The full updates list is needed for both the installed and the watched
+    // packages
+    let installed_fetch = async {
+        if config.shows(Section::Installed) || !watchlist.is_empty() {
+            Some(fetcher.all_updates().await)
+        } else {
+            None
+        }
+    };
+
+    let (maintainer_updates_results, installed_updates_result) =
+        tokio::join!(maintainer_fetch, installed_fetch);
+    super::offline_notice(fetcher);
+
+    let mut failed = false;
+
+    // Merge the updates for all emails into one map, attributing each update
+    // to its maintainer if there is more than one
+    let mut maintainer_updates = UpdateMap::new();
+    for (email, result) in emails.iter().zip(maintainer_updates_results) {
+        match result {
+            Ok(mut updates) => {
+                if emails.len() > 1 {
+                    for update in updates.0.values_mut() {
+                        update.maintainer = Some(email.clone());
+                    }
+                }
+                maintainer_updates.extend(updates);
+            }
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+
+    let all_updates = match installed_updates_result {
+        Some(Ok(updates)) => updates,
+        Some(Err(e)) => {
+            cli::error(e);
+            failed = true;
+            UpdateMap::new()
+        }
+        None => UpdateMap::new(),
+    };
+
+    (maintainer_updates, all_updates, failed)
+}
+
+// The sections and columns shown besides the maintainer's and the watched
+// ones, which all need the package database
+struct Shown {
+    installed: bool,
+    pending: bool,
+    unbuilt: bool,
+    impact: bool,
+    describe: bool,
+}
+
+impl Shown {
+    fn new(config: &Config, args: &Args, all_updates: &UpdateMap) -> Shown {
+        Shown {
+            installed: config.shows(Section::Installed) && !all_updates.0.is_empty(),
+            pending: config.shows(Section::Pending) || args.flag("pending"),
+            unbuilt: config.shows(Section::Unbuilt) && config.distdir.is_some(),
+            impact: args.flag("impact") || args.value("sort").as_deref() == Some("impact"),
+            describe: args.flag("describe"),
+        }
+    }
+
+    fn needs_pkgdb(&self) -> bool {
+        self.installed || self.pending || self.unbuilt || self.impact || self.describe
+    }
+}
+
+// The package database, as the sections see it
+struct Installed<'a> {
+    pkgdb: &'a [InstalledPackage],
+    // The packages whose updates are listed: the explicitly installed ones,
+    // or all of them with --all-installed
+    names: HashSet<&'a str>,
+    // Their source packages
+    sources: HashSet<String>,
+    // The source packages of every installed package
+    every_source: HashSet<String>,
+    // Packages put on hold with `xbps-pkgdb -m hold`
+    held: HashSet<&'a str>,
+}
+
+impl<'a> Installed<'a> {
+    fn new(config: &Config, args: &Args, pkgdb: &'a [InstalledPackage]) -> Installed<'a> {
+        let all_installed = args.flag("all-installed");
+        let names: HashSet<&str> = pkgdb
+            .iter()
+            .filter(|pkg| all_installed || !pkg.automatic)
+            .map(|pkg| pkg.name.as_str())
+            .collect();
+        let sources = pkgdb
+            .iter()
+            .filter(|pkg| names.contains(pkg.name.as_str()))
+            .map(|pkg| source_of(config, pkg))
+            .collect();
+        Installed {
+            pkgdb,
+            names,
+            sources,
+            every_source: pkgdb.iter().map(|pkg| source_of(config, pkg)).collect(),
+            held: pkgdb
+                .iter()
+                .filter(|pkg| pkg.hold)
+                .map(|pkg| pkg.name.as_str())
+                .collect(),
+        }
+    }
+}
+
+// updates.txt only lists source packages, so installed subpackages such as
+// `libfoo-devel` count as their source package
+fn source_of(config: &Config, pkg: &InstalledPackage) -> String {
+    pkg.sourcepkg
+        .clone()
+        .or_else(|| {
+            let distdir = config.distdir.as_deref()?;
+            template::source_package(distdir, &pkg.name)
+        })
+        .unwrap_or_else(|| pkg.name.clone())
+}
+
+// Sort the fetched updates into the sections, add the pending and unbuilt
+// ones, and rewrite the versions the configuration says to, returning the
+// sections and whether anything failed
+fn build_sections(
+    config: &Config,
+    shown: &Shown,
+    maintainer_updates: UpdateMap,
+    all_updates: &UpdateMap,
+    watchlist: &HashSet<String>,
+    installed: &Installed,
+) -> ([(Section, UpdateMap); 5], bool) {
+    let mut failed = false;
+    let mut maintainer_updates = maintainer_updates;
+
+    // Only keep updates for packages that are: a) Installed, b) Not being
+    // maintained by me. The history records the updates of all installed
+    // packages, whatever --all-installed says, and they are narrowed down
+    // once it is recorded.
+    let mut installed_updates = UpdateMap::new();
+    if shown.installed {
+        for (k, v) in &all_updates.0 {
+            if installed.every_source.contains(k) && !maintainer_updates.0.contains_key(k) {
+                installed_updates.0.insert(k.clone(), v.clone());
+            }
+        }
+    }
+
+    // Binary updates waiting in the repository, shown with revisions since a
+    // revision bump is an update too
+    let mut pending_updates = UpdateMap::new();
+    if shown.pending {
+        match xbps::pending_updates() {
+            Ok(pending) => {
+                for (name, pkgver) in pending {
+                    let current = installed.pkgdb.iter().find(|pkg| pkg.name == name);
+                    let update = PackageUpdate {
+                        current_version: current.map_or("", |pkg| pkg.version()).to_string(),
+                        new_version: pkgver[name.len() + 1..].to_string(),
+                        url: None,
+                        maintainer: None,
+                        tags: Vec::new(),
+                        first_seen: None,
+                        version_seen: None,
+                        impact: None,
+                        installs: None,
+                        description: None,
+                        note: None,
+                    };
+                    pending_updates.insert(name, update);
+                }
+            }
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+
+    // Installed packages whose template in the local checkout was bumped but
+    // not built yet
+    let mut unbuilt_updates = UpdateMap::new();
+    if let (true, Some(distdir)) = (
+        shown.unbuilt && !installed.names.is_empty(),
+        &config.distdir,
+    ) {
+        match xbps::outdated_templates(distdir, installed.names.iter().copied()) {
+            Ok(outdated) => {
+                for (name, repo, template) in outdated {
+                    let update = PackageUpdate {
+                        current_version: repo.unwrap_or_default(),
+                        new_version: template,
+                        url: None,
+                        maintainer: None,
+                        tags: Vec::new(),
+                        first_seen: None,
+                        version_seen: None,
+                        impact: None,
+                        installs: None,
+                        description: None,
+                        note: None,
+                    };
+                    unbuilt_updates.insert(name, update);
+                }
+            }
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+
+    // Watched packages that are not already listed in another section
+    let mut watched_updates = UpdateMap::new();
+    for (k, v) in &all_updates.0 {
+        if watchlist.contains(k)
+            && !maintainer_updates.0.contains_key(k)
+            && !installed_updates.0.contains_key(k)
+        {
+            watched_updates.0.insert(k.clone(), v.clone());
+        }
+    }
+
+    // Many packages depending on one make its update a big rebuild, or a
+    // widespread breakage if the ABI changes
+    if shown.impact {
+        let counts = xbps::reverse_dependencies(installed.pkgdb, |pkg| source_of(config, pkg));
+        for updates in [
+            &mut maintainer_updates,
+            &mut installed_updates,
+            &mut watched_updates,
+        ] {
+            for (k, v) in updates.0.iter_mut() {
+                v.impact = Some(counts.get(k).copied().unwrap_or(0));
+            }
+        }
+    }
+
+    let mut sections = [
+        (Section::Maintainer, maintainer_updates),
+        (Section::Installed, installed_updates),
+        (Section::Watched, watched_updates),
+        (Section::Pending, pending_updates),
+        (Section::Unbuilt, unbuilt_updates),
+    ];
+
+    // Names such as `libuv` and `libunibreak` are easy to mix up. Installed
+    // packages have their description in the package database, the others
+    // get it from their template later on.
+    if shown.describe {
+        let descriptions: HashMap<&str, &String> = installed
+            .pkgdb
+            .iter()
+            .filter_map(|pkg| Some((pkg.name.as_str(), pkg.short_desc.as_ref()?)))
+            .collect();
+        for (_, updates) in sections.iter_mut() {
+            for (k, v) in updates.0.iter_mut() {
+                v.description = descriptions.get(k.as_str()).map(|d| d.to_string());
+            }
+        }
+    }
+
+    // Unconventional upstream versions are rewritten by the configured rules,
+    // after which some of the updates turn out not to be updates at all
+    for (_, updates) in sections
+        .iter_mut()
+        .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+    {
+        updates.0.retain(|k, v| {
+            if !config.mangle.contains_key(k) {
+                return true;
+            }
+            v.new_version = config.mangled(k, &v.new_version);
+            version::compare(&v.new_version, &v.current_version) == Ordering::Greater
+        });
+    }
+
+    (sections, failed)
+}
+
+// The recorded runs, this one among them if it was recorded
+struct History {
+    runs: Vec<Run>,
+    this_run: Run,
+    recorded: bool,
+}
+
+// Tag the updates that are new since the last recorded run and, with
+// `record`, record this one unless something already `failed`, returning the
+// history and whether reading or recording it failed
+fn record_history(
+    config: &Config,
+    args: &Args,
+    record: bool,
+    failed: bool,
+    sections: &mut [(Section, UpdateMap)],
+) -> (History, bool) {
+    let mut history_failed = false;
+
+    // Updates missing from the previous run, or to another version, are new
+    let runs = history::load().unwrap_or_else(|e| {
+        cli::error(format!("Could not read the history: {}", e));
+        history_failed = true;
+        Vec::new()
+    });
+    if let Some(last) = runs.last() {
+        for (_, updates) in sections
+            .iter_mut()
+            .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+        {
+            for (k, v) in updates.0.iter_mut() {
+                if last
+                    .updates
+                    .get(k)
+                    .is_none_or(|r| r.new_version != v.new_version)
+                {
+                    v.tags.push("NEW".to_string());
+                }
+            }
+        }
+    }
+
+    // Remember what this run found, before the command line narrows it down.
+    // Runs that could not fetch everything, or read an old snapshot with
+    // --from-file, would only make the history misleading.
+    let this_run = current_run(sections);
+    let mut recorded = false;
+    // Nor does another maintainer's queue belong in one's own history
+    let history = record && !args.flag("no-history") && args.values("maintainer").is_empty();
+    if history && !failed && !history_failed && config.fetch.from_file.is_none() {
+        match history::record(&this_run, runs.last()) {
+            Ok(()) => recorded = true,
+            Err(e) => {
+                cli::error(format!("Could not record the history: {}", e));
+                history_failed = true;
+            }
+        }
+    }
+
+    let history = History {
+        runs,
+        this_run,
+        recorded,
+    };
+    (history, history_failed)
+}
+
+// Tell the configured services about the updates that are new since the
+// `last` recorded run, returning whether any of them failed. Only recorded
+// runs do, or the next run would send the same updates again. As the run is
+// recorded whatever the command line, so are they, or the updates it hides
+// would never be told about; only ignored packages are left out.
+async fn notify_new(
+    config: &Config,
+    fetcher: &Fetcher,
+    sections: &[(Section, UpdateMap)],
+    last: Option<&Run>,
+    ignored: Option<&IgnoreList>,
+    today: &str,
+) -> bool {
+    if config.notify.is_empty() && config.on_new_update.is_none() {
+        return false;
+    }
+    let notices: Vec<Notice> = sections
+        .iter()
+        .flat_map(|(section, updates)| {
+            updates
+                .sorted()
+                .into_iter()
+                .filter(|(_, v)| v.tags.iter().any(|t| t == "NEW"))
+                .filter(|(k, v)| {
+                    ignored.is_none_or(|ignored| !ignored.hides(k, &v.new_version, today))
+                })
+                .map(move |(k, v)| Notice {
+                    pkg_name: k,
+                    section: *section,
+                    update: v,
+                    previous: last
+                        .and_then(|last| last.updates.get(k))
+                        .map(|r| r.new_version.as_str()),
+                })
+        })
+        .collect();
+
+    let mut failed = false;
+    if !notices.is_empty() {
+        let sent = join_all(
+            config
+                .notify
+                .iter()
+                .map(|notifier| notifier.send(fetcher.client(), &notices)),
+        )
+        .await;
+        for e in sent.into_iter().filter_map(Result::err) {
+            cli::error(format!("Could not send a notification: {}", e));
+            failed = true;
+        }
+    }
+    if let Some(command) = &config.on_new_update {
+        for notice in &notices {
+            if let Err(e) = notify::run_hook(command, notice) {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+    failed
+}
+
+// Narrow the updates down to those the command line and the configuration
+// ask for. This only needs what is at hand, so it comes before the
+// annotations that take a request per update.
+fn narrow(
+    args: &Args,
+    filters: &Filters,
+    sections: &mut [(Section, UpdateMap)],
+    installed: &Installed,
+    watchlist: &HashSet<String>,
+    history: &History,
+    ignored: Option<&IgnoreList>,
+) {
+    // Packages installed as dependencies are only listed with --all-installed.
+    // The watched ones among them are listed as watched instead.
+    if !args.flag("all-installed") {
+        let mut dependencies = Vec::new();
+        if let Some((_, installed_updates)) = sections
+            .iter_mut()
+            .find(|(section, _)| *section == Section::Installed)
+        {
+            installed_updates.0.retain(|k, v| {
+                let explicit =
+                    installed.sources.contains(k) || installed.names.contains(k.as_str());
+                if !explicit && watchlist.contains(k) {
+                    dependencies.push((k.clone(), v.clone()));
+                }
+                explicit
+            });
+        }
+        if let Some((_, watched_updates)) = sections
+            .iter_mut()
+            .find(|(section, _)| *section == Section::Watched)
+        {
+            watched_updates.0.extend(dependencies);
+        }
+    }
+
+    if args.flag("only-new") {
+        for (_, updates) in sections.iter_mut() {
+            updates.0.retain(|_, v| v.tags.iter().any(|t| t == "NEW"));
+        }
+    }
+
+    // How long each update has been waiting
+    let seen = history::first_seen(&history.runs, &history.this_run);
+    for (_, updates) in sections
+        .iter_mut()
+        .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+    {
+        for (k, v) in updates.0.iter_mut() {
+            v.first_seen = seen.get(k).map(|seen| seen.package);
+            v.version_seen = seen.get(k).map(|seen| seen.version);
+        }
+    }
+    if let Some(min_age) = filters.min_age {
+        let now = history.this_run.time;
+        for (_, updates) in sections.iter_mut() {
+            updates.0.retain(|_, v| {
+                v.first_seen
+                    .is_some_and(|first| now.saturating_sub(first) >= min_age.as_secs())
+            });
+        }
+    }
+
+    // Only keep the updates to versions that first appeared on or after the
+    // given date
+    if let Some(since) = &filters.since {
+        for (_, updates) in sections.iter_mut() {
+            updates.0.retain(|k, _| {
+                seen.get(k)
+                    .is_some_and(|seen| date::from_timestamp(seen.version) >= *since)
+            });
+        }
+    }
+
+    // Only keep packages matching the patterns given on the command line
+    for (_, updates) in sections.iter_mut() {
+        updates.0.retain(|k, _| filters.patterns.matches(k));
+    }
+
+    // Pre-release versions are hidden unless asked for
+    if !args.flag("include-prereleases") {
+        for (_, updates) in sections.iter_mut() {
+            updates
+                .0
+                .retain(|_, v| !version::is_prerelease(&v.new_version));
+        }
+    }
+
+    // Patch-level churn is hidden when only significant updates are wanted
+    if let Some(min_level) = filters.min_level {
+        for (_, updates) in sections.iter_mut() {
+            updates
+                .0
+                .retain(|_, v| version::level(&v.current_version, &v.new_version) >= min_level);
+        }
+    }
+
+    // Drop updates for ignored packages
+    if let Some(ignored) = ignored {
+        let today = date::today();
+        for (_, updates) in sections.iter_mut() {
+            updates
+                .0
+                .retain(|k, v| !ignored.hides(k, &v.new_version, &today));
+        }
+    }
+
+    // Held packages are deliberately frozen, so their updates are either
+    // marked or hidden
+    let skip_held = args.flag("skip-held");
+    for (_, updates) in sections.iter_mut() {
+        updates
+            .0
+            .retain(|k, _| !(skip_held && installed.held.contains(k.as_str())));
+        for (k, v) in updates.0.iter_mut() {
+            if installed.held.contains(k.as_str()) {
+                v.tags.push("held".to_string());
+            }
+        }
+    }
+}
+
+// Tag the remaining updates with what the local state, the templates and the
+// other services tell about them, dropping those the --hide and --only
+// options leave out, and returning whether anything failed. One by one, as
+// the later ones take a request per update.
+async fn annotate(
+    config: &Config,
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> bool {
+    let mut failed = tag_local_state(config, args, fetcher, sections).await;
+    let (template_cache, templates_failed) = read_templates(config, args, fetcher, sections).await;
+    failed |= templates_failed;
+    failed |= tag_pull_requests(config, args, fetcher, sections).await;
+    failed |= check_distfiles(config, args, fetcher, sections, &template_cache).await;
+    failed |= check_security(config, args, fetcher, sections).await;
+    failed |= check_eol(args, fetcher, sections).await;
+    failed |= compare_sources(config, fetcher, sections).await;
+    failed |= check_repology(config, args, fetcher, sections).await;
+    failed |= add_installs(args, fetcher, sections).await;
+    failed
+}
+
+// Tag the updates with what the binary repository, the local checkout and
+// vupdate's own files say about them, returning whether any of them could
+// not be read
+async fn tag_local_state(
+    config: &Config,
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> bool {
+    let mut failed = false;
+
+    // Installed packages whose new version the binary repository already
+    // ships only need `xbps-install`, not packaging work
+    if let Some((_, installed_updates)) = sections
+        .iter_mut()
+        .find(|(section, updates)| *section == Section::Installed && !updates.0.is_empty())
+    {
+        match xbps::repo_versions(installed_updates.0.keys().map(String::as_str)) {
+            Ok(repo_versions) => {
+                let packaged = |k: &String, v: &PackageUpdate| {
+                    repo_versions.get(k).is_some_and(|repo| {
+                        let upstream = repo.rsplit_once('_').map_or(repo.as_str(), |(v, _)| v);
+                        version::compare(upstream, &v.new_version) != Ordering::Less
+                    })
+                };
+                let hide_packaged = args.flag("hide-packaged");
+                installed_updates
+                    .0
+                    .retain(|k, v| !(hide_packaged && packaged(k, v)));
+                for (k, v) in installed_updates.0.iter_mut() {
+                    if packaged(k, v) {
+                        v.tags.push("packaged".to_string());
+                    }
+                }
+            }
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+
+    // Updates already applied to the templates of the local checkout only
+    // wait for a build or a pull request
+    if let Some(distdir) = &config.distdir {
+        for (_, updates) in sections
+            .iter_mut()
+            .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+        {
+            for (k, v) in updates.0.iter_mut() {
+                match template::load(distdir, k) {
+                    Ok(Some(template))
+                        if version::compare(&template.version, &v.new_version)
+                            != Ordering::Less =>
+                    {
+                        v.tags.push("applied".to_string())
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        cli::error(e);
+                        failed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether `vupdate build` managed to build the new version
+    match builds::load() {
+        Ok(builds) if !builds.is_empty() => {
+            for (_, updates) in sections
+                .iter_mut()
+                .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+            {
+                for (k, v) in updates.0.iter_mut() {
+                    match builds.get(k) {
+                        Some(build) if build.version == v.new_version => {
+                            let tag = if build.ok { "built" } else { "build failed" };
+                            v.tags.push(tag.to_string())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            cli::error(format!("Could not read the build results: {}", e));
+            failed = true;
+        }
+    }
+
+    // Where the work on each update stands, as tracked in the status file.
+    // Merged updates need no more attention and are left out.
+    match StatusStore::load() {
+        Ok(mut store) => {
+            if !config.fetch.offline {
+                if let Err(e) = detect_merges(config, fetcher, &mut store).await {
+                    cli::error(e);
+                    failed = true;
+                }
+            }
+            for (_, updates) in sections.iter_mut() {
+                updates.0.retain(|k, v| match store.get(k) {
+                    Some(entry) if entry.applies_to(&v.new_version) => {
+                        if entry.status == Status::Merged {
+                            return false;
+                        }
+                        v.tags.push(entry.status.name().to_string());
+                        true
+                    }
+                    _ => true,
+                });
+            }
+        }
+        Err(e) => {
+            cli::error(format!("Could not read the status file: {}", e));
+            failed = true;
+        }
+    }
+
+    // The user's notes, which apply whatever the version
+    match NoteStore::load() {
+        Ok(store) => {
+            for (_, updates) in sections.iter_mut() {
+                for (k, v) in updates.0.iter_mut() {
+                    v.note = store.get(k).map(|note| note.text.clone());
+                }
+            }
+        }
+        Err(e) => {
+            cli::error(format!("Could not read the notes file: {}", e));
+            failed = true;
+        }
+    }
+
+    failed
+}
+
+// Tag the updates with what their templates say, and with whether
+// void-packages' master branch already has them, returning the template texts
+// read, for the distfiles check, and whether anything failed
+async fn read_templates(
+    config: &Config,
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> (HashMap<String, Option<String>>, bool) {
+    // Installed packages are usually maintained by someone else, who should
+    // be asked before updating them. Restricted and nonfree packages are not
+    // built by the build servers and take another workflow. The templates come
+    // from the local checkout if there is one, otherwise from void-packages'
+    // master branch with --templates, as that is a request per update. They
+    // only add information, so one that cannot be read fails nothing.
+    // --orphans-only needs the maintainers of the installed packages anyway.
+    let read_templates = config.distdir.is_some() || args.flag("templates");
+    let orphans_only = args.flag("orphans-only");
+    let describe = args.flag("describe");
+    let mut template_cache: HashMap<String, Option<String>> = HashMap::new();
+    for (section, updates) in sections.iter_mut().filter(|(section, updates)| {
+        (read_templates || orphans_only && *section == Section::Installed)
+            && UPSTREAM_SECTIONS.contains(section)
+            && !updates.0.is_empty()
+    }) {
+        let names: Vec<String> = updates.0.keys().cloned().collect();
+        let texts = template_texts(config, fetcher, &names).await;
+        for (name, result) in names.iter().zip(texts) {
+            let text = match result {
+                Ok(text) => text,
+                Err(e) => {
+                    cli::warning(format!("{}: {}", name, e));
+                    continue;
+                }
+            };
+            template_cache.insert(name.clone(), text.clone());
+            let template = match text {
+                Some(text) => template::parse(&text),
+                None => continue,
+            };
+            let update = updates.0.get_mut(name).expect("names are keys");
+            if template.restricted {
+                update.tags.push("restricted".to_string());
+            }
+            if *section == Section::Installed && !template.maintainer.is_empty() {
+                if template.is_orphaned() {
+                    update.tags.push("orphan".to_string());
+                }
+                update.maintainer = Some(template.maintainer);
+            }
+            if !template.repository.is_empty() {
+                update.tags.push(template.repository);
+            }
+            if describe && update.description.is_none() && !template.short_desc.is_empty() {
+                update.description = Some(template.short_desc);
+            }
+        }
+    }
+
+    // void-updates runs once a day, so someone may have bumped the template
+    // on void-packages' master branch since. Without a checkout, the templates
+    // read above already come from there.
+    let mut failed = false;
+    let hide_merged = args.flag("hide-merged");
+    if hide_merged || args.flag("check-merged") {
+        let from_master = config.distdir.is_none();
+        for (_, updates) in sections
+            .iter_mut()
+            .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+        {
+            let names: Vec<String> = updates.0.keys().cloned().collect();
+            let mut texts = Vec::new();
+            for chunk in names.chunks(SOURCE_REQUESTS) {
+                let lookups = chunk.iter().map(|name| {
+                    let cached = template_cache.get(name).filter(|_| from_master);
+                    async move {
+                        match cached {
+                            Some(text) => Ok(text.clone()),
+                            None => fetcher.master_template_text(name).await,
+                        }
+                    }
+                });
+                texts.extend(join_all(lookups).await);
+            }
+            for (name, result) in names.iter().zip(texts) {
+                match result.map(|text| text.map(|text| template::parse(&text))) {
+                    Ok(Some(template)) => {
+                        let merged = updates.0.get(name).is_some_and(|update| {
+                            version::compare(&template.version, &update.new_version)
+                                != Ordering::Less
+                        });
+                        if merged && hide_merged {
+                            updates.0.remove(name);
+                        } else if let (true, Some(update)) = (merged, updates.0.get_mut(name)) {
+                            // The status file may already say so
+                            if !update.tags.iter().any(|t| t == "merged") {
+                                update.tags.push("merged".to_string());
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        cli::error(e);
+                        failed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    (template_cache, failed)
+}
+
+// Someone may already be working on an update, which is worth knowing before
+// starting on it. Like the distfiles check, this needs the network and is
+// skipped offline. Returns whether the pull requests could not be listed.
+async fn tag_pull_requests(
+    config: &Config,
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> bool {
+    if !args.flag("check-prs") || config.fetch.offline {
+        return false;
+    }
+    let prs = match config.github_token.token() {
+        Ok(token) => {
+            let github = github::Client::new(fetcher.client().clone(), token);
+            github.open_update_prs().await
+        }
+        Err(e) => Err(e),
+    };
+    match prs {
+        Ok(prs) => {
+            for (_, updates) in sections
+                .iter_mut()
+                .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+            {
+                for pr in &prs {
+                    let update = github::parse_update_title(&pr.title)
+                        .and_then(|(pkg_name, _)| updates.0.get_mut(pkg_name));
+                    if let Some(update) = update {
+                        update
+                            .tags
+                            .push(format!("PR #{} by {}", pr.number, pr.author));
+                    }
+                }
+            }
+            false
+        }
+        Err(e) => {
+            cli::error(e);
+            true
+        }
+    }
+}
+
+// Projects often tag a release before its tarballs are published, and such
+// an update cannot be packaged yet. The templates already read are taken
+// from `template_cache`. Returns whether any of the checks failed.
+async fn check_distfiles(
+    config: &Config,
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+    template_cache: &HashMap<String, Option<String>>,
+) -> bool {
+    let only_fetchable = args.flag("only-fetchable");
+    if !(only_fetchable || args.flag("check-distfiles")) || config.fetch.offline {
+        return false;
+    }
+    let mut failed = false;
+    for (_, updates) in sections
+        .iter_mut()
+        .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+    {
+        let names: Vec<String> = updates.0.keys().cloned().collect();
+        let missing: Vec<String> = names
+            .iter()
+            .filter(|name| !template_cache.contains_key(*name))
+            .cloned()
+            .collect();
+        let mut fetched = missing
+            .iter()
+            .zip(template_texts(config, fetcher, &missing).await)
+            .collect::<HashMap<_, _>>();
+        let texts: Vec<_> = names
+            .iter()
+            .map(|name| match template_cache.get(name) {
+                Some(text) => Ok(text.clone()),
+                None => fetched.remove(name).expect("missing ones are fetched"),
+            })
+            .collect();
+        let checks = names.iter().zip(texts).map(|(name, text)| {
+            let new_version = updates.0[name].new_version.clone();
+            async move {
+                // Without a template there is nothing to check
+                let text = match text? {
+                    Some(text) => text,
+                    None => return Ok(true),
+                };
+                for distfile in template::parse_at_version(&text, &new_version).distfiles {
+                    let url = distfile.split('>').next().unwrap_or(&distfile);
+                    if !fetcher.exists(url).await? {
+                        return Ok(false);
+                    }
+                }
+                Ok::<_, vupdate::Error>(true)
+            }
+        });
+        let checks = join_all(checks).await;
+        for (name, result) in names.iter().zip(checks) {
+            match result {
+                Ok(true) => {}
+                Ok(false) if only_fetchable => {
+                    updates.0.remove(name);
+                }
+                Ok(false) => {
+                    if let Some(update) = updates.0.get_mut(name) {
+                        update.tags.push("no-distfile".to_string());
+                    }
+                }
+                Err(e) => {
+                    cli::error(e);
+                    failed = true;
+                }
+            }
+        }
+    }
+    failed
+}
+
+// Updates fixing known vulnerabilities are the ones not to put off. Returns
+// whether they could not be looked up.
+async fn check_security(
+    config: &Config,
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> bool {
+    let mut failed = false;
+    let security_only = args.flag("security-only");
+    if (security_only || args.flag("check-security")) && !config.fetch.offline {
+        for (_, updates) in sections
+            .iter_mut()
+            .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+        {
+            let listed: Vec<(&str, &PackageUpdate)> = updates
+                .0
+                .iter()
+                .map(|(name, update)| (name.as_str(), update))
+                .collect();
+            let names: Vec<String> = listed.iter().map(|(name, _)| name.to_string()).collect();
+            match osv::fixed_vulnerabilities(fetcher.client(), &listed).await {
+                Ok(fixed) => {
+                    for (name, ids) in names.iter().zip(fixed) {
+                        let update = updates.0.get_mut(name).expect("names are keys");
+                        match ids.as_slice() {
+                            [] => {}
+                            [id] => update.tags.push(format!("fixes {}", id)),
+                            ids => update
+                                .tags
+                                .push(format!("fixes {} vulnerabilities", ids.len())),
+                        }
+                    }
+                }
+                Err(e) => {
+                    cli::error(e);
+                    failed = true;
+                }
+            }
+        }
+    }
+    if security_only {
+        for (_, updates) in sections.iter_mut() {
+            updates.0.retain(|_, v| v.is_security_fix());
+        }
+    }
+    failed
+}
+
+// A bump within a release branch that is no longer supported leaves the real
+// work, moving to a supported branch, still to do. Returns whether any of the
+// lifecycles could not be read.
+async fn check_eol(args: &Args, fetcher: &Fetcher, sections: &mut [(Section, UpdateMap)]) -> bool {
+    if !args.flag("check-eol") {
+        return false;
+    }
+    let mut failed = false;
+    let today = date::today();
+    for (_, updates) in sections
+        .iter_mut()
+        .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+    {
+        for (name, update) in updates.0.iter_mut() {
+            let product = match eol::product(name) {
+                Some(product) => product,
+                None => continue,
+            };
+            let lifecycle = match eol::lifecycle(fetcher, product).await {
+                Ok(Some(lifecycle)) => lifecycle,
+                Ok(None) => continue,
+                Err(e) => {
+                    cli::error(e);
+                    failed = true;
+                    continue;
+                }
+            };
+            let stuck = lifecycle
+                .eol(&update.current_version, &today)
+                .filter(|_| lifecycle.eol(&update.new_version, &today).is_some());
+            if let Some(branch) = stuck {
+                let mut tag = match &branch.date {
+                    Some(date) => format!("{} EOL since {}", branch.cycle, date),
+                    None => format!("{} EOL", branch.cycle),
+                };
+                if let Some(latest) = branch.latest.as_ref().filter(|l| **l != branch.cycle) {
+                    tag.push_str(&format!(", latest {}", latest));
+                }
+                update.tags.push(tag);
+            }
+        }
+    }
+    failed
+}
+
+// What the other sources say about the new versions: one agreeing with
+// void-updates makes an update more trustworthy, one disagreeing may point at
+// a misparsed version or at an even newer one. Returns whether any of them
+// failed.
+async fn compare_sources(
+    config: &Config,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> bool {
+    if config.sources.is_empty() || config.fetch.offline {
+        return false;
+    }
+    let mut failed = false;
+    let sources: Vec<_> = config
+        .sources
+        .iter()
+        .filter(|source| match source.available() {
+            Ok(()) => true,
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+                false
+            }
+        })
+        .collect();
+    for (_, updates) in sections
+        .iter_mut()
+        .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+    {
+        let names: Vec<String> = updates.0.keys().cloned().collect();
+        for source in &sources {
+            let mut versions = Vec::new();
+            // A few at a time, to go easy on the services
+            for chunk in names.chunks(SOURCE_REQUESTS) {
+                let lookups = chunk
+                    .iter()
+                    .map(|name| source.latest_version(fetcher, name));
+                versions.extend(join_all(lookups).await);
+            }
+            for (name, result) in names.iter().zip(versions) {
+                let update = updates.0.get_mut(name).expect("names are keys");
+                let result = result.map(|v| v.map(|v| config.mangled(name, &v)));
+                match result {
+                    Ok(Some(version)) if version == update.new_version => {
+                        if !update.tags.iter().any(|t| t == "confirmed") {
+                            update.tags.push("confirmed".to_string());
+                        }
+                    }
+                    Ok(Some(version)) => {
+                        update.tags.push(format!("{}: {}", source.name(), version))
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        cli::error(e);
+                        failed = true;
+                    }
+                }
+            }
+        }
+    }
+    failed
+}
+
+// An unusual version that no other distribution has may be a misreading of
+// void-updates, while a widely packaged one is likely sound. Returns whether
+// any of the lookups failed.
+async fn check_repology(
+    config: &Config,
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> bool {
+    if !args.flag("repology") || config.fetch.offline {
+        return false;
+    }
+    let mut failed = false;
+    let mut first = true;
+    for (_, updates) in sections
+        .iter_mut()
+        .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+    {
+        for (name, update) in updates.0.iter_mut() {
+            if !first {
+                tokio::time::sleep(repology::REQUEST_INTERVAL).await;
+            }
+            first = false;
+            match repology::distros_with(fetcher, name, &update.new_version).await {
+                Ok(Some(distros)) => update.tags.push(match distros.len() {
+                    1 => "in 1 distro".to_string(),
+                    n => format!("in {} distros", n),
+                }),
+                Ok(None) => {}
+                Err(e) => {
+                    cli::error(e);
+                    failed = true;
+                }
+            }
+        }
+    }
+    failed
+}
+
+// Bumps that many use are worth doing first. Returns whether the statistics
+// could not be fetched.
+async fn add_installs(
+    args: &Args,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+) -> bool {
+    if !args.flag("popcorn") && args.value("sort").as_deref() != Some("installs") {
+        return false;
+    }
+    match popcorn::latest(fetcher).await {
+        Ok(Some(popularity)) => {
+            for (_, updates) in sections.iter_mut() {
+                for (name, update) in updates.0.iter_mut() {
+                    update.installs = Some(popularity.installs(name));
+                }
+            }
+            false
+        }
+        Ok(None) => false,
+        Err(e) => {
+            cli::error(e);
+            true
+        }
+    }
+}
+
+// Orphaned packages one uses are good candidates for adoption, so keep only
+// the installed ones tagged by their template.
+fn keep_orphans(sections: &mut [(Section, UpdateMap)]) {
+    for (section, updates) in sections.iter_mut() {
+        if *section == Section::Installed {
+            updates
+                .0
+                .retain(|_, v| v.tags.iter().any(|t| t == "orphan"));
+        } else {
+            updates.0.clear();
+        }
+    }
+}
+
+// Run the version checks of the configuration for the packages they apply to,
+// returning whether any of them failed. A supplementing check tags the update
+// like the other sources do. An overriding one replaces void-updates' new
+// version, which drops the update if upstream has nothing newer, and adds the
+// updates void-updates missed for the packages in `installed`.
+async fn upstream_checks(
+    config: &Config,
+    fetcher: &Fetcher,
+    sections: &mut [(Section, UpdateMap)],
+    installed: &[&InstalledPackage],
+) -> bool {
+    let listed = |sections: &[(Section, UpdateMap)], name: &str| {
+        sections.iter().any(|(section, updates)| {
+            UPSTREAM_SECTIONS.contains(section) && updates.0.contains_key(name)
+        })
+    };
+    let names: Vec<&String> = config
+        .upstream
+        .iter()
+        .filter(|(name, check)| {
+            listed(sections, name)
+                || (check.mode == upstream::Mode::Override
+                    && installed.iter().any(|pkg| pkg.name == **name))
+        })
+        .map(|(name, _)| name)
+        .collect();
+    let mut versions = Vec::new();
+    for chunk in names.chunks(SOURCE_REQUESTS) {
+        let lookups = chunk
+            .iter()
+            .map(|name| config.upstream[*name].latest_version(fetcher, name));
+        versions.extend(join_all(lookups).await);
+    }
+
+    let mut failed = false;
+    for (name, result) in names.into_iter().zip(versions) {
+        let check = &config.upstream[name];
+        let version = match result {
+            Ok(Some(version)) => config.mangled(name, &version),
+            Ok(None) => continue,
+            Err(e) => {
+                cli::error(format!("{}: {}", name, e));
+                failed = true;
+                continue;
+            }
+        };
+        if check.mode == upstream::Mode::Supplement {
+            let update = sections
+                .iter_mut()
+                .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+                .find_map(|(_, updates)| updates.0.get_mut(name));
+            if let Some(update) = update {
+                let tag = if version == update.new_version {
+                    "confirmed".to_string()
+                } else {
+                    format!("{}: {}", check.kind.name(), version)
+                };
+                if !update.tags.contains(&tag) {
+                    update.tags.push(tag);
+                }
+            }
+            continue;
+        }
+
+        if listed(sections, name) {
+            for (_, updates) in sections
+                .iter_mut()
+                .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section))
+            {
+                let newer = match updates.0.get_mut(name) {
+                    Some(update)
+                        if version::compare(&version, &update.current_version)
+                            == Ordering::Greater =>
+                    {
+                        update.new_version = version.clone();
+                        update.url = Some(check.url.clone());
+                        true
+                    }
+                    Some(_) => false,
+                    None => continue,
+                };
+                if !newer {
+                    updates.0.remove(name);
+                }
+            }
+        } else if let Some(pkg) = installed.iter().find(|pkg| pkg.name == *name) {
+            let current = pkg.version().rsplit_once('_').map_or("", |(v, _)| v);
+            if version::compare(&version, current) == Ordering::Greater {
+                let update = PackageUpdate {
+                    current_version: current.to_string(),
+                    new_version: version,
+                    url: Some(check.url.clone()),
+                    maintainer: None,
+                    tags: Vec::new(),
+                    first_seen: None,
+                    version_seen: None,
+                    impact: None,
+                    installs: None,
+                    description: None,
+                    note: None,
+                };
+                if let Some((_, updates)) = sections
+                    .iter_mut()
+                    .find(|(section, _)| *section == Section::Installed)
+                {
+                    updates.insert(name.clone(), update);
+                }
+            }
+        }
+    }
+    failed
+}
+
+// This run's upstream updates, as recorded in the history
+fn current_run(sections: &[(Section, UpdateMap)]) -> Run {
+    let mut updates = BTreeMap::new();
+    // Binary updates only repeat upstream ones
+    let upstream = sections
+        .iter()
+        .filter(|(section, _)| UPSTREAM_SECTIONS.contains(section));
+    for (section, section_updates) in upstream {
+        for (name, update) in &section_updates.0 {
+            let recorded = Recorded {
+                section: section.name().to_string(),
+                current_version: update.current_version.clone(),
+                new_version: update.new_version.clone(),
+            };
+            updates.insert(name.clone(), recorded);
+        }
+    }
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    Run { time, updates }
+}
+
+// Move the packages whose pull request was merged to `merged` in the status
+// file. A closed pull request counts as merged when master has the version.
+async fn detect_merges(
+    config: &Config,
+    fetcher: &Fetcher,
+    store: &mut StatusStore,
+) -> Result<(), String> {
+    let filed: Vec<(Entry, u64)> = store
+        .entries()
+        .filter(|e| e.status == Status::PrFiled)
+        .filter_map(|e| Some((e.clone(), e.pr?)))
+        .collect();
+    if filed.is_empty() {
+        return Ok(());
+    }
+    let token = config.github_token.token().map_err(|e| e.to_string())?;
+    let github = github::Client::new(fetcher.client().clone(), token);
+    let merged = filed.iter().map(|(entry, pr)| {
+        let github = &github;
+        async move {
+            match github.pull_request_state(*pr).await? {
+                PullRequestState::Merged => Ok(true),
+                PullRequestState::Open => Ok(false),
+                PullRequestState::Closed => {
+                    let template = fetcher.master_template(&entry.pkg_name).await?;
+                    Ok(match (template, &entry.version) {
+                        (Some(template), Some(version)) => {
+                            version::compare(&template.version, version) != Ordering::Less
+                        }
+                        _ => false,
+                    })
+                }
+            }
+        }
+    });
+    let results: Vec<vupdate::error::Result<bool>> = join_all(merged).await;
+    let mut changed = false;
+    let mut errors = Vec::new();
+    for ((entry, _), result) in filed.into_iter().zip(results) {
+        match result {
+            Ok(true) => {
+                store.set(Entry {
+                    status: Status::Merged,
+                    date: date::today(),
+                    ..entry
+                });
+                changed = true;
+            }
+            Ok(false) => {}
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+    if changed {
+        store
+            .save()
+            .map_err(|e| format!("Could not write the status file: {}", e))?;
+    }
+    match errors.first() {
+        Some(e) => Err(e.clone()),
+        None => Ok(()),
+    }
+}
+
+// The texts of the templates building `names`, from the local checkout if
+// there is one and otherwise from void-packages' master branch, a few at a
+// time
+async fn template_texts(
+    config: &Config,
+    fetcher: &Fetcher,
+    names: &[String],
+) -> Vec<vupdate::error::Result<Option<String>>> {
+    match &config.distdir {
+        Some(distdir) => names
+            .iter()
+            .map(|name| template::load_text(distdir, name))
+            .collect(),
+        None => {
+            let mut texts = Vec::new();
+            for chunk in names.chunks(SOURCE_REQUESTS) {
+                let lookups = chunk.iter().map(|name| fetcher.master_template_text(name));
+                texts.extend(join_all(lookups).await);
+            }
+            texts
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vupdate::parse::response_to_hashmap;
+
+    #[test]
+    fn keeps_only_installed_orphans() {
+        let mut installed = response_to_hashmap("foo 1.0 -> 2.0\nbar 1.0 -> 1.1\n");
+        installed
+            .0
+            .get_mut("foo")
+            .unwrap()
+            .tags
+            .push("orphan".to_string());
+        let mut sections = [
+            (Section::Installed, installed),
+            (Section::Watched, response_to_hashmap("baz 1.0 -> 2.0\n")),
+        ];
+        keep_orphans(&mut sections);
+        let names: Vec<&String> = sections[0].1 .0.keys().collect();
+        assert_eq!(names, ["foo"]);
+        assert!(sections[1].1 .0.is_empty());
+    }
+}
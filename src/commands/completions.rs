@@ -0,0 +1,362 @@
+// `vupdate completions bash|zsh|fish`: print a shell completion script
+// generated from the command and option tables in `cli.rs`.
+//
+// Package names are completed dynamically: the scripts call the hidden
+// `vupdate __complete installed|ignored` command, implemented by `helper`.
+
+use crate::cli::{self, Args, Cmd, Opt, COMMANDS, OPTIONS};
+use vupdate::config::Config;
+use vupdate::ignore::IgnoreList;
+use vupdate::xbps::installed_packages_in;
+
+static NAME: &str = env!("CARGO_PKG_NAME");
+
+pub fn run(args: &Args) -> i32 {
+    let script = match args.operands.as_slice() {
+        [shell] if shell == "bash" => bash(),
+        [shell] if shell == "zsh" => zsh(),
+        [shell] if shell == "fish" => fish(),
+        [shell] => cli::usage_error(cli::CliError::UnexpectedArgument(shell.clone())),
+        [] => cli::usage_error(cli::CliError::MissingArgument("bash, zsh or fish")),
+        [_, extra, ..] => cli::usage_error(cli::CliError::UnexpectedArgument(extra.clone())),
+    };
+    print!("{}", script);
+    0
+}
+
+// `vupdate __complete installed|ignored`: print package names, one per line
+pub fn helper(config: &Config, args: &Args) -> i32 {
+    let mut names: Vec<String> = match args.operands.first().map(String::as_str) {
+        Some("installed") => installed_packages_in(&config.pkgdb, true)
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+        Some("ignored") => match IgnoreList::load() {
+            Ok(ignored) => ignored.entries().map(|e| e.pkg_name.clone()).collect(),
+            Err(_) => Vec::new(),
+        },
+        _ => return 2,
+    };
+    names.sort();
+    for name in names {
+        println!("{}", name);
+    }
+    0
+}
+
+// What the operands of a command complete to: a fixed list of words, or
+// package names from `__complete`
+enum Operands {
+    Nothing,
+    Words(&'static [&'static str]),
+    Packages(&'static str),
+}
+
+// Completion of the first operand of a command. For `ignore`, the operands
+// after the subcommand are completed by `ignore_operands`.
+fn operands(cmd: &Cmd) -> Operands {
+    match cmd.name {
+        "check" | "report" | "digest" | "watch" | "serve" | "pick" | "info" | "open"
+        | "changelog" | "bump" | "pr" | "build" | "revbump" | "note" => {
+            Operands::Packages("installed")
+        }
+        "ignore" => Operands::Words(&["add", "remove", "list"]),
+        "diff" => Operands::Words(&["latest", "previous"]),
+        "status" => Operands::Words(&["set", "show"]),
+        "worktree" => Operands::Words(&["list", "clean"]),
+        "completions" => Operands::Words(&["bash", "zsh", "fish"]),
+        _ => Operands::Nothing,
+    }
+}
+
+// Completion of the operands following an `ignore` subcommand
+static IGNORE_OPERANDS: &[(&str, &str)] = &[("add", "installed"), ("remove", "ignored")];
+
+fn visible_commands() -> impl Iterator<Item = &'static Cmd> {
+    COMMANDS.iter().filter(|c| !c.hidden)
+}
+
+fn options_for(cmd: Option<&Cmd>) -> impl Iterator<Item = &'static Opt> + '_ {
+    OPTIONS
+        .iter()
+        .filter(move |o| o.commands.is_empty() || cmd.is_some_and(|c| o.commands.contains(&c.name)))
+}
+
+// Every spelling of an option, e.g. `--output` and `-o`
+fn spellings(opt: &Opt) -> Vec<String> {
+    let mut spellings = vec![format!("--{}", opt.long)];
+    if let Some(short) = opt.short {
+        spellings.push(format!("-{}", short));
+    }
+    spellings
+}
+
+fn bash() -> String {
+    let mut out = format!(
+        "# bash completion for {name}, generated by `{name} completions bash`\n\n_{name}() {{\n",
+        name = NAME
+    );
+    out.push_str("    local cur prev cmd i\n");
+    out.push_str("    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    out.push_str("    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n\n");
+
+    // Find the command, remembering where it is
+    let names: Vec<&str> = visible_commands().map(|c| c.name).collect();
+    out.push_str("    cmd=\"\"\n    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+    out.push_str(&format!(
+        "        case \"${{COMP_WORDS[i]}}\" in\n            {}) cmd=\"${{COMP_WORDS[i]}}\"; break ;;\n        esac\n    done\n\n",
+        names.join("|")
+    ));
+
+    // Values of options
+    out.push_str("    case \"$prev\" in\n");
+    for opt in OPTIONS.iter().filter(|o| o.value.is_some()) {
+        let reply = if opt.choices.is_empty() {
+            "COMPREPLY=()".to_string()
+        } else {
+            format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                opt.choices.join(" ")
+            )
+        };
+        out.push_str(&format!(
+            "        {}) {}; return ;;\n",
+            spellings(opt).join("|"),
+            reply
+        ));
+    }
+    out.push_str("    esac\n\n");
+
+    // Options, depending on the command
+    out.push_str("    if [[ \"$cur\" == -* ]]; then\n        case \"$cmd\" in\n");
+    for cmd in visible_commands() {
+        let opts: Vec<String> = options_for(Some(cmd)).flat_map(spellings).collect();
+        out.push_str(&format!(
+            "            {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n",
+            cmd.name,
+            opts.join(" ")
+        ));
+    }
+    let global: Vec<String> = options_for(None).flat_map(spellings).collect();
+    out.push_str(&format!(
+        "            *) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n        esac\n        return\n    fi\n\n",
+        global.join(" ")
+    ));
+
+    // Operands
+    out.push_str(&format!(
+        "    case \"$cmd\" in\n        \"\") COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n",
+        names.join(" ")
+    ));
+    for cmd in visible_commands() {
+        let reply = match operands(cmd) {
+            Operands::Nothing => continue,
+            Operands::Words(words) if cmd.name == "ignore" => {
+                let mut reply = format!(
+                    "if ((COMP_CWORD == i + 1)); then\n                COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+                    words.join(" ")
+                );
+                for (sub, kind) in IGNORE_OPERANDS {
+                    reply.push_str(&format!(
+                        "            elif [[ \"${{COMP_WORDS[i+1]}}\" == {} ]]; then\n                COMPREPLY=($(compgen -W \"$({} __complete {} 2>/dev/null)\" -- \"$cur\"))\n",
+                        sub, NAME, kind
+                    ));
+                }
+                reply.push_str("            fi");
+                reply
+            }
+            Operands::Words(words) => {
+                format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                    words.join(" ")
+                )
+            }
+            Operands::Packages(kind) => format!(
+                "COMPREPLY=($(compgen -W \"$({} __complete {} 2>/dev/null)\" -- \"$cur\"))",
+                NAME, kind
+            ),
+        };
+        out.push_str(&format!(
+            "        {})\n            {}\n            ;;\n",
+            cmd.name, reply
+        ));
+    }
+    out.push_str("    esac\n}\n\n");
+    out.push_str(&format!("complete -F _{name} {name}\n", name = NAME));
+    out
+}
+
+// Quote a `name:description` pair for zsh's `_describe`, which splits on the
+// first unescaped colon
+fn zsh_describe_item(name: &str, help: &str) -> String {
+    format!(
+        "'{}:{}'",
+        name.replace(':', "\\:"),
+        help.replace('\'', "'\\''")
+    )
+}
+
+fn zsh() -> String {
+    let mut out = format!("#compdef {name}\n\n_{name}() {{\n", name = NAME);
+    out.push_str("    local cmd i\n");
+    out.push_str("    for ((i = 2; i < CURRENT; i++)); do\n");
+    let names: Vec<&str> = visible_commands().map(|c| c.name).collect();
+    out.push_str(&format!(
+        "        case ${{words[i]}} in\n            {}) cmd=${{words[i]}}; break ;;\n        esac\n    done\n\n",
+        names.join("|")
+    ));
+
+    out.push_str("    case ${words[CURRENT-1]} in\n");
+    for opt in OPTIONS.iter().filter(|o| o.value.is_some()) {
+        let reply = if opt.choices.is_empty() {
+            "return".to_string()
+        } else {
+            format!("compadd -- {}; return", opt.choices.join(" "))
+        };
+        out.push_str(&format!(
+            "        {}) {} ;;\n",
+            spellings(opt).join("|"),
+            reply
+        ));
+    }
+    out.push_str("    esac\n\n");
+
+    out.push_str("    if [[ $PREFIX == -* ]]; then\n        local -a opts\n        case $cmd in\n");
+    let opt_items = |cmd: Option<&Cmd>| -> String {
+        options_for(cmd)
+            .flat_map(|o| {
+                spellings(o)
+                    .into_iter()
+                    .map(move |s| zsh_describe_item(&s, o.help))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    for cmd in visible_commands() {
+        out.push_str(&format!(
+            "            {}) opts=({}) ;;\n",
+            cmd.name,
+            opt_items(Some(cmd))
+        ));
+    }
+    out.push_str(&format!(
+        "            *) opts=({}) ;;\n        esac\n        _describe 'option' opts\n        return\n    fi\n\n",
+        opt_items(None)
+    ));
+
+    let cmd_items: Vec<String> = visible_commands()
+        .map(|c| zsh_describe_item(c.name, c.help))
+        .collect();
+    out.push_str(&format!(
+        "    case $cmd in\n        '')\n            local -a cmds\n            cmds=({})\n            _describe 'command' cmds\n            ;;\n",
+        cmd_items.join(" ")
+    ));
+    for cmd in visible_commands() {
+        let reply = match operands(cmd) {
+            Operands::Nothing => continue,
+            Operands::Words(words) if cmd.name == "ignore" => {
+                let mut reply = format!(
+                    "if ((CURRENT == i + 1)); then\n                compadd -- {}\n",
+                    words.join(" ")
+                );
+                for (sub, kind) in IGNORE_OPERANDS {
+                    reply.push_str(&format!(
+                        "            elif [[ ${{words[i+1]}} == {} ]]; then\n                compadd -- ${{(f)\"$({} __complete {} 2>/dev/null)\"}}\n",
+                        sub, NAME, kind
+                    ));
+                }
+                reply.push_str("            fi");
+                reply
+            }
+            Operands::Words(words) => format!("compadd -- {}", words.join(" ")),
+            Operands::Packages(kind) => format!(
+                "compadd -- ${{(f)\"$({} __complete {} 2>/dev/null)\"}}",
+                NAME, kind
+            ),
+        };
+        out.push_str(&format!(
+            "        {})\n            {}\n            ;;\n",
+            cmd.name, reply
+        ));
+    }
+    out.push_str("    esac\n}\n\n");
+    out.push_str(&format!(
+        "if [[ $funcstack[1] == _{name} ]]; then\n    _{name} \"$@\"\nelse\n    compdef _{name} {name}\nfi\n",
+        name = NAME
+    ));
+    out
+}
+
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish() -> String {
+    let mut out = format!(
+        "# fish completion for {name}, generated by `{name} completions fish`\n\ncomplete -c {name} -f\n",
+        name = NAME
+    );
+    for cmd in visible_commands() {
+        out.push_str(&format!(
+            "complete -c {} -n __fish_use_subcommand -a {} -d {}\n",
+            NAME,
+            cmd.name,
+            fish_quote(cmd.help)
+        ));
+    }
+
+    for opt in OPTIONS {
+        let mut line = format!("complete -c {}", NAME);
+        if !opt.commands.is_empty() {
+            line.push_str(&format!(
+                " -n '__fish_seen_subcommand_from {}'",
+                opt.commands.join(" ")
+            ));
+        }
+        line.push_str(&format!(" -l {}", opt.long));
+        if let Some(short) = opt.short {
+            line.push_str(&format!(" -s {}", short));
+        }
+        if opt.value.is_some() {
+            line.push_str(" -x");
+            if !opt.choices.is_empty() {
+                line.push_str(&format!(" -a {}", fish_quote(&opt.choices.join(" "))));
+            }
+        }
+        line.push_str(&format!(" -d {}\n", fish_quote(opt.help)));
+        out.push_str(&line);
+    }
+
+    for cmd in visible_commands() {
+        let seen = format!("__fish_seen_subcommand_from {}", cmd.name);
+        match operands(cmd) {
+            Operands::Nothing => {}
+            Operands::Words(words) if cmd.name == "ignore" => {
+                out.push_str(&format!(
+                    "complete -c {} -n '{}; and not __fish_seen_subcommand_from {}' -a {}\n",
+                    NAME,
+                    seen,
+                    words.join(" "),
+                    fish_quote(&words.join(" "))
+                ));
+                for (sub, kind) in IGNORE_OPERANDS {
+                    out.push_str(&format!(
+                        "complete -c {} -n '{}; and __fish_seen_subcommand_from {}' -a '({} __complete {} 2>/dev/null)'\n",
+                        NAME, seen, sub, NAME, kind
+                    ));
+                }
+            }
+            Operands::Words(words) => out.push_str(&format!(
+                "complete -c {} -n '{}' -a {}\n",
+                NAME,
+                seen,
+                fish_quote(&words.join(" "))
+            )),
+            Operands::Packages(kind) => out.push_str(&format!(
+                "complete -c {} -n '{}' -a '({} __complete {} 2>/dev/null)'\n",
+                NAME, seen, NAME, kind
+            )),
+        }
+    }
+    out
+}
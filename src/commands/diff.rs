@@ -0,0 +1,92 @@
+// `vupdate diff [FROM] [TO]`: compare two runs recorded in the history
+
+use crate::cli::{self, Args, CliError};
+use colored::Colorize;
+use vupdate::date;
+use vupdate::history::{self, Recorded, Run};
+
+pub fn run(args: &Args) -> i32 {
+    let (from, to) = match args.operands.as_slice() {
+        [] => ("previous", "latest"),
+        [from] => (from.as_str(), "latest"),
+        [from, to] => (from.as_str(), to.as_str()),
+        [_, _, extra, ..] => cli::usage_error(CliError::UnexpectedArgument(extra.clone())),
+    };
+    for reference in [from, to] {
+        if !matches!(reference, "latest" | "previous") && !date::is_valid(reference) {
+            cli::usage_error(CliError::UnexpectedArgument(reference.to_string()));
+        }
+    }
+    let runs = match history::load() {
+        Ok(runs) => runs,
+        Err(e) => {
+            cli::error(format!("Could not read the history: {}", e));
+            return 1;
+        }
+    };
+    let (old, new) = match (find(&runs, from), find(&runs, to)) {
+        (Some(old), Some(new)) => (old, new),
+        (old, _) => {
+            let missing = if old.is_none() { from } else { to };
+            cli::error(format!("No run recorded for '{}'", missing));
+            return 1;
+        }
+    };
+
+    println!(
+        "From {} to {}:",
+        date::format_timestamp(old.time),
+        date::format_timestamp(new.time)
+    );
+    let diff = history::diff(old, new);
+    if diff.is_empty() {
+        println!("No changes");
+        return 0;
+    }
+    let width = diff
+        .added
+        .iter()
+        .chain(&diff.removed)
+        .map(|(name, _)| name.len())
+        .chain(diff.changed.iter().map(|(name, _, _)| name.len()))
+        .max()
+        .unwrap_or(0);
+    for (name, update) in &diff.added {
+        println!("{} {}", "+".green(), line(name, update, width));
+    }
+    for (name, update) in &diff.removed {
+        println!("{} {}", "-".red(), line(name, update, width));
+    }
+    for (name, before, after) in &diff.changed {
+        println!(
+            "{} {} (was {})",
+            "~".yellow(),
+            line(name, after, width),
+            before.new_version
+        );
+    }
+    0
+}
+
+// The run named by `reference`: `latest`, `previous` or the last run on or
+// before a date
+fn find<'a>(runs: &'a [Run], reference: &str) -> Option<&'a Run> {
+    match reference {
+        "latest" => runs.last(),
+        "previous" => runs.len().checked_sub(2).map(|i| &runs[i]),
+        day => runs
+            .iter()
+            .rev()
+            .find(|run| date::from_timestamp(run.time).as_str() <= day),
+    }
+}
+
+fn line(name: &str, update: &Recorded, width: usize) -> String {
+    format!(
+        "{:width$}  {} -> {}",
+        name,
+        update.current_version,
+        update.new_version,
+        width = width
+    )
+}
@@ -0,0 +1,81 @@
+// `vupdate digest --email-to ADDRESS [PATTERN]...`: mail the updates `check`
+// would list, e.g. from a weekly cron job
+
+use super::check;
+use crate::cli::{self, Args, CliError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use vupdate::config::Config;
+use vupdate::output;
+use vupdate::smtp::Message;
+
+// How recent an update's version must be to count as new in the digest
+const WEEK: u64 = 7 * 86_400;
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let to = args.values("email-to");
+    if to.is_empty() {
+        cli::usage_error(CliError::MissingArgument("--email-to ADDRESS"));
+    }
+    let server = match &config.smtp {
+        Some(server) => server,
+        None => {
+            cli::error("No mail server configured, see [smtp] in the configuration file");
+            return 1;
+        }
+    };
+
+    let (sections, failed) = match check::updates(config, args, false).await {
+        Some(found) => found,
+        None => return 1,
+    };
+    let total: usize = sections.iter().map(|(_, updates)| updates.0.len()).sum();
+    // Nobody wants a mail saying that there is nothing to do
+    if total == 0 {
+        return if failed { 1 } else { 0 };
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let recent = sections
+        .iter()
+        .flat_map(|(_, updates)| updates.0.values())
+        .filter(|u| {
+            u.version_seen
+                .is_some_and(|seen| now.saturating_sub(seen) < WEEK)
+        })
+        .count();
+    let summary = format!(
+        "{} update{}, {} new this week",
+        total,
+        if total == 1 { "" } else { "s" },
+        recent
+    );
+    let mut body = format!("{}.\n", summary);
+    for (section, updates) in &sections {
+        if updates.0.is_empty() {
+            continue;
+        }
+        body.push_str(&format!(
+            "\n{}\n{}",
+            section.title(),
+            output::table(&updates.sorted(), None)
+        ));
+    }
+    body.push_str("\n-- \nSent by vupdate\n");
+
+    let message = Message {
+        to,
+        subject: format!("Void package updates: {}", summary),
+        body,
+    };
+    if let Err(e) = server.send(&message).await {
+        cli::error(e);
+        return 1;
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,66 @@
+// `vupdate ignore add|remove|list`: manage the ignore file
+
+use crate::cli::{self, Args};
+use vupdate::date;
+use vupdate::ignore::{IgnoreEntry, IgnoreList};
+
+pub fn run(args: &Args) -> i32 {
+    let command: Vec<&str> = args.operands.iter().map(String::as_str).collect();
+    match ignore(args, &command) {
+        Ok(()) => 0,
+        Err(e) => cli::usage_error(e),
+    }
+}
+
+fn ignore(args: &Args, command: &[&str]) -> Result<(), cli::CliError> {
+    let mut ignored = IgnoreList::load().unwrap_or_else(|e| {
+        cli::error(format!("Could not read the ignore file: {}", e));
+        std::process::exit(1);
+    });
+
+    match command {
+        ["list"] => {
+            let today = date::today();
+            for entry in ignored.entries() {
+                match &entry.until {
+                    Some(until) if today >= *until => println!("{} (expired)", entry),
+                    _ => println!("{}", entry),
+                }
+            }
+            return Ok(());
+        }
+        ["add", pkgs @ ..] if !pkgs.is_empty() => {
+            let until = args.value("until");
+            if let Some(until) = &until {
+                if !date::is_valid(until) {
+                    return Err(cli::CliError::invalid_value("until", until.clone()));
+                }
+            }
+            let until_version = args.value("until-version");
+            for pkg_name in pkgs {
+                let mut entry = IgnoreEntry::new(pkg_name);
+                entry.until = until.clone();
+                entry.until_version = until_version.clone();
+                if !ignored.add(entry) {
+                    println!("{} was already ignored, updated its entry", pkg_name);
+                }
+            }
+        }
+        ["remove", pkgs @ ..] if !pkgs.is_empty() => {
+            for pkg_name in pkgs {
+                if !ignored.remove(pkg_name) {
+                    println!("{} is not ignored", pkg_name);
+                }
+            }
+        }
+        ["add"] | ["remove"] => return Err(cli::CliError::MissingArgument("<PKG>")),
+        [] => return Err(cli::CliError::MissingArgument("add, remove or list")),
+        [other, ..] => return Err(cli::CliError::UnexpectedArgument(other.to_string())),
+    }
+
+    if let Err(e) = ignored.save() {
+        cli::error(format!("Could not write the ignore file: {}", e));
+        std::process::exit(1);
+    }
+    Ok(())
+}
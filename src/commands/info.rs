@@ -0,0 +1,251 @@
+// `vupdate info <PKG>...`: show what is known about packages
+
+use crate::cli::{self, Args};
+use colored::Colorize;
+use futures_util::future::join_all;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use vupdate::changelog::Forge;
+use vupdate::config::Config;
+use vupdate::error::Error;
+use vupdate::fetch::Fetcher;
+use vupdate::github;
+use vupdate::ignore::IgnoreList;
+use vupdate::parse::{PackageUpdate, UpdateMap};
+use vupdate::xbps::read_pkgdb;
+use vupdate::{date, repology, template, version, watchlist};
+
+// How much of a release's notes is shown
+const RELEASE_NOTES_LINES: usize = 15;
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    if args.operands.is_empty() {
+        cli::usage_error(cli::CliError::MissingArgument("<PKG>"));
+    }
+
+    let fetcher = match Fetcher::new(config.fetch.clone()) {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    let maintainer_fetch = join_all(config.emails.iter().map(|e| fetcher.maintainer_updates(e)));
+    let (maintainer_results, all_result) = tokio::join!(maintainer_fetch, fetcher.all_updates());
+    super::offline_notice(&fetcher);
+
+    let mut failed = false;
+    let mut maintained = UpdateMap::new();
+    for (email, result) in config.emails.iter().zip(maintainer_results) {
+        match result {
+            Ok(mut updates) => {
+                for update in updates.0.values_mut() {
+                    update.maintainer = Some(email.clone());
+                }
+                maintained.extend(updates);
+            }
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+    let mut all_updates = all_result.unwrap_or_else(|e| {
+        cli::error(e);
+        failed = true;
+        UpdateMap::new()
+    });
+    for (k, v) in all_updates.0.iter_mut() {
+        v.new_version = config.mangled(k, &v.new_version);
+    }
+
+    let installed = read_pkgdb(&config.pkgdb).unwrap_or_else(|e| {
+        cli::error(e);
+        failed = true;
+        Vec::new()
+    });
+    let ignored = IgnoreList::load()
+        .map_err(|e| {
+            cli::error(format!("Could not read the ignore file: {}", e));
+            failed = true;
+        })
+        .ok();
+    let watched = watchlist::load().unwrap_or_else(|e| {
+        cli::error(format!("Could not read the watchlist: {}", e));
+        failed = true;
+        HashSet::new()
+    });
+    // Release notes are only asked of GitHub, whose limit on anonymous
+    // requests a token raises
+    let github = if config.fetch.offline {
+        None
+    } else {
+        match config.github_token.token() {
+            Ok(token) => Some(github::Client::new(fetcher.client().clone(), token)),
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+                None
+            }
+        }
+    };
+    let today = date::today();
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+
+    for (i, pkg_name) in args.operands.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", pkg_name.bold());
+        match all_updates.0.get(pkg_name) {
+            Some(update) => {
+                println!(
+                    "  Update:     {} -> {}",
+                    update.current_version, update.new_version
+                );
+                if let Some(url) = &update.url {
+                    println!("  Found at:   {}", url);
+                }
+                if !config.fetch.offline {
+                    if i > 0 {
+                        tokio::time::sleep(repology::REQUEST_INTERVAL).await;
+                    }
+                    match repology::distros_with(&fetcher, pkg_name, &update.new_version).await {
+                        Ok(Some(distros)) if distros.is_empty() => {
+                            println!("  Repology:   no other distribution has it");
+                        }
+                        Ok(Some(distros)) if distros.len() == 1 => {
+                            println!("  Repology:   1 other distribution has it ({})", distros[0]);
+                        }
+                        Ok(Some(distros)) => println!(
+                            "  Repology:   {} other distributions have it ({})",
+                            distros.len(),
+                            distros.join(", ")
+                        ),
+                        Ok(None) => println!("  Repology:   package unknown"),
+                        Err(e) => {
+                            cli::error(e);
+                            failed = true;
+                        }
+                    }
+                }
+                if let Some(github) = &github {
+                    if let Err(e) = release_notes(config, &fetcher, github, pkg_name, update).await
+                    {
+                        cli::error(e);
+                        failed = true;
+                    }
+                }
+            }
+            None => println!("  Update:     none available"),
+        }
+        // The template names the maintainer in full, the updates lists only
+        // for the configured emails
+        let mut maintainer = maintained
+            .0
+            .get(pkg_name)
+            .and_then(|u| u.maintainer.clone());
+        let pkg = installed.iter().find(|pkg| &pkg.name == pkg_name);
+        let mut description = pkg.and_then(|pkg| pkg.short_desc.clone());
+        if let Some(distdir) = &config.distdir {
+            match template::load(distdir, pkg_name) {
+                Ok(Some(template)) => {
+                    let applied = match all_updates.0.get(pkg_name) {
+                        Some(update)
+                            if version::compare(&template.version, &update.new_version)
+                                != Ordering::Less =>
+                        {
+                            " (update applied)"
+                        }
+                        _ => "",
+                    };
+                    println!("  Template:   {}{}", template.full_version(), applied);
+                    if !template.repository.is_empty() {
+                        println!("  Repository: {}", template.repository);
+                    }
+                    if template.restricted {
+                        println!("  Restricted: not built by the build servers");
+                    }
+                    if !template.maintainer.is_empty() {
+                        maintainer = Some(template.maintainer);
+                    }
+                    if description.is_none() && !template.short_desc.is_empty() {
+                        description = Some(template.short_desc);
+                    }
+                }
+                Ok(None) => println!("  Template:   not in {}", distdir.display()),
+                Err(e) => {
+                    cli::error(e);
+                    failed = true;
+                }
+            }
+        }
+        if let Some(maintainer) = maintainer {
+            println!("  Maintainer: {}", maintainer);
+        }
+        if let Some(description) = description {
+            println!("  Summary:    {}", description);
+        }
+        match pkg {
+            Some(pkg) if pkg.hold => println!("  Installed:  {} (on hold)", pkg.version()),
+            Some(pkg) if pkg.automatic => {
+                println!("  Installed:  {} (as a dependency)", pkg.version())
+            }
+            Some(pkg) => println!("  Installed:  {}", pkg.version()),
+            None => println!("  Installed:  no"),
+        }
+        println!("  Watched:    {}", yes_no(watched.contains(pkg_name)));
+        if let Some(entry) = ignored
+            .as_ref()
+            .and_then(|l| l.entries().find(|e| &e.pkg_name == pkg_name))
+        {
+            let active = match all_updates.0.get(pkg_name) {
+                Some(update) => entry.hides(&update.new_version, &today),
+                None => true,
+            };
+            let state = if active { "" } else { " (not in effect)" };
+            println!("  Ignored:    {}{}", entry, state);
+        }
+    }
+
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+// Print the GitHub release notes of the new version, if upstream is on GitHub
+// and made a release of it
+async fn release_notes(
+    config: &Config,
+    fetcher: &Fetcher,
+    github: &github::Client,
+    pkg_name: &str,
+    update: &PackageUpdate,
+) -> Result<(), Error> {
+    let repo = match super::changelog::forge(config, fetcher, pkg_name, update).await {
+        Some(Forge::GitHub(repo)) => repo,
+        _ => return Ok(()),
+    };
+    let release = match github.release(&repo, &update.new_version).await? {
+        Some(release) => release,
+        None => return Ok(()),
+    };
+    match &release.date {
+        Some(date) => println!("  Release:    {} ({})", release.tag, date),
+        None => println!("  Release:    {}", release.tag),
+    }
+    let lines: Vec<&str> = release.body.trim().lines().collect();
+    for line in lines.iter().take(RELEASE_NOTES_LINES) {
+        println!("    {}", line.trim_end());
+    }
+    if lines.len() > RELEASE_NOTES_LINES {
+        println!(
+            "    {}",
+            format!("... {} more lines", lines.len() - RELEASE_NOTES_LINES).dimmed()
+        );
+    }
+    println!("    {}", release.url.dimmed());
+    Ok(())
+}
@@ -0,0 +1,86 @@
+// `vupdate maintainers`: the maintainers with the most pending updates in the
+// full updates list, to spot the areas of the tree nobody keeps up with
+
+use crate::cli::{self, Args, CliError};
+use std::collections::BTreeMap;
+use std::path::Path;
+use vupdate::config::Config;
+use vupdate::fetch::Fetcher;
+use vupdate::template;
+
+// How many maintainers are listed by default
+const DEFAULT_TOP: usize = 20;
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    if let Some(extra) = args.operands.first() {
+        cli::usage_error(CliError::UnexpectedArgument(extra.clone()));
+    }
+    let top = match args.value("top") {
+        Some(top) => top
+            .parse()
+            .unwrap_or_else(|_| cli::usage_error(CliError::invalid_value("top", top))),
+        None => DEFAULT_TOP,
+    };
+    // Only the templates of a local checkout can be read for all packages
+    let distdir = match &config.distdir {
+        Some(distdir) => distdir,
+        None => {
+            cli::error("No void-packages checkout: set 'distdir' or pass --distdir");
+            return 1;
+        }
+    };
+    let fetcher = match Fetcher::new(config.fetch.clone()) {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    let result = fetcher.all_updates().await;
+    super::offline_notice(&fetcher);
+    let updates = match result {
+        Ok(updates) => updates,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+
+    let maintainers = by_maintainer(distdir, updates.0.keys());
+    let width = maintainers
+        .first()
+        .map_or(0, |(_, count)| count.to_string().len());
+    let shown = if top == 0 { maintainers.len() } else { top };
+    for (maintainer, count) in maintainers.iter().take(shown) {
+        println!("{:>width$}  {}", count, maintainer, width = width);
+    }
+    if maintainers.len() > shown {
+        println!("... {} more maintainers", maintainers.len() - shown);
+    }
+    println!(
+        "{} updates, {} maintainers",
+        updates.0.len(),
+        maintainers.len()
+    );
+    0
+}
+
+// How many of the packages `names` each maintainer has, according to the
+// templates of the checkout at `distdir`, most first. Packages without a
+// template or maintainer count as `(unknown)`.
+pub fn by_maintainer<'a>(
+    distdir: &Path,
+    names: impl IntoIterator<Item = &'a String>,
+) -> Vec<(String, usize)> {
+    let mut maintainers: BTreeMap<String, usize> = BTreeMap::new();
+    for name in names {
+        let maintainer = match template::load(distdir, name) {
+            Ok(Some(template)) if !template.maintainer.is_empty() => template.maintainer,
+            _ => "(unknown)".to_string(),
+        };
+        *maintainers.entry(maintainer).or_default() += 1;
+    }
+    let mut maintainers: Vec<(String, usize)> = maintainers.into_iter().collect();
+    maintainers.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    maintainers
+}
@@ -0,0 +1,125 @@
+// `vupdate mangen`: print a roff man page generated from the command and
+// option tables in `cli.rs`, so the installed documentation always matches
+// the program. Hidden commands are left out.
+
+use crate::cli::{self, Args, Cmd, Opt, COMMANDS, OPTIONS};
+
+static NAME: &str = env!("CARGO_PKG_NAME");
+
+pub fn run(args: &Args) -> i32 {
+    if let Some(arg) = args.operands.first() {
+        cli::usage_error(cli::CliError::UnexpectedArgument(arg.clone()));
+    }
+    print!("{}", page());
+    0
+}
+
+// Escape text for roff: backslashes and hyphens, and a leading `.` or `'`
+// that would otherwise start a request
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+fn option(opt: &Opt) -> String {
+    let mut spec = String::new();
+    if let Some(short) = opt.short {
+        spec.push_str(&format!("\\fB\\-{}\\fR, ", short));
+    }
+    spec.push_str(&format!("\\fB\\-\\-{}\\fR", escape(opt.long)));
+    if let Some(value) = opt.value {
+        spec.push_str(&format!(" \\fI{}\\fR", value));
+    }
+    let mut out = format!(".TP\n{}\n{}", spec, escape(opt.help));
+    if let Some(env) = opt.env {
+        out.push_str(&format!(" [env: {}]", env));
+    }
+    out.push('\n');
+    out
+}
+
+fn command(cmd: &Cmd) -> String {
+    let usage = format!("{} {}", cmd.name, escape(cmd.args));
+    let mut out = format!(".SS \"{}\"\n{}.\n", usage.trim_end(), escape(cmd.help));
+    let opts: Vec<&Opt> = OPTIONS
+        .iter()
+        .filter(|o| o.commands.contains(&cmd.name))
+        .collect();
+    if !opts.is_empty() {
+        out.push_str(".RS\n");
+        for opt in opts {
+            out.push_str(&option(opt));
+        }
+        out.push_str(".RE\n");
+    }
+    out
+}
+
+fn page() -> String {
+    let upper = NAME.to_uppercase();
+    let mut out = format!(
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"\n",
+        upper,
+        NAME,
+        env!("CARGO_PKG_VERSION")
+    );
+    out.push_str(&format!(
+        ".SH NAME\n{} \\- list Void Linux packages with updates available upstream\n",
+        NAME
+    ));
+    out.push_str(&format!(
+        ".SH SYNOPSIS\n\\fB{}\\fR [\\fIOPTIONS\\fR] [\\fICOMMAND\\fR] [\\fIARGS\\fR]...\n",
+        NAME
+    ));
+    out.push_str(&format!(
+        ".SH DESCRIPTION\n\\fB{}\\fR reads the update lists published by Void Linux and reports \
+         upstream updates for packages you maintain, packages installed on this system and \
+         packages on your watchlist.\nWithout a command, \\fB{}\\fR is run.\n",
+        NAME,
+        cli::DEFAULT_COMMAND
+    ));
+
+    out.push_str(".SH COMMANDS\n");
+    for cmd in COMMANDS.iter().filter(|c| !c.hidden) {
+        out.push_str(&command(cmd));
+    }
+
+    out.push_str(".SH GLOBAL OPTIONS\n");
+    for opt in OPTIONS.iter().filter(|o| o.commands.is_empty()) {
+        out.push_str(&option(opt));
+    }
+
+    out.push_str(".SH ENVIRONMENT\n");
+    for opt in OPTIONS.iter().filter(|o| o.env.is_some()) {
+        out.push_str(&format!(
+            ".TP\n\\fB{}\\fR\nDefault for \\fB\\-\\-{}\\fR.\n",
+            opt.env.unwrap(),
+            escape(opt.long)
+        ));
+    }
+    out.push_str(
+        ".TP\n\\fBNO_COLOR\\fR\nWhen set and not empty, disables colors unless \\fB\\-\\-color\\fR says otherwise.\n\
+         .TP\n\\fBXDG_CONFIG_HOME\\fR\nBase directory of the configuration files, \\fI~/.config\\fR by default.\n",
+    );
+
+    out.push_str(&format!(
+        ".SH FILES\n\
+         .TP\n\\fI$XDG_CONFIG_HOME/{name}/config.toml\\fR\nThe configuration file.\n\
+         .TP\n\\fI$XDG_CONFIG_HOME/{name}/ignore\\fR\nPackages whose updates are not reported, see \\fB{name} ignore\\fR.\n\
+         .TP\n\\fI$XDG_CONFIG_HOME/{name}/watchlist\\fR\nExtra packages to report updates for, one per line.\n",
+        name = NAME
+    ));
+
+    out.push_str(
+        ".SH EXIT STATUS\n\
+         .TP\n\\fB0\\fR\nNo updates were found.\n\
+         .TP\n\\fB10\\fR\nUpdates were found.\n\
+         .TP\n\\fB1\\fR\nAn error occurred, e.g. an update list could not be fetched.\n\
+         .TP\n\\fB2\\fR\nThe command line was invalid.\n",
+    );
+    out
+}
@@ -0,0 +1,64 @@
+// `vupdate note [<PKG> [TEXT]...]`: attach free-form notes to packages, shown
+// with their updates
+
+use crate::cli::{self, Args, CliError};
+use colored::Colorize;
+use vupdate::date;
+use vupdate::notes::{Note, NoteStore};
+
+pub fn run(args: &Args) -> i32 {
+    let mut store = match NoteStore::load() {
+        Ok(store) => store,
+        Err(e) => {
+            cli::error(format!("Could not read the notes file: {}", e));
+            return 1;
+        }
+    };
+
+    if args.flag("remove") {
+        if args.operands.is_empty() {
+            cli::usage_error(CliError::MissingArgument("<PKG>"));
+        }
+        for pkg_name in &args.operands {
+            if !store.remove(pkg_name) {
+                eprintln!("No note on {}", pkg_name);
+            }
+        }
+    } else {
+        match args.operands.as_slice() {
+            [] => {
+                list(store.notes());
+                return 0;
+            }
+            [pkg_name] => {
+                match store.get(pkg_name) {
+                    Some(note) => list(std::iter::once(note)),
+                    None => eprintln!("No note on {}", pkg_name),
+                }
+                return 0;
+            }
+            [pkg_name, text @ ..] => store.set(Note {
+                pkg_name: pkg_name.clone(),
+                text: text.join(" "),
+                date: date::today(),
+            }),
+        }
+    }
+
+    if let Err(e) = store.save() {
+        cli::error(format!("Could not write the notes file: {}", e));
+        return 1;
+    }
+    0
+}
+
+fn list<'a>(notes: impl Iterator<Item = &'a Note>) {
+    for note in notes {
+        println!(
+            "{}  {}  {}",
+            note.pkg_name.bold(),
+            note.date.dimmed(),
+            note.text
+        );
+    }
+}
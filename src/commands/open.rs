@@ -0,0 +1,97 @@
+// `vupdate open <PKG>...`: open the upstream homepages of packages with
+// xdg-open
+
+use crate::cli::{self, Args};
+use std::process::{Command, Stdio};
+use vupdate::config::Config;
+use vupdate::fetch::Fetcher;
+use vupdate::parse::UpdateMap;
+use vupdate::template::{self, Template};
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    if args.operands.is_empty() {
+        cli::usage_error(cli::CliError::MissingArgument("<PKG>"));
+    }
+    let fetcher = match Fetcher::new(config.fetch.clone()) {
+        Ok(fetcher) => fetcher,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+
+    let mut failed = false;
+    // Only read when a template does not tell
+    let mut all_updates: Option<UpdateMap> = None;
+    for pkg_name in &args.operands {
+        let mut url = None;
+        if let Some(distdir) = &config.distdir {
+            match template::load(distdir, pkg_name) {
+                Ok(template) => url = template.and_then(homepage),
+                Err(e) => cli::error(e),
+            }
+        }
+        if url.is_none() {
+            match fetcher.master_template(pkg_name).await {
+                Ok(template) => url = template.and_then(homepage),
+                Err(e) => cli::error(e),
+            }
+        }
+        if url.is_none() {
+            if all_updates.is_none() {
+                let result = fetcher.all_updates().await;
+                super::offline_notice(&fetcher);
+                all_updates = Some(result.unwrap_or_else(|e| {
+                    cli::error(e);
+                    UpdateMap::new()
+                }));
+            }
+            url = all_updates
+                .as_ref()
+                .and_then(|updates| updates.0.get(pkg_name))
+                .and_then(|update| update.url.clone());
+        }
+
+        let url = match url {
+            Some(url) => url,
+            None => {
+                cli::error(format!("No homepage known for {}", pkg_name));
+                failed = true;
+                continue;
+            }
+        };
+        let status = Command::new("xdg-open")
+            .arg(&url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => println!("Opened {}", url),
+            Ok(status) => {
+                cli::error(format!("xdg-open {} failed with {}", url, status));
+                failed = true;
+            }
+            Err(e) => {
+                cli::error(format!("Could not run xdg-open: {}", e));
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+// The template's homepage, unless it is missing or uses variables that are
+// not expanded
+fn homepage(template: Template) -> Option<String> {
+    let homepage = template.homepage.replace("${pkgname}", &template.pkgname);
+    let homepage = homepage.replace("$pkgname", &template.pkgname);
+    if homepage.is_empty() || homepage.contains('$') {
+        None
+    } else {
+        Some(homepage)
+    }
+}
@@ -0,0 +1,482 @@
+// `vupdate pick [PATTERN]...`: choose among the updates `check` would list
+// with a fuzzy finder on the terminal, then act on the chosen ones: print
+// their names (the default), bump them, ignore them, open their homepages or
+// copy the commands bumping them by hand.
+
+use super::{bump, check, open};
+use crate::cli::{self, Args, CliError};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use vupdate::config::Config;
+use vupdate::ignore::{IgnoreEntry, IgnoreList};
+use vupdate::{git, template};
+
+// The exit status when the picker is left without choosing, like fzf's
+const CANCELLED: i32 = 130;
+
+// What is done with the chosen updates
+enum Action {
+    Print,
+    Bump,
+    Ignore,
+    Open,
+    Copy,
+}
+
+impl Action {
+    fn from_name(name: &str) -> Option<Action> {
+        match name {
+            "print" => Some(Action::Print),
+            "bump" => Some(Action::Bump),
+            "ignore" => Some(Action::Ignore),
+            "open" => Some(Action::Open),
+            "copy" => Some(Action::Copy),
+            _ => None,
+        }
+    }
+}
+
+// One update to choose
+struct Item {
+    pkg_name: String,
+    new_version: String,
+    // What is shown and matched against
+    line: String,
+}
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let action = match args.value("action") {
+        Some(name) => Action::from_name(&name)
+            .unwrap_or_else(|| cli::usage_error(CliError::invalid_value("action", name))),
+        None if args.flag("open") => Action::Open,
+        None => Action::Print,
+    };
+    let (sections, failed) = match check::updates(config, args, false).await {
+        Some(found) => found,
+        None => return 1,
+    };
+    let items: Vec<Item> = sections
+        .iter()
+        .flat_map(|(section, updates)| {
+            updates.sorted().into_iter().map(move |(pkg_name, update)| {
+                let mut line = format!(
+                    "{} {} -> {}  ({})",
+                    pkg_name,
+                    update.current_version,
+                    update.new_version,
+                    section.name()
+                );
+                if let Some(note) = &update.note {
+                    line.push_str(&format!("  {}", note));
+                }
+                Item {
+                    pkg_name: pkg_name.clone(),
+                    new_version: update.new_version.clone(),
+                    line,
+                }
+            })
+        })
+        .collect();
+    if items.is_empty() {
+        eprintln!("No updates to pick from");
+        return if failed { 1 } else { 0 };
+    }
+
+    let chosen = match pick(&items) {
+        Ok(Some(chosen)) => chosen,
+        Ok(None) => return CANCELLED,
+        Err(e) => {
+            cli::error(format!("Could not run the picker on the terminal: {}", e));
+            return 1;
+        }
+    };
+    let chosen: Vec<&Item> = chosen.into_iter().map(|i| &items[i]).collect();
+    let names: Vec<String> = chosen.iter().map(|item| item.pkg_name.clone()).collect();
+
+    match action {
+        Action::Bump => {
+            let mut args = args.clone();
+            args.command = "bump";
+            args.operands = names;
+            bump::run(config, &args).await
+        }
+        Action::Open => {
+            let mut args = args.clone();
+            args.command = "open";
+            args.operands = names;
+            open::run(config, &args).await
+        }
+        Action::Ignore => {
+            let mut ignored = match IgnoreList::load() {
+                Ok(ignored) => ignored,
+                Err(e) => {
+                    cli::error(format!("Could not read the ignore file: {}", e));
+                    return 1;
+                }
+            };
+            for name in &names {
+                ignored.add(IgnoreEntry::new(name));
+            }
+            if let Err(e) = ignored.save() {
+                cli::error(format!("Could not write the ignore file: {}", e));
+                return 1;
+            }
+            println!("Ignored {}", names.join(", "));
+            0
+        }
+        Action::Copy => {
+            let commands = bump_commands(config.distdir.as_deref(), &chosen);
+            if let Err(e) = copy(&commands) {
+                // Printed instead, so that they are not lost
+                cli::error(format!("Could not copy to the clipboard: {}", e));
+                print!("{}", commands);
+                return 1;
+            }
+            println!("Copied the commands bumping {}", names.join(", "));
+            0
+        }
+        Action::Print => {
+            for name in &names {
+                println!("{}", name);
+            }
+            0
+        }
+    }
+}
+
+// The commands bumping `items` by hand in the void-packages checkout at
+// `distdir`, one line each: a branch `<pkg>-<version>` like `bump` creates,
+// the new version in the template, its checksums and a commit with xtools,
+// then a build. The branches start from the one checked out now.
+fn bump_commands(distdir: Option<&Path>, items: &[&Item]) -> String {
+    let mut commands = String::new();
+    let base = distdir
+        .and_then(|distdir| git::current_branch(distdir).ok().flatten())
+        .unwrap_or_else(|| "master".to_string());
+    if let Some(distdir) = distdir {
+        commands.push_str(&format!("cd {}\n", shell_word(&distdir.to_string_lossy())));
+    }
+    for item in items {
+        let main = distdir
+            .and_then(|distdir| template::source_package(distdir, &item.pkg_name))
+            .unwrap_or_else(|| item.pkg_name.clone());
+        let pkg = shell_word(&main);
+        let branch = shell_word(&format!("{}-{}", main, item.new_version));
+        let set_version = shell_word(&format!("s/^version=.*/version={}/", item.new_version));
+        commands.push_str(&format!(
+            "git checkout -b {branch} {base}\n\
+             sed -i -e {set_version} -e 's/^revision=.*/revision=1/' srcpkgs/{pkg}/template\n\
+             xgensum -i {pkg}\n\
+             xbump {pkg}\n\
+             ./xbps-src pkg {pkg}\n",
+            branch = branch,
+            base = shell_word(&base),
+            set_version = set_version,
+            pkg = pkg,
+        ));
+    }
+    commands
+}
+
+// `word` quoted for the shell if it needs to be
+fn shell_word(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "+-./_=@:,%".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+// Put `text` on the clipboard with wl-copy under Wayland, or else xclip
+fn copy(text: &str) -> io::Result<()> {
+    let (program, args): (&str, &[&str]) = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-copy", &[])
+    } else {
+        ("xclip", &["-selection", "clipboard"])
+    };
+    let run = |e: io::Error| io::Error::new(e.kind(), format!("could not run {}: {}", program, e));
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(run)?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(text.as_bytes()).map_err(run)?;
+    drop(stdin);
+    let status = child.wait().map_err(run)?;
+    if !status.success() {
+        let msg = format!("{} failed with {}", program, status);
+        return Err(io::Error::other(msg));
+    }
+    Ok(())
+}
+
+// How well `query` matches `text` as a subsequence, ignoring case, or `None`
+// if it does not. Like fzf, runs of consecutive characters and matches at the
+// start of words score higher, and gaps lower.
+fn score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let found = next + text[next..].iter().position(|c| *c == q)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        match last {
+            Some(last) if last + 1 == found => score += 5,
+            Some(last) => score -= (found - last - 1).min(5) as i64,
+            None => score -= found.min(5) as i64,
+        }
+        last = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Backspace,
+    // Clear the query
+    Kill,
+    Up,
+    Down,
+    // Toggle the current item for a choice of several
+    Toggle,
+    Enter,
+    Cancel,
+    Other,
+}
+
+// The controlling terminal in raw mode, showing the alternate screen until
+// dropped
+struct Terminal {
+    tty: File,
+    saved: libc::termios,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Terminal> {
+        let tty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")?;
+        let fd = tty.as_raw_fd();
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = saved;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut terminal = Terminal { tty, saved };
+        terminal.tty.write_all(b"\x1b[?1049h")?;
+        Ok(terminal)
+    }
+
+    // The rows and columns, 24x80 if the terminal does not tell
+    fn size(&self) -> (usize, usize) {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let ok = unsafe { libc::ioctl(self.tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) } == 0;
+        if ok && size.ws_row > 0 && size.ws_col > 0 {
+            (size.ws_row as usize, size.ws_col as usize)
+        } else {
+            (24, 80)
+        }
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8];
+        self.tty.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    // Whether more input follows within a moment, telling an escape
+    // sequence from a lone Escape
+    fn pending(&self) -> bool {
+        let mut poll = libc::pollfd {
+            fd: self.tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut poll, 1, 50) > 0 }
+    }
+
+    fn key(&mut self) -> io::Result<Key> {
+        let key = match self.byte()? {
+            b'\r' => Key::Enter,
+            b'\t' => Key::Toggle,
+            0x7f | 0x08 => Key::Backspace,
+            // Ctrl-U, Ctrl-P and Ctrl-K, Ctrl-N and Ctrl-J, Ctrl-C and Ctrl-G
+            0x15 => Key::Kill,
+            0x10 | 0x0b => Key::Up,
+            0x0e | 0x0a => Key::Down,
+            0x03 | 0x07 => Key::Cancel,
+            0x1b if !self.pending() => Key::Cancel,
+            0x1b => match (self.byte()?, self.byte()?) {
+                (b'[', b'A') | (b'O', b'A') => Key::Up,
+                (b'[', b'B') | (b'O', b'B') => Key::Down,
+                _ => Key::Other,
+            },
+            b if b < 0x20 => Key::Other,
+            b => {
+                // The rest of a UTF-8 sequence
+                let len = match b {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf7 => 4,
+                    _ => 1,
+                };
+                let mut bytes = vec![b];
+                for _ in 1..len {
+                    bytes.push(self.byte()?);
+                }
+                match std::str::from_utf8(&bytes)
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                {
+                    Some(c) => Key::Char(c),
+                    None => Key::Other,
+                }
+            }
+        };
+        Ok(key)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.tty.write_all(b"\x1b[?1049l");
+        unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSAFLUSH, &self.saved) };
+    }
+}
+
+// Let the user choose items, returning their indices in order, or `None` if
+// the picker was left without choosing. Enter takes the items toggled with
+// Tab, or the current one if none are.
+fn pick(items: &[Item]) -> io::Result<Option<Vec<usize>>> {
+    let mut terminal = Terminal::open()?;
+    let mut query = String::new();
+    let mut toggled: Vec<bool> = vec![false; items.len()];
+    let mut cursor = 0;
+    let mut scroll = 0;
+    loop {
+        let mut matches: Vec<(i64, usize)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| score(&query, &item.line).map(|s| (s, i)))
+            .collect();
+        // Best first, in the order of the report among equals
+        matches.sort_by_key(|(score, i)| (std::cmp::Reverse(*score), *i));
+        cursor = cursor.min(matches.len().saturating_sub(1));
+
+        let (rows, cols) = terminal.size();
+        let visible = rows.saturating_sub(2).max(1);
+        if cursor < scroll {
+            scroll = cursor;
+        } else if cursor >= scroll + visible {
+            scroll = cursor + 1 - visible;
+        }
+        let mut screen = String::from("\x1b[H\x1b[2J");
+        let count = toggled.iter().filter(|t| **t).count();
+        screen.push_str(&format!(
+            "\x1b[2;1H\x1b[2m  {}/{}{}\x1b[0m",
+            matches.len(),
+            items.len(),
+            if count > 0 {
+                format!(" ({} chosen)", count)
+            } else {
+                String::new()
+            }
+        ));
+        for (row, (_, i)) in matches.iter().enumerate().skip(scroll).take(visible) {
+            let marker = if toggled[*i] { '*' } else { ' ' };
+            let line: String = format!("{} {}", marker, items[*i].line)
+                .chars()
+                .take(cols.saturating_sub(1))
+                .collect();
+            let style = if row == cursor { "\x1b[7m" } else { "" };
+            screen.push_str(&format!(
+                "\x1b[{};1H{}{}\x1b[0m",
+                row - scroll + 3,
+                style,
+                line
+            ));
+        }
+        screen.push_str(&format!("\x1b[1;1H> {}", query));
+        terminal.tty.write_all(screen.as_bytes())?;
+        terminal.tty.flush()?;
+
+        match terminal.key()? {
+            Key::Char(c) => {
+                query.push(c);
+                cursor = 0;
+                scroll = 0;
+            }
+            Key::Backspace => {
+                query.pop();
+            }
+            Key::Kill => query.clear(),
+            Key::Up => cursor = cursor.saturating_sub(1),
+            Key::Down => cursor += 1,
+            Key::Toggle => {
+                if let Some((_, i)) = matches.get(cursor) {
+                    toggled[*i] = !toggled[*i];
+                    cursor += 1;
+                }
+            }
+            Key::Enter => {
+                let chosen: Vec<usize> = (0..items.len()).filter(|i| toggled[*i]).collect();
+                if !chosen.is_empty() {
+                    return Ok(Some(chosen));
+                }
+                if let Some((_, i)) = matches.get(cursor) {
+                    return Ok(Some(vec![*i]));
+                }
+            }
+            Key::Cancel => return Ok(None),
+            Key::Other => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::score;
+
+    #[test]
+    fn matches_subsequences_ignoring_case() {
+        assert_eq!(score("", "vim"), Some(0));
+        assert_eq!(score("vim", "vim"), Some(16));
+        assert_eq!(score("VIM", "vim"), score("vim", "Vim"));
+        assert!(score("vm", "vim").is_some());
+        assert_eq!(score("mv", "vim"), None);
+        assert_eq!(score("vimm", "vim"), None);
+        assert_eq!(score("ä", "Änderung"), Some(4));
+    }
+
+    #[test]
+    fn prefers_runs_and_word_starts() {
+        // A run beats the same letters spread out
+        assert!(score("gtk", "gtk+3") > score("gtk", "gst-plugins-kit"));
+        // The start of a word beats the middle of one
+        assert!(score("qt", "python3-qt5") > score("qt", "python3-aqt5"));
+        assert!(score("mpv", "mpv 0.38") > score("mpv", "libmpv 0.38"));
+        // Longer gaps cost more, up to a limit
+        assert!(score("ab", "a-b") > score("ab", "a---b"));
+        assert_eq!(score("ab", "a-----b"), score("ab", "a----------b"));
+    }
+}
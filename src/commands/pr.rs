@@ -0,0 +1,109 @@
+// `vupdate pr <PKG>`: push the checked out branch of the void-packages
+// checkout to one's fork and open a pull request for it
+
+use crate::cli::{self, Args};
+use vupdate::config::Config;
+use vupdate::fetch::Fetcher;
+use vupdate::status::Status;
+use vupdate::{git, github, template, xbps};
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let pkg_name = match args.operands.as_slice() {
+        [pkg_name] => pkg_name,
+        [] => cli::usage_error(cli::CliError::MissingArgument("<PKG>")),
+        [_, extra, ..] => cli::usage_error(cli::CliError::UnexpectedArgument(extra.clone())),
+    };
+    let tested = args.value("tested").unwrap_or_else(|| "no".to_string());
+    match open(config, pkg_name, &tested, args.flag("built")).await {
+        Ok(url) => {
+            println!("{}", url);
+            0
+        }
+        Err(e) => {
+            cli::error(e);
+            1
+        }
+    }
+}
+
+// Push the branch and open the pull request, returning its URL
+async fn open(
+    config: &Config,
+    pkg_name: &str,
+    tested: &str,
+    built: bool,
+) -> Result<String, String> {
+    let distdir = config
+        .distdir
+        .as_deref()
+        .ok_or("No void-packages checkout: set 'distdir' or pass --distdir")?;
+    let token = config
+        .github_token
+        .token()
+        .map_err(|e| e.to_string())?
+        .ok_or("Opening a pull request needs a GitHub token, see 'github_token'")?;
+    let template = template::load(distdir, pkg_name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No template for {} in {}", pkg_name, distdir.display()))?;
+    let main = if template.pkgname.is_empty() {
+        pkg_name
+    } else {
+        template.pkgname.as_str()
+    };
+
+    let branch = git::current_branch(distdir)
+        .map_err(|e| e.to_string())?
+        .filter(|branch| branch != "master")
+        .ok_or("Check out the branch with the update first")?;
+    let remote = &config.fork_remote;
+    let remote_url =
+        git::run(distdir, &["remote", "get-url", remote]).map_err(|e| e.to_string())?;
+    let owner = github::remote_owner(&remote_url)
+        .ok_or_else(|| format!("Remote '{}' is not a GitHub repository", remote))?;
+    git::run_visibly(distdir, &["push", "--set-upstream", remote, &branch])
+        .map_err(|e| e.to_string())?;
+
+    let fetcher = Fetcher::new(config.fetch.clone()).map_err(|e| e.to_string())?;
+    let client = github::Client::new(fetcher.client().clone(), Some(token));
+    let title = format!("{}: update to {}.", main, template.version);
+    let head = format!("{}:{}", owner, branch);
+    let pr = client
+        .create_pull_request(&title, &head, &body(tested, built))
+        .await
+        .map_err(|e| e.to_string())?;
+    super::record_status(main, Status::PrFiled, &template.version, Some(pr.number));
+    Ok(pr.url)
+}
+
+// The pull request description, filled in like void-packages' pull request
+// template asks. The local build section is only included if the package
+// was built.
+fn body(tested: &str, built: bool) -> String {
+    let tested = match tested {
+        "yes" => "**YES**",
+        "briefly" => "**briefly**",
+        _ => "**NO**",
+    };
+    let mut body = format!(
+        "#### Testing the changes\n- I tested the changes in this PR: {}\n",
+        tested
+    );
+    let arch = if built { native_arch() } else { None };
+    if let Some(arch) = arch {
+        body.push_str(&format!(
+            "\n#### Local build testing\n- I built this PR locally for my native architecture, ({})\n",
+            arch
+        ));
+    }
+    body
+}
+
+// The native architecture as void-packages names it, e.g. `x86_64-glibc`
+fn native_arch() -> Option<String> {
+    let arch = xbps::native_arch()?;
+    if arch.contains('-') {
+        Some(arch)
+    } else {
+        Some(format!("{}-glibc", arch))
+    }
+}
@@ -0,0 +1,37 @@
+// `vupdate report --html FILE [PATTERN]...`: the updates `check` would list,
+// as a page to publish
+
+use super::check;
+use crate::cli::{self, Args, CliError};
+use vupdate::config::{Config, Section};
+use vupdate::output;
+use vupdate::parse::UpdateMap;
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let path = args
+        .value("html")
+        .unwrap_or_else(|| cli::usage_error(CliError::MissingArgument("--html FILE")));
+    let sort = check::sort_key(args);
+
+    let (sections, failed) = match check::updates(config, args, false).await {
+        Some(found) => found,
+        None => return 1,
+    };
+    let sections: Vec<(Section, &UpdateMap)> = sections
+        .iter()
+        .map(|(section, updates)| (*section, updates))
+        .collect();
+    let page = output::html(&sections, sort);
+
+    if path == "-" {
+        print!("{}", page);
+    } else if let Err(e) = std::fs::write(&path, page) {
+        cli::error(format!("Could not write {}: {}", path, e));
+        return 1;
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,214 @@
+// `vupdate revbump <PKG>`: after building a library in the void-packages
+// checkout, tell which packages need a revbump because sonames it provided
+// are gone, and with --commit bump their revisions like xtools' xrevbump
+
+use crate::cli::{self, Args};
+use colored::Colorize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use vupdate::config::Config;
+use vupdate::{git, template, xbps};
+
+pub fn run(config: &Config, args: &Args) -> i32 {
+    let pkg_name = match args.operands.as_slice() {
+        [pkg_name] => pkg_name,
+        [] => cli::usage_error(cli::CliError::MissingArgument("<PKG>")),
+        [_, extra, ..] => cli::usage_error(cli::CliError::UnexpectedArgument(extra.clone())),
+    };
+    let distdir = match &config.distdir {
+        Some(distdir) => distdir,
+        None => {
+            cli::error("No void-packages checkout: set 'distdir' or pass --distdir");
+            return 1;
+        }
+    };
+    let template = match template::load(distdir, pkg_name) {
+        Ok(Some(template)) => template,
+        Ok(None) => {
+            cli::error(format!(
+                "No template for {} in {}",
+                pkg_name,
+                distdir.display()
+            ));
+            return 1;
+        }
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    let main = if template.pkgname.is_empty() {
+        pkg_name.clone()
+    } else {
+        template.pkgname.clone()
+    };
+    let mut packages = vec![main.clone()];
+    match template::subpackages(distdir, &main) {
+        Ok(subpackages) => packages.extend(subpackages),
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    }
+
+    // The sonames of the binaries in the repositories against those of the
+    // binaries just built
+    let repositories = local_repositories(distdir, &template.repository);
+    let mut before = BTreeSet::new();
+    let mut after = BTreeSet::new();
+    let mut built = false;
+    for name in &packages {
+        let provided = xbps::shlib_provides(name, &[])
+            .and_then(|old| Ok((old, xbps::shlib_provides(name, &repositories)?)));
+        match provided {
+            Ok((old, new)) => {
+                built |= new.is_some();
+                before.extend(old.unwrap_or_default());
+                after.extend(new.unwrap_or_default());
+            }
+            Err(e) => {
+                cli::error(e);
+                return 1;
+            }
+        }
+    }
+    if !built {
+        cli::error(format!(
+            "{} is not built in {}, build it first, e.g. with vupdate build {}",
+            main,
+            distdir.display(),
+            main
+        ));
+        return 1;
+    }
+    let removed: Vec<&String> = before.difference(&after).collect();
+    let added: Vec<&String> = after.difference(&before).collect();
+    if removed.is_empty() {
+        println!(
+            "{} provides the same sonames, nothing needs a revbump",
+            main
+        );
+        return 0;
+    }
+    for soname in &removed {
+        println!("{} {}", "-".red(), soname);
+    }
+    for soname in &added {
+        println!("{} {}", "+".green(), soname);
+    }
+    // xbps-src refuses to build against sonames common/shlibs does not list
+    let shlibs = std::fs::read_to_string(distdir.join("common/shlibs")).unwrap_or_default();
+    for soname in &added {
+        let listed = shlibs
+            .lines()
+            .any(|line| line.split_whitespace().next() == Some(soname.as_str()));
+        if !listed {
+            let notice = format!("{} is missing from common/shlibs", soname);
+            eprintln!("{}", notice.yellow());
+        }
+    }
+
+    // The source packages linking to the sonames that are gone
+    let mut dependents = BTreeSet::new();
+    for soname in &removed {
+        match xbps::shlib_dependents(soname) {
+            Ok(pkgvers) => {
+                for pkgver in pkgvers {
+                    let name = xbps::split_pkgver(&pkgver).map_or(pkgver.as_str(), |(n, _)| n);
+                    let source =
+                        template::source_package(distdir, name).unwrap_or_else(|| name.to_string());
+                    if !packages.contains(&source) {
+                        dependents.insert(source);
+                    }
+                }
+            }
+            Err(e) => {
+                cli::error(e);
+                return 1;
+            }
+        }
+    }
+    if dependents.is_empty() {
+        println!("No package in the repositories links to the removed sonames");
+        return 0;
+    }
+
+    if !args.flag("commit") {
+        println!("Needs a revbump:");
+        for dependent in &dependents {
+            println!("  {}", dependent);
+        }
+        return 0;
+    }
+    let message = format!("rebuild for {}-{}.", main, template.version);
+    let mut failed = false;
+    for dependent in &dependents {
+        match revbump(distdir, dependent, &message, args.flag("sign")) {
+            Ok((old, new)) => println!("{}: revision {} -> {} (committed)", dependent, old, new),
+            Err(e) => {
+                cli::error(format!("{}: {}", dependent, e));
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+// The local repositories xbps-src puts the packages of the checkout at
+// `distdir` in: `hostdir/binpkgs`, or a directory named after the checked
+// out branch in it, and their subdirectory for packages going to
+// `repository`, such as `nonfree`
+fn local_repositories(distdir: &Path, repository: &str) -> Vec<PathBuf> {
+    let binpkgs = distdir.join("hostdir").join("binpkgs");
+    let mut dirs = Vec::new();
+    if let Ok(Some(branch)) = git::current_branch(distdir) {
+        dirs.push(binpkgs.join(branch));
+    }
+    dirs.push(binpkgs);
+    dirs.into_iter()
+        .map(|dir| match repository {
+            "" => dir,
+            repository => dir.join(repository),
+        })
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+// Increase the revision of the template of `pkg_name` and commit it with
+// `<pkg>: <message>`, like `xrevbump` does, returning the old and new
+// revision
+fn revbump(
+    distdir: &Path,
+    pkg_name: &str,
+    message: &str,
+    sign: bool,
+) -> Result<(u32, u32), String> {
+    let text = template::load_text(distdir, pkg_name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("no template in {}", distdir.display()))?;
+    let old = template::parse(&text);
+    let revision: u32 = old
+        .revision
+        .parse()
+        .map_err(|_| format!("invalid revision {:?}", old.revision))?;
+    let text = template::set_field(&text, "revision", &(revision + 1).to_string())
+        .ok_or_else(|| "the template has no revision".to_string())?;
+    let path = template::path(distdir, pkg_name);
+    std::fs::write(&path, text)
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+
+    let relative = format!("srcpkgs/{}/template", pkg_name);
+    git::run(distdir, &["add", "--", &relative]).map_err(|e| e.to_string())?;
+    let message = format!("{}: {}", pkg_name, message);
+    let mut args = vec!["commit", "--quiet", "--message", &message];
+    if sign {
+        args.push("--gpg-sign");
+    }
+    args.extend(["--", relative.as_str()]);
+    git::run(distdir, &args).map_err(|e| e.to_string())?;
+    Ok((revision, revision + 1))
+}
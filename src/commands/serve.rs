@@ -0,0 +1,115 @@
+// `vupdate serve [PATTERN]...`: check for updates on an interval like `watch`
+// and serve what the last check found over HTTP, in the JSON of
+// `check --output json`:
+//
+// - `/updates`: all the updates
+// - `/maintainer`, `/installed`, `/watched`, ...: the updates of one section
+// - `/history`: the runs recorded in the history, oldest first
+// - `/metrics`: figures for Prometheus, see `metrics.rs`
+
+use super::{check, watch};
+use crate::cli::{self, Args};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use vupdate::config::{Config, Section};
+use vupdate::history;
+use vupdate::http::{self, Request, Response};
+use vupdate::metrics::Metrics;
+use vupdate::output::{self, Format, SortKey};
+use vupdate::parse::UpdateMap;
+
+const DEFAULT_LISTEN: &str = "127.0.0.1:8642";
+
+#[derive(Default)]
+struct State {
+    // The updates found by the last check, none before the first one finishes
+    found: Option<Vec<(Section, UpdateMap)>>,
+    metrics: Metrics,
+}
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let interval = watch::interval(args);
+    let address = args
+        .value("listen")
+        .unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+    let listener = match TcpListener::bind(&address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            cli::error(format!("Could not listen on {}: {}", address, e));
+            return 1;
+        }
+    };
+    if let Ok(address) = listener.local_addr() {
+        println!("Serving on http://{}", address);
+    }
+
+    let state = Arc::new(Mutex::new(State::default()));
+    let shared = state.clone();
+    tokio::spawn(http::serve(listener, move |request| {
+        handle(request, &shared.lock().unwrap())
+    }));
+
+    loop {
+        let found = check::updates(config, args, false).await;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let sections = found.as_ref().map(|(sections, _)| sections.as_slice());
+        let failed = found.as_ref().is_none_or(|(_, failed)| *failed);
+        state.lock().unwrap().metrics.record(now, sections, failed);
+        // Until a check succeeds, the updates of the last one are served
+        if let Some((sections, failed)) = found {
+            watch::print_summary(now, &sections, failed);
+            state.lock().unwrap().found = Some(sections);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+fn handle(request: &Request, state: &State) -> Response {
+    let path = request.path.trim_end_matches('/');
+    if path == "/metrics" {
+        return metrics_response(&state.metrics);
+    }
+    if path == "/history" {
+        return match history::load() {
+            Ok(runs) => {
+                let runs: Vec<Value> = runs.iter().map(|run| run.to_json()).collect();
+                Response::json(200, &Value::Array(runs))
+            }
+            Err(e) => Response::error(500, &format!("could not read the history: {}", e)),
+        };
+    }
+
+    let wanted = match path {
+        "/updates" => None,
+        _ => match path.strip_prefix('/').and_then(Section::from_name) {
+            Some(section) => Some(section),
+            None => return Response::error(404, "not found"),
+        },
+    };
+    let sections = match &state.found {
+        Some(sections) => sections,
+        None => return Response::error(503, "the first check has not finished yet"),
+    };
+    let sections: Vec<(Section, &UpdateMap)> = sections
+        .iter()
+        .filter(|(section, _)| wanted.is_none_or(|wanted| wanted == *section))
+        .map(|(section, updates)| (*section, updates))
+        .collect();
+    Response {
+        status: 200,
+        content_type: "application/json",
+        body: output::render(&sections, &Format::Json, SortKey::Name),
+    }
+}
+
+pub fn metrics_response(metrics: &Metrics) -> Response {
+    Response {
+        status: 200,
+        content_type: "text/plain; version=0.0.4",
+        body: metrics.render(),
+    }
+}
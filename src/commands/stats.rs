@@ -0,0 +1,132 @@
+// `vupdate stats`: figures about the updates recorded in the history
+
+use crate::cli::{self, Args, CliError};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use vupdate::config::Config;
+use vupdate::date;
+use vupdate::history::{self, Run};
+
+// How many of the longest pending packages are listed by default
+const DEFAULT_TOP: usize = 10;
+// How many weeks the trend shows at most
+const WEEKS: usize = 8;
+
+pub fn run(config: &Config, args: &Args) -> i32 {
+    if let Some(extra) = args.operands.first() {
+        cli::usage_error(CliError::UnexpectedArgument(extra.clone()));
+    }
+    let top = match args.value("top") {
+        Some(top) => top
+            .parse()
+            .unwrap_or_else(|_| cli::usage_error(CliError::invalid_value("top", top))),
+        None => DEFAULT_TOP,
+    };
+    let runs = match history::load() {
+        Ok(runs) => runs,
+        Err(e) => {
+            cli::error(format!("Could not read the history: {}", e));
+            return 1;
+        }
+    };
+    let latest = match runs.last() {
+        Some(latest) => latest,
+        None => {
+            cli::error("No runs recorded yet, run 'vupdate check' first");
+            return 1;
+        }
+    };
+
+    heading(&format!(
+        "Updates on {}:",
+        date::format_timestamp(latest.time)
+    ));
+    let mut sections: BTreeMap<&str, usize> = BTreeMap::new();
+    for update in latest.updates.values() {
+        *sections.entry(&update.section).or_default() += 1;
+    }
+    let mut rows: Vec<(String, String)> = sections
+        .into_iter()
+        .map(|(section, count)| (section.to_string(), count.to_string()))
+        .collect();
+    rows.push(("total".to_string(), latest.updates.len().to_string()));
+    print_rows(&rows);
+
+    // Only the templates of a local checkout can be read for all packages
+    if let Some(distdir) = &config.distdir {
+        heading("\nBy maintainer:");
+        let maintainers = super::maintainers::by_maintainer(distdir, latest.updates.keys());
+        let rows: Vec<(String, String)> = maintainers
+            .into_iter()
+            .map(|(maintainer, count)| (maintainer, count.to_string()))
+            .collect();
+        print_rows(&rows);
+    }
+
+    if top > 0 {
+        heading("\nLongest pending:");
+        let seen = history::first_seen(&runs[..runs.len() - 1], latest);
+        let mut pending: Vec<(&String, u64)> = seen
+            .iter()
+            .map(|(name, seen)| (name, seen.package))
+            .collect();
+        pending.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+        let rows: Vec<(String, String)> = pending
+            .into_iter()
+            .take(top)
+            .map(|(name, first)| {
+                let update = &latest.updates[name];
+                let days = latest.time.saturating_sub(first) / 86_400;
+                let versions = format!(
+                    "{:>4}d  {} -> {}",
+                    days, update.current_version, update.new_version
+                );
+                (name.clone(), versions)
+            })
+            .collect();
+        print_rows(&rows);
+    }
+
+    heading("\nUpdates appearing (+) and resolved (-) per week:");
+    let rows: Vec<(String, String)> = trend(&runs)
+        .into_iter()
+        .map(|(week, (appeared, resolved))| {
+            let counts = format!("+{:<5} -{}", appeared, resolved);
+            (week, counts)
+        })
+        .collect();
+    if rows.is_empty() {
+        println!("  Not enough history yet");
+    }
+    print_rows(&rows);
+    0
+}
+
+// The updates appearing and going away each week, by the Monday starting
+// it, for the last `WEEKS` weeks with any changes
+fn trend(runs: &[Run]) -> Vec<(String, (usize, usize))> {
+    let mut weeks: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for pair in runs.windows(2) {
+        let diff = history::diff(&pair[0], &pair[1]);
+        let week = weeks.entry(date::week_of(pair[1].time)).or_default();
+        week.0 += diff.added.len();
+        week.1 += diff.removed.len();
+    }
+    let skip = weeks.len().saturating_sub(WEEKS);
+    weeks.into_iter().skip(skip).collect()
+}
+
+fn heading(text: &str) {
+    println!("{}", text.bold());
+}
+
+fn print_rows(rows: &[(String, String)]) {
+    let width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (label, value) in rows {
+        println!("  {:width$}  {}", label, value, width = width);
+    }
+}
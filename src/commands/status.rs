@@ -0,0 +1,118 @@
+// `vupdate status set|show`: track where the work on each update stands
+
+use crate::cli::{self, Args, CliError};
+use vupdate::config::Config;
+use vupdate::date;
+use vupdate::fetch::Fetcher;
+use vupdate::status::{Entry, Status, StatusStore};
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let command: Vec<&str> = args.operands.iter().map(String::as_str).collect();
+    let mut store = match StatusStore::load() {
+        Ok(store) => store,
+        Err(e) => {
+            cli::error(format!("Could not read the status file: {}", e));
+            return 1;
+        }
+    };
+
+    match command.as_slice() {
+        ["show", pkgs @ ..] => {
+            show(&store, pkgs);
+            return 0;
+        }
+        ["set", pkg_name, status] => {
+            let status = match Status::from_name(status) {
+                Some(status) => status,
+                None => {
+                    let names: Vec<&str> = Status::ALL.iter().map(|s| s.name()).collect();
+                    cli::error(format!(
+                        "Unknown status '{}', expected one of {}",
+                        status,
+                        names.join(", ")
+                    ));
+                    return 2;
+                }
+            };
+            let pr = args.value("pr").map(|pr| {
+                pr.trim_start_matches('#')
+                    .parse()
+                    .unwrap_or_else(|_| cli::usage_error(CliError::invalid_value("pr", pr)))
+            });
+            let version = known_version(config, pkg_name).await;
+            // A pull request stays known for as long as the work is on the
+            // same version
+            let pr = pr.or_else(|| {
+                store
+                    .get(pkg_name)
+                    .filter(|e| version.as_deref().is_none_or(|v| e.applies_to(v)))
+                    .and_then(|e| e.pr)
+            });
+            store.set(Entry {
+                pkg_name: pkg_name.to_string(),
+                status,
+                version,
+                pr,
+                date: date::today(),
+            });
+        }
+        ["set", ..] => cli::usage_error(CliError::MissingArgument("<PKG> <STATUS>")),
+        [] => cli::usage_error(CliError::MissingArgument("set or show")),
+        [other, ..] => cli::usage_error(CliError::UnexpectedArgument(other.to_string())),
+    }
+
+    if let Err(e) = store.save() {
+        cli::error(format!("Could not write the status file: {}", e));
+        return 1;
+    }
+    0
+}
+
+// List the tracked packages, or the given ones
+fn show(store: &StatusStore, pkgs: &[&str]) {
+    let entries: Vec<&Entry> = store
+        .entries()
+        .filter(|e| pkgs.is_empty() || pkgs.contains(&e.pkg_name.as_str()))
+        .collect();
+    let name_w = entries.iter().map(|e| e.pkg_name.len()).max().unwrap_or(0);
+    let status_w = entries
+        .iter()
+        .map(|e| e.status.name().len())
+        .max()
+        .unwrap_or(0);
+    let version_w = entries
+        .iter()
+        .map(|e| e.version.as_deref().unwrap_or("").len())
+        .max()
+        .unwrap_or(0);
+    for entry in entries {
+        let mut line = format!(
+            "{:name_w$}  {:status_w$}  {:version_w$}  {}",
+            entry.pkg_name,
+            entry.status.name(),
+            entry.version.as_deref().unwrap_or(""),
+            entry.date,
+            name_w = name_w,
+            status_w = status_w,
+            version_w = version_w
+        );
+        if let Some(pr) = entry.pr {
+            line.push_str(&format!("  #{}", pr));
+        }
+        println!("{}", line);
+    }
+    for pkg_name in pkgs {
+        if store.get(pkg_name).is_none() {
+            println!("{} is new", pkg_name);
+        }
+    }
+}
+
+// The new version of the package in the cached updates list, so that the
+// status is tied to the update the work is on. Nothing is fetched for this.
+async fn known_version(config: &Config, pkg_name: &str) -> Option<String> {
+    let mut options = config.fetch.clone();
+    options.offline = true;
+    let updates = Fetcher::new(options).ok()?.all_updates().await.ok()?;
+    updates.0.get(pkg_name).map(|u| u.new_version.clone())
+}
@@ -0,0 +1,122 @@
+// `vupdate systemd-install`: set up a systemd user timer running
+// `vupdate check`, so that a new machine needs a single command
+
+use crate::cli::{self, Args, CliError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DEFAULT_CALENDAR: &str = "daily";
+
+pub fn run(args: &Args) -> i32 {
+    if let Some(extra) = args.operands.first() {
+        cli::usage_error(CliError::UnexpectedArgument(extra.clone()));
+    }
+    let calendar = args
+        .value("on-calendar")
+        .unwrap_or_else(|| DEFAULT_CALENDAR.to_string());
+    if calendar.trim().is_empty() || calendar.contains('\n') {
+        cli::usage_error(CliError::invalid_value("on-calendar", calendar));
+    }
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            cli::error(format!("Could not tell where vupdate is installed: {}", e));
+            return 1;
+        }
+    };
+    let dir = match unit_dir() {
+        Some(dir) => dir,
+        None => {
+            cli::error("HOME is not set");
+            return 1;
+        }
+    };
+
+    let units = [
+        ("vupdate.service", service(&exe)),
+        ("vupdate.timer", timer(&calendar)),
+    ];
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        cli::error(format!("Could not create {}: {}", dir.display(), e));
+        return 1;
+    }
+    for (name, text) in &units {
+        let path = dir.join(name);
+        if let Err(e) = std::fs::write(&path, text) {
+            cli::error(format!("Could not write {}: {}", path.display(), e));
+            return 1;
+        }
+        println!("Wrote {}", path.display());
+    }
+
+    let systemctl = |args: &[&str]| {
+        Command::new("systemctl")
+            .arg("--user")
+            .args(args)
+            .status()
+            .map(|status| status.success())
+    };
+    match systemctl(&["daemon-reload"])
+        .and_then(|ok| Ok(ok && systemctl(&["enable", "--now", "vupdate.timer"])?))
+    {
+        Ok(true) => {
+            println!("Enabled vupdate.timer ({})", calendar);
+            0
+        }
+        Ok(false) => 1,
+        Err(e) => {
+            cli::error(format!("Could not run systemctl: {}", e));
+            println!("Enable the timer with: systemctl --user enable --now vupdate.timer");
+            1
+        }
+    }
+}
+
+// Where systemd looks for the units of the user's own
+fn unit_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("systemd/user"))
+}
+
+// The service running the check. Finding updates exits with 10, which is not
+// a failure; the notifications configured in config.toml are sent by the
+// check itself, as every recorded check sends them. In the path, systemd
+// would take `%` for a specifier and `$` for a variable.
+fn service(exe: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Check for updates of Void Linux packages\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart=\"{}\" check --only-new --color never\n\
+         SuccessExitStatus=10\n",
+        exe.display()
+            .to_string()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+            .replace('$', "$$")
+    )
+}
+
+fn timer(calendar: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Check for updates of Void Linux packages regularly\n\
+         \n\
+         [Timer]\n\
+         OnCalendar={}\n\
+         Persistent=true\n\
+         RandomizedDelaySec=15m\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n",
+        calendar
+    )
+}
@@ -0,0 +1,330 @@
+// `vupdate watch [PATTERN]...`: check for updates on an interval, keeping the
+// history and sending the notifications, without needing cron. With --dbus,
+// the updates found by the last check are also served on the session bus, and
+// with --listen, metrics about the checks over HTTP.
+
+use super::{check, serve};
+use crate::cli::{self, Args, CliError};
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use vupdate::config::{Config, Section};
+use vupdate::dbus::{self, Message, MessageType, Value};
+use vupdate::http::{self, Response};
+use vupdate::metrics::Metrics;
+use vupdate::parse::UpdateMap;
+use vupdate::{date, duration};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// Where the D-Bus interface is found
+const BUS_NAME: &str = "io.github.kartikynwa.Vupdate";
+const OBJECT_PATH: &str = "/io/github/kartikynwa/Vupdate";
+const INTERFACE: &str = "io.github.kartikynwa.Vupdate";
+
+const INTROSPECTION: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="io.github.kartikynwa.Vupdate">
+    <property name="Count" type="u" access="read"/>
+    <property name="Counts" type="a{su}" access="read"/>
+    <property name="Packages" type="as" access="read"/>
+    <property name="Updates" type="a(ssss)" access="read"/>
+    <property name="LastCheck" type="t" access="read"/>
+  </interface>
+  <interface name="org.freedesktop.DBus.Properties">
+    <method name="Get">
+      <arg name="interface" direction="in" type="s"/>
+      <arg name="name" direction="in" type="s"/>
+      <arg name="value" direction="out" type="v"/>
+    </method>
+    <method name="GetAll">
+      <arg name="interface" direction="in" type="s"/>
+      <arg name="properties" direction="out" type="a{sv}"/>
+    </method>
+    <signal name="PropertiesChanged">
+      <arg name="interface" type="s"/>
+      <arg name="changed" type="a{sv}"/>
+      <arg name="invalidated" type="as"/>
+    </signal>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg name="xml" direction="out" type="s"/>
+    </method>
+  </interface>
+  <interface name="org.freedesktop.DBus.Peer">
+    <method name="Ping"/>
+  </interface>
+</node>
+"#;
+
+// The updates found by the last check
+#[derive(Debug, Default)]
+struct Snapshot {
+    time: u64,
+    // Package, section, current and new version, by section and name
+    updates: Vec<(String, Section, String, String)>,
+}
+
+impl Snapshot {
+    fn properties(&self) -> Vec<(String, Value)> {
+        let mut counts: Vec<(Section, u32)> = Vec::new();
+        for (_, section, _, _) in &self.updates {
+            match counts.iter_mut().find(|(s, _)| s == section) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*section, 1)),
+            }
+        }
+        let counts = counts
+            .into_iter()
+            .map(|(section, count)| {
+                Value::DictEntry(
+                    Box::new(Value::Str(section.name().to_string())),
+                    Box::new(Value::U32(count)),
+                )
+            })
+            .collect();
+        let updates = self
+            .updates
+            .iter()
+            .map(|(name, section, current, new)| {
+                Value::Struct(vec![
+                    Value::Str(name.clone()),
+                    Value::Str(section.name().to_string()),
+                    Value::Str(current.clone()),
+                    Value::Str(new.clone()),
+                ])
+            })
+            .collect();
+        vec![
+            ("Count".to_string(), Value::U32(self.updates.len() as u32)),
+            (
+                "Counts".to_string(),
+                Value::Array("{su}".to_string(), counts),
+            ),
+            (
+                "Packages".to_string(),
+                Value::strings(self.updates.iter().map(|(name, _, _, _)| name.clone())),
+            ),
+            (
+                "Updates".to_string(),
+                Value::Array("(ssss)".to_string(), updates),
+            ),
+            ("LastCheck".to_string(), Value::U64(self.time)),
+        ]
+    }
+}
+
+pub async fn run(config: &Config, args: &Args) -> i32 {
+    let interval = interval(args);
+    let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+    let metrics = match args.value("listen") {
+        Some(address) => match listen(&address).await {
+            Ok(metrics) => Some(metrics),
+            Err(e) => {
+                cli::error(format!("Could not listen on {}: {}", address, e));
+                return 1;
+            }
+        },
+        None => None,
+    };
+    let bus = if args.flag("dbus") {
+        match serve(snapshot.clone()).await {
+            Ok(bus) => Some(bus),
+            Err(e) => {
+                cli::error(format!("Could not serve on the session bus: {}", e));
+                return 1;
+            }
+        }
+    } else {
+        None
+    };
+
+    loop {
+        let found = check::updates(config, args, true).await;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        if let Some(metrics) = &metrics {
+            let sections = found.as_ref().map(|(sections, _)| sections.as_slice());
+            let failed = found.as_ref().is_none_or(|(_, failed)| *failed);
+            metrics.lock().unwrap().record(now, sections, failed);
+        }
+        // Errors were reported by `updates`, and the next round may go better
+        if let Some((sections, failed)) = found {
+            print_summary(now, &sections, failed);
+
+            let mut snapshot = snapshot.lock().await;
+            snapshot.time = now;
+            snapshot.updates = sections
+                .iter()
+                .flat_map(|(section, updates)| {
+                    updates.sorted().into_iter().map(move |(name, u)| {
+                        let (current, new) = (u.current_version.clone(), u.new_version.clone());
+                        (name.clone(), *section, current, new)
+                    })
+                })
+                .collect();
+            if let Some(bus) = &bus {
+                let changed = Message::signal(
+                    OBJECT_PATH,
+                    "org.freedesktop.DBus.Properties",
+                    "PropertiesChanged",
+                    vec![
+                        Value::Str(INTERFACE.to_string()),
+                        Value::dict(snapshot.properties()),
+                        Value::strings(Vec::new()),
+                    ],
+                );
+                if let Err(e) = bus.lock().await.send(&changed).await {
+                    cli::error(format!("Could not signal on the session bus: {}", e));
+                }
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+// The --interval between checks
+pub fn interval(args: &Args) -> Duration {
+    match args.value("interval") {
+        Some(text) => duration::parse(&text)
+            .filter(|d| !d.is_zero())
+            .unwrap_or_else(|| cli::usage_error(CliError::invalid_value("interval", text))),
+        None => DEFAULT_INTERVAL,
+    }
+}
+
+// The line printed after each check
+pub fn print_summary(time: u64, sections: &[(Section, UpdateMap)], failed: bool) {
+    let updates = sections.iter().flat_map(|(_, updates)| updates.0.values());
+    let total = updates.clone().count();
+    let new = updates
+        .filter(|u| u.tags.iter().any(|t| t == "NEW"))
+        .count();
+    println!(
+        "{}: {} updates, {} new{}",
+        date::format_timestamp(time),
+        total,
+        new,
+        if failed { ", with errors" } else { "" }
+    );
+}
+
+// Serve `/metrics` on `address` in the background
+async fn listen(address: &str) -> io::Result<Arc<std::sync::Mutex<Metrics>>> {
+    let listener = TcpListener::bind(address).await?;
+    let metrics = Arc::new(std::sync::Mutex::new(Metrics::default()));
+    let shared = metrics.clone();
+    tokio::spawn(http::serve(listener, move |request| {
+        match request.path.trim_end_matches('/') {
+            "/metrics" => serve::metrics_response(&shared.lock().unwrap()),
+            _ => Response::error(404, "not found"),
+        }
+    }));
+    Ok(metrics)
+}
+
+// Take `BUS_NAME` on the session bus and answer the calls on `OBJECT_PATH`
+// in the background, returning the connection to send signals on
+async fn serve(snapshot: Arc<Mutex<Snapshot>>) -> io::Result<Arc<Mutex<dbus::Sender>>> {
+    let (mut sender, mut receiver) = dbus::session_bus().await?;
+    let call = |member: &str, body| {
+        Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            member,
+            body,
+        )
+    };
+    let hello = sender.send(&call("Hello", Vec::new())).await?;
+    wait_for_reply(&mut receiver, hello).await?;
+    // Flags: replace an existing owner that allows it, do not queue
+    let request = call(
+        "RequestName",
+        vec![Value::Str(BUS_NAME.to_string()), Value::U32(2 | 4)],
+    );
+    let request = sender.send(&request).await?;
+    match wait_for_reply(&mut receiver, request).await?.body.first() {
+        // The primary owner, or already it
+        Some(Value::U32(1)) | Some(Value::U32(4)) => {}
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is owned by another process", BUS_NAME),
+            ))
+        }
+    }
+
+    let sender = Arc::new(Mutex::new(sender));
+    let replies = sender.clone();
+    tokio::spawn(async move {
+        while let Ok(message) = receiver.receive().await {
+            if message.kind != MessageType::MethodCall {
+                continue;
+            }
+            let reply = answer(&message, &*snapshot.lock().await);
+            if replies.lock().await.send(&reply).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(sender)
+}
+
+async fn wait_for_reply(receiver: &mut dbus::Receiver, serial: u32) -> io::Result<Message> {
+    loop {
+        let message = receiver.receive().await?;
+        if message.reply_serial != Some(serial) {
+            continue;
+        }
+        if message.kind == MessageType::Error {
+            let name = message.error_name.unwrap_or_default();
+            return Err(io::Error::other(name));
+        }
+        return Ok(message);
+    }
+}
+
+// The reply to a method call
+fn answer(call: &Message, snapshot: &Snapshot) -> Message {
+    let interface = call.interface.as_deref().unwrap_or("");
+    let member = call.member.as_deref().unwrap_or("");
+    let args: Vec<&str> = call.body.iter().filter_map(Value::as_str).collect();
+    if call.path.as_deref() != Some(OBJECT_PATH) {
+        return call.error("org.freedesktop.DBus.Error.UnknownObject", "No such object");
+    }
+    match (interface, member, args.as_slice()) {
+        ("org.freedesktop.DBus.Properties", "Get", [INTERFACE, name]) => {
+            match snapshot.properties().into_iter().find(|(n, _)| n == name) {
+                Some((_, value)) => call.reply(vec![Value::Variant(Box::new(value))]),
+                None => call.error(
+                    "org.freedesktop.DBus.Error.UnknownProperty",
+                    &format!("No property {}", name),
+                ),
+            }
+        }
+        ("org.freedesktop.DBus.Properties", "GetAll", [INTERFACE]) => {
+            call.reply(vec![Value::dict(snapshot.properties())])
+        }
+        ("org.freedesktop.DBus.Properties", "GetAll", [_]) => {
+            call.reply(vec![Value::dict(Vec::new())])
+        }
+        ("org.freedesktop.DBus.Properties", "Set", _) => call.error(
+            "org.freedesktop.DBus.Error.PropertyReadOnly",
+            "The properties are read-only",
+        ),
+        ("org.freedesktop.DBus.Introspectable", "Introspect", _) => {
+            call.reply(vec![Value::Str(INTROSPECTION.to_string())])
+        }
+        ("org.freedesktop.DBus.Peer", "Ping", _) => call.reply(Vec::new()),
+        _ => call.error(
+            "org.freedesktop.DBus.Error.UnknownMethod",
+            &format!("No method {}.{}", interface, member),
+        ),
+    }
+}
@@ -0,0 +1,99 @@
+// `vupdate worktree list|clean`: manage the worktrees of the void-packages
+// checkout made by `bump --worktree`
+
+use crate::cli::{self, Args};
+use std::path::Path;
+use vupdate::config::Config;
+use vupdate::git::{self, Worktree};
+
+pub fn run(config: &Config, args: &Args) -> i32 {
+    let command: Vec<&str> = args.operands.iter().map(String::as_str).collect();
+    let (subcommand, pkgs) = match command.as_slice() {
+        [] => cli::usage_error(cli::CliError::MissingArgument("list or clean")),
+        ["list", extra, ..] => {
+            cli::usage_error(cli::CliError::UnexpectedArgument(extra.to_string()))
+        }
+        [subcommand @ ("list" | "clean"), pkgs @ ..] => (*subcommand, pkgs),
+        [other, ..] => cli::usage_error(cli::CliError::UnexpectedArgument(other.to_string())),
+    };
+    let (distdir, worktree_dir) = match (&config.distdir, &config.worktree_dir) {
+        (Some(distdir), Some(worktree_dir)) => (distdir, worktree_dir),
+        (None, _) => {
+            cli::error("No void-packages checkout: set 'distdir' or pass --distdir");
+            return 1;
+        }
+        (_, None) => {
+            cli::error("No directory for the worktrees: set 'worktree_dir'");
+            return 1;
+        }
+    };
+    let worktrees = match git::worktrees(distdir) {
+        Ok(worktrees) => worktrees,
+        Err(e) => {
+            cli::error(e);
+            return 1;
+        }
+    };
+    // Other worktrees of the checkout are none of vupdate's business
+    let worktrees: Vec<Worktree> = worktrees
+        .into_iter()
+        .filter(|w| w.path.starts_with(worktree_dir))
+        .collect();
+
+    match subcommand {
+        "list" => {
+            list(&worktrees);
+            0
+        }
+        _ => clean(distdir, &worktrees, pkgs),
+    }
+}
+
+fn list(worktrees: &[Worktree]) {
+    let width = worktrees.iter().map(|w| branch(w).len()).max().unwrap_or(0);
+    for worktree in worktrees {
+        println!(
+            "{:width$}  {}",
+            branch(worktree),
+            worktree.path.display(),
+            width = width
+        );
+    }
+}
+
+// Remove the worktrees of `pkgs`, or all of them. Their branches are kept,
+// since they may still have to be pushed or have a pull request open.
+fn clean(distdir: &Path, worktrees: &[Worktree], pkgs: &[&str]) -> i32 {
+    let mut failed = false;
+    for worktree in worktrees {
+        // Branches are named `<pkg>-<version>`, and versions have no dashes
+        let pkg_name = branch(worktree)
+            .rsplit_once('-')
+            .map_or("", |(pkg_name, _)| pkg_name);
+        if !pkgs.is_empty() && !pkgs.contains(&pkg_name) {
+            continue;
+        }
+        let path = worktree.path.to_string_lossy();
+        // git refuses to remove a worktree with uncommitted changes
+        match git::run(distdir, &["worktree", "remove", &path]) {
+            Ok(_) => println!("Removed {}", path),
+            Err(e) => {
+                cli::error(e);
+                failed = true;
+            }
+        }
+    }
+    if let Err(e) = git::run(distdir, &["worktree", "prune"]) {
+        cli::error(e);
+        failed = true;
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+fn branch(worktree: &Worktree) -> &str {
+    worktree.branch.as_deref().unwrap_or("(detached)")
+}
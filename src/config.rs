@@ -0,0 +1,948 @@
+// User configuration, read from `$XDG_CONFIG_HOME/vupdate/config.toml` (or
+// `~/.config/vupdate/config.toml` when XDG_CONFIG_HOME is unset). Every key
+// is optional and a missing file simply yields the defaults:
+//
+// ```
+// email = "kartik.ynwa@gmail.com"   # or a list: ["a@example.com", "b@example.com"]
+// color = "auto"   # or "always", "never", true, false
+// sections = ["maintainer", "installed", "watched"]   # "pending" is opt-in
+// distdir = "~/void-packages"   # adds "unbuilt" to the default sections
+// ```
+
+use crate::notify::{self, Notifier, Service};
+use crate::smtp::{self, Security};
+use crate::sources::Source;
+use crate::toml::{self, Table, Value};
+use crate::{duration, fetch, github, mangle, upstream, xbps};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// The sections of the report that can be enabled or disabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Maintainer,
+    Installed,
+    Watched,
+    // Binary updates already in the repository, waiting to be installed
+    Pending,
+    // Templates in the local void-packages checkout that are newer than the
+    // binary repository, i.e. builds that have not been published yet
+    Unbuilt,
+}
+
+impl Section {
+    pub fn from_name(name: &str) -> Option<Section> {
+        match name {
+            "maintainer" => Some(Section::Maintainer),
+            "installed" => Some(Section::Installed),
+            "watched" => Some(Section::Watched),
+            "pending" => Some(Section::Pending),
+            "unbuilt" => Some(Section::Unbuilt),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Section::Maintainer => "maintainer",
+            Section::Installed => "installed",
+            Section::Watched => "watched",
+            Section::Pending => "pending",
+            Section::Unbuilt => "unbuilt",
+        }
+    }
+
+    // Header printed above the section in text output
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Maintainer => "Maintainer updates:",
+            Section::Installed => "Updates for installed packages:",
+            Section::Watched => "Watched packages:",
+            Section::Pending => "Binary updates ready to install:",
+            Section::Unbuilt => "Templates ahead of the binary repository:",
+        }
+    }
+}
+
+// When to color the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    // Only when stdout is a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        match name {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    // Resolve the choice to whether colors should be used
+    pub fn enabled(self) -> bool {
+        use std::io::IsTerminal;
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+pub struct Config {
+    pub emails: Vec<String>,
+    pub color: ColorChoice,
+    pub sections: Vec<Section>,
+    pub fetch: fetch::Options,
+    // The xbps package database listing the installed packages
+    pub pkgdb: PathBuf,
+    // A local clone of void-packages
+    pub distdir: Option<PathBuf>,
+    // How to get a token for the GitHub API
+    pub github_token: github::TokenSource,
+    // The git remote of the distdir checkout pointing at one's fork
+    pub fork_remote: String,
+    // Where `bump --worktree` creates the worktrees of the distdir checkout
+    pub worktree_dir: Option<PathBuf>,
+    // Where to send notifications about new updates
+    pub notify: Vec<Notifier>,
+    // A shell command run for each new update, see `notify::run_hook`
+    pub on_new_update: Option<String>,
+    // The mail server `vupdate digest` sends through
+    pub smtp: Option<smtp::Server>,
+    // Where else to look for upstream versions
+    pub sources: Vec<Source>,
+    // The version checks of one's own, by package
+    pub upstream: BTreeMap<String, upstream::Check>,
+    // How to rewrite the upstream versions, by package
+    pub mangle: BTreeMap<String, Vec<mangle::Rule>>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            emails: Vec::new(),
+            color: ColorChoice::Auto,
+            sections: vec![Section::Maintainer, Section::Installed, Section::Watched],
+            fetch: fetch::Options::default(),
+            pkgdb: PathBuf::from(xbps::DEFAULT_PKGDB),
+            distdir: None,
+            github_token: github::TokenSource::default(),
+            fork_remote: "origin".to_string(),
+            worktree_dir: data_dir().map(|dir| dir.join("worktrees")),
+            notify: Vec::new(),
+            on_new_update: None,
+            smtp: None,
+            sources: Vec::new(),
+            upstream: BTreeMap::new(),
+            mangle: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::ParseError),
+    Invalid(PathBuf, String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Could not read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "Could not parse {}: {}", path.display(), e),
+            ConfigError::Invalid(path, msg) => write!(f, "Invalid {}: {}", path.display(), msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// Directory holding vupdate's configuration files
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("vupdate"))
+}
+
+// Directory holding the data vupdate creates, such as worktrees
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("vupdate"))
+}
+
+impl Config {
+    // Load the configuration file, falling back to the defaults if it does not
+    // exist
+    pub fn load() -> Result<Config, ConfigError> {
+        match config_dir() {
+            Some(dir) => Config::load_from(dir.join("config.toml")),
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: PathBuf) -> Result<Config, ConfigError> {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(ConfigError::Io(path, e)),
+        };
+        let table = match toml::parse(&text) {
+            Ok(table) => table,
+            Err(e) => return Err(ConfigError::Parse(path, e)),
+        };
+        Config::from_table(&table).map_err(|msg| ConfigError::Invalid(path, msg))
+    }
+
+    fn from_table(table: &Table) -> Result<Config, String> {
+        if table.contains_key("url") && table.contains_key("mirrors") {
+            return Err("only one of 'url' and 'mirrors' can be set".to_string());
+        }
+        let mut config = Config::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "email" => {
+                    config.emails = match value {
+                        Value::String(email) => vec![email.clone()],
+                        _ => expect_str_array(key, value)?
+                            .into_iter()
+                            .map(String::from)
+                            .collect(),
+                    }
+                }
+                "color" => {
+                    config.color = match value {
+                        Value::Boolean(true) => ColorChoice::Always,
+                        Value::Boolean(false) => ColorChoice::Never,
+                        Value::String(name) => ColorChoice::from_name(name)
+                            .ok_or_else(|| format!("unknown color choice '{}'", name))?,
+                        _ => return Err(type_error(key, "a boolean or string", value)),
+                    }
+                }
+                "sections" => {
+                    config.sections = expect_str_array(key, value)?
+                        .iter()
+                        .map(|name| {
+                            Section::from_name(name)
+                                .ok_or_else(|| format!("unknown section '{}'", name))
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "retries" => {
+                    config.fetch.retries = value
+                        .as_integer()
+                        .and_then(|n| u32::try_from(n).ok())
+                        .ok_or_else(|| type_error(key, "a non-negative integer", value))?
+                }
+                "timeout" => {
+                    config.fetch.timeout = match value {
+                        Value::Integer(secs) if *secs > 0 => Duration::from_secs(*secs as u64),
+                        Value::String(text) => duration::parse(text)
+                            .filter(|d| !d.is_zero())
+                            .ok_or_else(|| format!("invalid timeout '{}'", text))?,
+                        _ => return Err(type_error(key, "a duration", value)),
+                    }
+                }
+                "proxy" => {
+                    let url = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.fetch.proxy = Some(url.to_string())
+                }
+                "user_agent" => {
+                    let agent = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.fetch.user_agent = agent.to_string()
+                }
+                "pkgdb" => {
+                    let path = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.pkgdb = PathBuf::from(path)
+                }
+                "distdir" => {
+                    let path = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.distdir = Some(expand_home(path))
+                }
+                "github_token" => {
+                    let token = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.github_token.token = Some(token.to_string())
+                }
+                "token_command" => {
+                    let command = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.github_token.command = Some(command.to_string())
+                }
+                "fork_remote" => {
+                    let remote = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.fork_remote = remote.to_string()
+                }
+                "worktree_dir" => {
+                    let path = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.worktree_dir = Some(expand_home(path))
+                }
+                "on_new_update" => {
+                    let command = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.on_new_update = Some(command.to_string())
+                }
+                "notify" => {
+                    config.notify = value
+                        .as_array()
+                        .ok_or_else(|| type_error(key, "an array of tables", value))?
+                        .iter()
+                        .map(notifier)
+                        .collect::<Result<_, _>>()?
+                }
+                "smtp" => config.smtp = Some(smtp_server(value)?),
+                // Plugins are only looked for when they are asked
+                "sources" => {
+                    config.sources = expect_str_array(key, value)?
+                        .iter()
+                        .map(|name| Source::from_name(name))
+                        .collect()
+                }
+                "upstream" => {
+                    let checks = match value {
+                        Value::Table(checks) => checks,
+                        _ => return Err(type_error(key, "a table", value)),
+                    };
+                    config.upstream = checks
+                        .iter()
+                        .map(|(pkg_name, check)| {
+                            Ok((pkg_name.clone(), upstream_check(pkg_name, check)?))
+                        })
+                        .collect::<Result<_, String>>()?
+                }
+                "mangle" => {
+                    let rules = match value {
+                        Value::Table(rules) => rules,
+                        _ => return Err(type_error(key, "a table", value)),
+                    };
+                    config.mangle = rules
+                        .iter()
+                        .map(|(pkg_name, names)| {
+                            let rules = expect_str_array(&format!("mangle.{}", pkg_name), names)?
+                                .iter()
+                                .map(|name| {
+                                    mangle::Rule::from_name(name)
+                                        .ok_or_else(|| format!("unknown mangling rule '{}'", name))
+                                })
+                                .collect::<Result<_, _>>()?;
+                            Ok((pkg_name.clone(), rules))
+                        })
+                        .collect::<Result<_, String>>()?
+                }
+                "url" => {
+                    let url = value
+                        .as_str()
+                        .ok_or_else(|| type_error(key, "a string", value))?;
+                    config.fetch.mirrors = vec![url.to_string()]
+                }
+                "mirrors" => {
+                    let mirrors = expect_str_array(key, value)?;
+                    if mirrors.is_empty() {
+                        return Err("'mirrors' should not be empty".to_string());
+                    }
+                    config.fetch.mirrors = mirrors.into_iter().map(String::from).collect()
+                }
+                _ => return Err(format!("unknown key '{}'", key)),
+            }
+        }
+        if config.distdir.is_some() && !table.contains_key("sections") {
+            config.sections.push(Section::Unbuilt);
+        }
+        Ok(config)
+    }
+
+    pub fn shows(&self, section: Section) -> bool {
+        self.sections.contains(&section)
+    }
+
+    // `version` of `pkg_name` from upstream, rewritten by the package's
+    // mangling rules
+    pub fn mangled(&self, pkg_name: &str, version: &str) -> String {
+        match self.mangle.get(pkg_name) {
+            Some(rules) => mangle::apply(rules, version),
+            None => version.to_string(),
+        }
+    }
+}
+
+// Replace a leading `~/` with the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// A `[[notify]]` table
+fn notifier(value: &Value) -> Result<Notifier, String> {
+    let table = match value {
+        Value::Table(table) => table,
+        _ => return Err(type_error("notify", "an array of tables", value)),
+    };
+    let string = |key: &str| match table.get(key) {
+        Some(value) => value
+            .as_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or_else(|| type_error(&format!("notify.{}", key), "a string", value)),
+        None => Ok(None),
+    };
+    let service = string("service")?.ok_or("'notify' needs a 'service'")?;
+    // Plugins are only looked for when a notification is sent
+    let service = Service::from_name(&service);
+    let sections = match table.get("sections") {
+        Some(value) => expect_str_array("notify.sections", value)?
+            .iter()
+            .map(|name| {
+                Section::from_name(name).ok_or_else(|| format!("unknown section '{}'", name))
+            })
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+    let headers = match table.get("headers") {
+        Some(Value::Table(headers)) => headers
+            .iter()
+            .map(|(name, value)| match value.as_str() {
+                Some(value) => Ok((name.clone(), value.to_string())),
+                None => Err(type_error(
+                    &format!("notify.headers.{}", name),
+                    "a string",
+                    value,
+                )),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(value) => return Err(type_error("notify.headers", "a table", value)),
+        None => Vec::new(),
+    };
+    for key in table.keys() {
+        if !matches!(
+            key.as_str(),
+            "service" | "url" | "token" | "room" | "chat_id" | "sections" | "headers"
+        ) {
+            return Err(format!("unknown key 'notify.{}'", key));
+        }
+    }
+    let room = string("room")?;
+    if service == Service::Matrix && room.is_none() {
+        return Err("'matrix' notifications need a 'room'".to_string());
+    }
+    // Chat ids are numbers, but channels can be given as `@name`
+    let chat_id = match table.get("chat_id") {
+        Some(Value::Integer(id)) => Some(id.to_string()),
+        Some(_) => string("chat_id")?,
+        None => None,
+    };
+    let token = string("token")?;
+    if service == Service::Telegram && (chat_id.is_none() || token.is_none()) {
+        return Err("'telegram' notifications need a 'token' and a 'chat_id'".to_string());
+    }
+    let url = match (string("url")?, &service) {
+        (Some(url), _) => url,
+        (None, Service::Telegram) => notify::TELEGRAM_API_URL.to_string(),
+        (None, Service::Plugin(_)) => String::new(),
+        (None, _) => return Err("'notify' needs a 'url'".to_string()),
+    };
+    Ok(Notifier {
+        service,
+        url,
+        token,
+        room,
+        chat_id,
+        sections,
+        headers,
+    })
+}
+
+// The `[smtp]` table
+fn smtp_server(value: &Value) -> Result<smtp::Server, String> {
+    let table = match value {
+        Value::Table(table) => table,
+        _ => return Err(type_error("smtp", "a table", value)),
+    };
+    for key in table.keys() {
+        if !matches!(
+            key.as_str(),
+            "server" | "port" | "security" | "username" | "password" | "password_command" | "from"
+        ) {
+            return Err(format!("unknown key 'smtp.{}'", key));
+        }
+    }
+    let string = |key: &str| match table.get(key) {
+        Some(value) => value
+            .as_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or_else(|| type_error(&format!("smtp.{}", key), "a string", value)),
+        None => Ok(None),
+    };
+    let security = match string("security")? {
+        Some(name) => {
+            Security::from_name(&name).ok_or_else(|| format!("unknown SMTP security '{}'", name))?
+        }
+        None => Security::StartTls,
+    };
+    let port = match table.get("port") {
+        Some(value) => Some(
+            value
+                .as_integer()
+                .and_then(|n| u16::try_from(n).ok())
+                .ok_or_else(|| type_error("smtp.port", "a port number", value))?,
+        ),
+        None => None,
+    };
+    Ok(smtp::Server {
+        host: string("server")?.ok_or("'smtp' needs a 'server'")?,
+        port,
+        security,
+        username: string("username")?,
+        password: string("password")?,
+        password_command: string("password_command")?,
+        from: string("from")?,
+    })
+}
+
+// An `[upstream.<pkg>]` table
+fn upstream_check(pkg_name: &str, value: &Value) -> Result<upstream::Check, String> {
+    let name = format!("upstream.{}", pkg_name);
+    let table = match value {
+        Value::Table(table) => table,
+        _ => return Err(type_error(&name, "a table", value)),
+    };
+    for key in table.keys() {
+        if !matches!(key.as_str(), "source" | "url" | "pattern" | "path" | "mode") {
+            return Err(format!("unknown key '{}.{}'", name, key));
+        }
+    }
+    let string = |key: &str| match table.get(key) {
+        Some(value) => value
+            .as_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or_else(|| type_error(&format!("{}.{}", name, key), "a string", value)),
+        None => Ok(None),
+    };
+    let kind = string("source")?.ok_or_else(|| format!("'{}' needs a 'source'", name))?;
+    let kind = upstream::Kind::from_name(&kind)
+        .ok_or_else(|| format!("unknown upstream source '{}'", kind))?;
+    let pattern = match string("pattern")? {
+        Some(pattern) => {
+            Some(Regex::new(&pattern).map_err(|e| format!("invalid '{}.pattern': {}", name, e))?)
+        }
+        None if kind == upstream::Kind::Regex => {
+            return Err(format!("'{}' needs a 'pattern'", name))
+        }
+        None => None,
+    };
+    let path = string("path")?;
+    if kind == upstream::Kind::Json && path.is_none() {
+        return Err(format!("'{}' needs a 'path'", name));
+    }
+    let mode = match string("mode")? {
+        Some(mode) => upstream::Mode::from_name(&mode)
+            .ok_or_else(|| format!("unknown upstream mode '{}'", mode))?,
+        None => upstream::Mode::Supplement,
+    };
+    Ok(upstream::Check {
+        kind,
+        url: string("url")?.ok_or_else(|| format!("'{}' needs a 'url'", name))?,
+        pattern,
+        path,
+        mode,
+    })
+}
+
+fn type_error(key: &str, expected: &str, value: &Value) -> String {
+    format!(
+        "'{}' should be {}, not {}",
+        key,
+        expected,
+        value.type_name()
+    )
+}
+
+fn expect_str_array<'a>(key: &str, value: &'a Value) -> Result<Vec<&'a str>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| type_error(key, "an array of strings", value))?
+        .iter()
+        .map(|v| {
+            v.as_str()
+                .ok_or_else(|| type_error(key, "an array of strings", v))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(text: &str) -> Config {
+        match Config::from_table(&toml::parse(text).unwrap()) {
+            Ok(config) => config,
+            Err(e) => panic!("the configuration should be valid: {}", e),
+        }
+    }
+
+    fn error(text: &str) -> String {
+        match Config::from_table(&toml::parse(text).unwrap()) {
+            Ok(_) => panic!("the configuration should be invalid"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn reads_the_top_level_keys() {
+        let config = config(
+            r#"
+email = "me@example.com"
+color = false
+sections = ["installed", "pending"]
+"#,
+        );
+        assert_eq!(config.emails, ["me@example.com"]);
+        assert_eq!(config.color, ColorChoice::Never);
+        assert_eq!(config.sections, [Section::Installed, Section::Pending]);
+    }
+
+    #[test]
+    fn falls_back_to_the_defaults() {
+        let config = config("");
+        assert!(config.emails.is_empty());
+        assert_eq!(config.color, ColorChoice::Auto);
+        assert_eq!(
+            config.sections,
+            [Section::Maintainer, Section::Installed, Section::Watched]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_values() {
+        assert_eq!(error("colour = true"), "unknown key 'colour'");
+        assert_eq!(
+            error("color = 'sometimes'"),
+            "unknown color choice 'sometimes'"
+        );
+        assert_eq!(error("sections = ['all']"), "unknown section 'all'");
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        assert_eq!(
+            error("color = 1"),
+            "'color' should be a boolean or string, not integer"
+        );
+        assert_eq!(
+            error("sections = ['installed', 2]"),
+            "'sections' should be an array of strings, not integer"
+        );
+    }
+
+    #[test]
+    fn reads_one_or_several_emails() {
+        let config = config("email = ['a@example.com', 'b@example.com']");
+        assert_eq!(config.emails, ["a@example.com", "b@example.com"]);
+        assert_eq!(
+            error("email = 1"),
+            "'email' should be an array of strings, not integer"
+        );
+        assert_eq!(
+            error("email = ['a@example.com', true]"),
+            "'email' should be an array of strings, not boolean"
+        );
+    }
+
+    #[test]
+    fn reads_the_retries() {
+        assert_eq!(config("retries = 5").fetch.retries, 5);
+        assert_eq!(config("retries = 0").fetch.retries, 0);
+        assert_eq!(
+            error("retries = -1"),
+            "'retries' should be a non-negative integer, not integer"
+        );
+        assert_eq!(
+            error("retries = '3'"),
+            "'retries' should be a non-negative integer, not string"
+        );
+    }
+
+    #[test]
+    fn reads_the_timeout() {
+        assert_eq!(
+            config("timeout = 10").fetch.timeout,
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            config("timeout = '2m'").fetch.timeout,
+            Duration::from_secs(120)
+        );
+        assert_eq!(error("timeout = '0s'"), "invalid timeout '0s'");
+        assert_eq!(error("timeout = 'soon'"), "invalid timeout 'soon'");
+        assert_eq!(
+            error("timeout = 0"),
+            "'timeout' should be a duration, not integer"
+        );
+    }
+
+    #[test]
+    fn reads_the_mirrors() {
+        let mirrors = config("mirrors = ['https://a.example.com', 'https://b.example.com']")
+            .fetch
+            .mirrors;
+        assert_eq!(mirrors, ["https://a.example.com", "https://b.example.com"]);
+        let mirrors = config("url = 'https://c.example.com'").fetch.mirrors;
+        assert_eq!(mirrors, ["https://c.example.com"]);
+        assert_eq!(
+            error("url = 'https://a'\nmirrors = ['https://b']"),
+            "only one of 'url' and 'mirrors' can be set"
+        );
+        assert_eq!(error("mirrors = []"), "'mirrors' should not be empty");
+    }
+
+    #[test]
+    fn adds_unbuilt_with_a_distdir() {
+        let with_distdir = config("distdir = '/src/void-packages'");
+        assert_eq!(
+            with_distdir.distdir,
+            Some(PathBuf::from("/src/void-packages"))
+        );
+        assert_eq!(with_distdir.sections.last(), Some(&Section::Unbuilt));
+        let sections = config("distdir = '/src'\nsections = ['installed']").sections;
+        assert_eq!(sections, [Section::Installed]);
+        assert_eq!(
+            error("distdir = true"),
+            "'distdir' should be a string, not boolean"
+        );
+    }
+
+    #[test]
+    fn reads_mangling_rules() {
+        let mangle = config("mangle.foo = ['strip-v', 'underscores']").mangle;
+        assert_eq!(
+            mangle["foo"],
+            [mangle::Rule::StripV, mangle::Rule::Underscores]
+        );
+        assert_eq!(
+            error("mangle.foo = ['upper']"),
+            "unknown mangling rule 'upper'"
+        );
+        assert_eq!(
+            error("mangle.foo = 'strip-v'"),
+            "'mangle.foo' should be an array of strings, not string"
+        );
+        assert_eq!(
+            error("mangle = ['strip-v']"),
+            "'mangle' should be a table, not array"
+        );
+    }
+
+    #[test]
+    fn reads_notify_tables() {
+        let notify = config(
+            r#"
+[[notify]]
+service = "ntfy"
+url = "https://ntfy.sh/updates"
+sections = ["maintainer"]
+
+[[notify]]
+service = "gotify"
+url = "https://gotify.example.com"
+token = "secret"
+"#,
+        )
+        .notify;
+        assert_eq!(notify.len(), 2);
+        assert_eq!(notify[0].service, Service::Ntfy);
+        assert_eq!(notify[0].url, "https://ntfy.sh/updates");
+        assert_eq!(notify[0].sections, [Section::Maintainer]);
+        assert_eq!(notify[1].service, Service::Gotify);
+        assert_eq!(notify[1].token.as_deref(), Some("secret"));
+        assert!(notify[1].sections.is_empty());
+    }
+
+    #[test]
+    fn checks_notify_tables() {
+        assert_eq!(
+            error("[[notify]]\nurl = 'https://a'"),
+            "'notify' needs a 'service'"
+        );
+        assert_eq!(
+            error("[[notify]]\nservice = 'ntfy'"),
+            "'notify' needs a 'url'"
+        );
+        assert_eq!(
+            error("[[notify]]\nservice = 'ntfy'\nurl = 'https://a'\ntopic = 'b'"),
+            "unknown key 'notify.topic'"
+        );
+        assert_eq!(
+            error("[[notify]]\nservice = 'ntfy'\nurl = 'https://a'\nsections = ['all']"),
+            "unknown section 'all'"
+        );
+        assert_eq!(
+            error("[notify]\nservice = 'ntfy'"),
+            "'notify' should be an array of tables, not table"
+        );
+    }
+
+    #[test]
+    fn reads_webhook_headers() {
+        let notify = config(
+            "[[notify]]\nservice = 'webhook'\nurl = 'https://a'\nheaders = { Authorization = 'Bearer x' }",
+        )
+        .notify;
+        assert_eq!(notify[0].service, Service::Webhook);
+        assert_eq!(
+            notify[0].headers,
+            [("Authorization".to_string(), "Bearer x".to_string())]
+        );
+        assert_eq!(
+            error("[[notify]]\nservice = 'webhook'\nurl = 'https://a'\nheaders.X = 1"),
+            "'notify.headers.X' should be a string, not integer"
+        );
+        assert_eq!(
+            error("[[notify]]\nservice = 'webhook'\nurl = 'https://a'\nheaders = 'X: 1'"),
+            "'notify.headers' should be a table, not string"
+        );
+    }
+
+    #[test]
+    fn checks_matrix_rooms() {
+        let notify = config(
+            "[[notify]]\nservice = 'matrix'\nurl = 'https://matrix.org'\nroom = '!a:matrix.org'",
+        )
+        .notify;
+        assert_eq!(notify[0].room.as_deref(), Some("!a:matrix.org"));
+        assert_eq!(
+            error("[[notify]]\nservice = 'matrix'\nurl = 'https://a'"),
+            "'matrix' notifications need a 'room'"
+        );
+    }
+
+    #[test]
+    fn reads_telegram_chats() {
+        let notify = config(
+            "[[notify]]\nservice = 'telegram'\ntoken = 'secret'\nchat_id = 1234\n\
+             [[notify]]\nservice = 'telegram'\ntoken = 'secret'\nchat_id = '@channel'",
+        )
+        .notify;
+        assert_eq!(notify[0].url, notify::TELEGRAM_API_URL);
+        assert_eq!(notify[0].chat_id.as_deref(), Some("1234"));
+        assert_eq!(notify[1].chat_id.as_deref(), Some("@channel"));
+        assert_eq!(
+            error("[[notify]]\nservice = 'telegram'\ntoken = 'a'"),
+            "'telegram' notifications need a 'token' and a 'chat_id'"
+        );
+    }
+
+    #[test]
+    fn reads_the_smtp_server() {
+        let smtp = config(
+            "[smtp]\nserver = 'smtp.example.com'\nport = 2525\nsecurity = 'tls'\nusername = 'me'",
+        )
+        .smtp
+        .unwrap();
+        assert_eq!(smtp.host, "smtp.example.com");
+        assert_eq!(smtp.port, Some(2525));
+        assert_eq!(smtp.security, Security::Tls);
+        assert_eq!(smtp.from(), Some("me"));
+        let smtp = config("[smtp]\nserver = 'a'").smtp.unwrap();
+        assert_eq!(smtp.security, Security::StartTls);
+        assert_eq!(smtp.port, None);
+        assert_eq!(error("[smtp]\nport = 25"), "'smtp' needs a 'server'");
+        assert_eq!(
+            error("[smtp]\nserver = 'a'\nhost = 'b'"),
+            "unknown key 'smtp.host'"
+        );
+        assert_eq!(
+            error("[smtp]\nserver = 'a'\nsecurity = 'ssl'"),
+            "unknown SMTP security 'ssl'"
+        );
+        assert_eq!(
+            error("[smtp]\nserver = 'a'\nport = 70000"),
+            "'smtp.port' should be a port number, not integer"
+        );
+    }
+
+    #[test]
+    fn reads_upstream_checks() {
+        let upstream = config(
+            "[upstream.foo]\nsource = 'json'\nurl = 'https://a'\npath = 'info.version'\n\
+             [upstream.bar]\nsource = 'git'\nurl = 'https://b'\nmode = 'override'",
+        )
+        .upstream;
+        assert_eq!(upstream["foo"].kind, upstream::Kind::Json);
+        assert_eq!(upstream["foo"].path.as_deref(), Some("info.version"));
+        assert_eq!(upstream["foo"].mode, upstream::Mode::Supplement);
+        assert_eq!(upstream["bar"].kind, upstream::Kind::Git);
+        assert_eq!(upstream["bar"].mode, upstream::Mode::Override);
+    }
+
+    #[test]
+    fn checks_upstream_tables() {
+        assert_eq!(
+            error("[upstream.foo]\nurl = 'https://a'"),
+            "'upstream.foo' needs a 'source'"
+        );
+        assert_eq!(
+            error("[upstream.foo]\nsource = 'svn'\nurl = 'https://a'"),
+            "unknown upstream source 'svn'"
+        );
+        assert_eq!(
+            error("[upstream.foo]\nsource = 'git'"),
+            "'upstream.foo' needs a 'url'"
+        );
+        assert_eq!(
+            error("[upstream.foo]\nsource = 'json'\nurl = 'https://a'"),
+            "'upstream.foo' needs a 'path'"
+        );
+        assert_eq!(
+            error("[upstream.foo]\nsource = 'regex'\nurl = 'https://a'"),
+            "'upstream.foo' needs a 'pattern'"
+        );
+        assert!(
+            error("[upstream.foo]\nsource = 'regex'\nurl = 'https://a'\npattern = '('")
+                .starts_with("invalid 'upstream.foo.pattern': ")
+        );
+        assert_eq!(
+            error("[upstream.foo]\nsource = 'git'\nurl = 'https://a'\nmode = 'replace'"),
+            "unknown upstream mode 'replace'"
+        );
+        assert_eq!(
+            error("[upstream.foo]\nsource = 'git'\nurl = 'https://a'\nbranch = 'c'"),
+            "unknown key 'upstream.foo.branch'"
+        );
+        assert_eq!(
+            error("upstream.foo = 'git'"),
+            "'upstream.foo' should be a table, not string"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Calendar dates as `YYYY-MM-DD` strings. Dates in this format compare
+// correctly as plain strings, so no dedicated type is needed.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Convert days since 1970-01-01 to a (year, month, day) triple, using Howard
+// Hinnant's `civil_from_days` algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Today's date in UTC
+pub fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The UTC date of a Unix timestamp
+pub fn from_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The UTC date of the Monday starting the week of a Unix timestamp
+pub fn week_of(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    // 1970-01-01 was a Thursday
+    let monday = days - (days + 3).rem_euclid(7);
+    let (year, month, day) = civil_from_days(monday);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// A Unix timestamp as `YYYY-MM-DD HH:MM UTC`
+pub fn format_timestamp(secs: u64) -> String {
+    let secs = secs as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let minutes = secs.rem_euclid(86_400) / 60;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+// A Unix timestamp as an RFC 3339 date and time, e.g. `2024-06-13T08:30:00Z`
+pub fn rfc3339(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+// A Unix timestamp as an RFC 2822 date, as mail headers have them, e.g.
+// `Thu, 13 Jun 2024 08:30:00 +0000`
+pub fn rfc2822(secs: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let days = secs / 86_400;
+    let (year, month, day) = civil_from_days(days as i64);
+    let secs = secs % 86_400;
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} +0000",
+        // 1970-01-01 was a Thursday
+        WEEKDAYS[((days + 4) % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+// Whether `date` is a well-formed `YYYY-MM-DD` date
+pub fn is_valid(date: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap();
+    }
+    match RE.captures(date) {
+        Some(cap) => {
+            let month: u32 = cap[2].parse().unwrap();
+            let day: u32 = cap[3].parse().unwrap();
+            (1..=12).contains(&month) && (1..=31).contains(&day)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        // 2000-03-01 and the leap day before it
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn validates_dates() {
+        assert!(is_valid("2025-01-01"));
+        assert!(is_valid("2024-12-31"));
+        assert!(!is_valid("2025-13-01"));
+        assert!(!is_valid("2025-00-10"));
+        assert!(!is_valid("2025-01-32"));
+        assert!(!is_valid("2025-1-1"));
+        assert!(!is_valid("2025-01-01T00:00"));
+        assert!(!is_valid("tomorrow"));
+    }
+
+    #[test]
+    fn finds_the_monday_of_the_week() {
+        // Thursday 2024-06-13, Monday 2024-06-17 and the Sunday before it
+        assert_eq!(week_of(1_718_267_400), "2024-06-10");
+        assert_eq!(week_of(1_718_582_400), "2024-06-17");
+        assert_eq!(week_of(1_718_582_399), "2024-06-10");
+        // The week of 1970-01-01 started in 1969
+        assert_eq!(week_of(0), "1969-12-29");
+    }
+
+    #[test]
+    fn formats_rfc3339_timestamps() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_718_267_400), "2024-06-13T08:30:00Z");
+        assert_eq!(rfc3339(1_709_251_199), "2024-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn formats_rfc2822_dates() {
+        assert_eq!(rfc2822(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(rfc2822(1_718_267_400), "Thu, 13 Jun 2024 08:30:00 +0000");
+        assert_eq!(rfc2822(1_718_582_399), "Sun, 16 Jun 2024 23:59:59 +0000");
+        assert_eq!(rfc2822(1_735_732_800), "Wed, 01 Jan 2025 12:00:00 +0000");
+    }
+}
@@ -0,0 +1,789 @@
+// Just enough of the D-Bus wire protocol for `vupdate watch --dbus` to own a
+// name on the session bus and serve properties: authentication, (un)marshalling
+// of the basic and container types and method calls, replies and signals.
+// Messages are sent little-endian; big-endian ones are read too.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    U32(u32),
+    U64(u64),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    // The signature of the elements, so that empty arrays have one too
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+    Variant(Box<Value>),
+}
+
+impl Value {
+    pub fn signature(&self) -> String {
+        match self {
+            Value::Byte(_) => "y".to_string(),
+            Value::Bool(_) => "b".to_string(),
+            Value::U32(_) => "u".to_string(),
+            Value::U64(_) => "t".to_string(),
+            Value::Str(_) => "s".to_string(),
+            Value::ObjectPath(_) => "o".to_string(),
+            Value::Signature(_) => "g".to_string(),
+            Value::Array(element, _) => format!("a{}", element),
+            Value::Struct(fields) => format!(
+                "({})",
+                fields.iter().map(Value::signature).collect::<String>()
+            ),
+            Value::DictEntry(key, value) => format!("{{{}{}}}", key.signature(), value.signature()),
+            Value::Variant(_) => "v".to_string(),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) | Value::ObjectPath(s) | Value::Signature(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // A string array, `as`
+    pub fn strings<I: IntoIterator<Item = String>>(strings: I) -> Value {
+        Value::Array(
+            "s".to_string(),
+            strings.into_iter().map(Value::Str).collect(),
+        )
+    }
+
+    // A dictionary with string keys and variant values, `a{sv}`
+    pub fn dict<I: IntoIterator<Item = (String, Value)>>(entries: I) -> Value {
+        let entries = entries
+            .into_iter()
+            .map(|(k, v)| {
+                Value::DictEntry(
+                    Box::new(Value::Str(k)),
+                    Box::new(Value::Variant(Box::new(v))),
+                )
+            })
+            .collect();
+        Value::Array("{sv}".to_string(), entries)
+    }
+}
+
+fn alignment(signature: u8) -> usize {
+    match signature {
+        b'y' | b'g' | b'v' => 1,
+        b'b' | b'u' | b's' | b'o' | b'a' => 4,
+        b't' | b'(' | b'{' => 8,
+        _ => 1,
+    }
+}
+
+fn pad(buf: &mut Vec<u8>, align: usize) {
+    while !buf.len().is_multiple_of(align) {
+        buf.push(0);
+    }
+}
+
+// Append `value` to `buf`, whose start is 8-aligned in the message
+fn marshal(buf: &mut Vec<u8>, value: &Value) {
+    let string = |buf: &mut Vec<u8>, s: &str| {
+        buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+        buf.push(0);
+    };
+    pad(buf, alignment(value.signature().as_bytes()[0]));
+    match value {
+        Value::Byte(b) => buf.push(*b),
+        Value::Bool(b) => buf.extend_from_slice(&(*b as u32).to_le_bytes()),
+        Value::U32(n) => buf.extend_from_slice(&n.to_le_bytes()),
+        Value::U64(n) => buf.extend_from_slice(&n.to_le_bytes()),
+        Value::Str(s) | Value::ObjectPath(s) => string(buf, s),
+        Value::Signature(s) => {
+            buf.push(s.len() as u8);
+            buf.extend_from_slice(s.as_bytes());
+            buf.push(0);
+        }
+        Value::Array(element, items) => {
+            let length_at = buf.len();
+            buf.extend_from_slice(&[0; 4]);
+            // The length leaves out the padding up to the first element
+            pad(buf, alignment(element.as_bytes()[0]));
+            let start = buf.len();
+            for item in items {
+                marshal(buf, item);
+            }
+            let length = (buf.len() - start) as u32;
+            buf[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+        }
+        Value::Struct(fields) => {
+            for field in fields {
+                marshal(buf, field);
+            }
+        }
+        Value::DictEntry(key, value) => {
+            marshal(buf, key);
+            marshal(buf, value);
+        }
+        Value::Variant(inner) => {
+            marshal(buf, &Value::Signature(inner.signature()));
+            marshal(buf, inner);
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("D-Bus: {}", msg))
+}
+
+// Reads values of a message out of `data`, whose start is 8-aligned
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    little_endian: bool,
+}
+
+impl Reader<'_> {
+    fn align(&mut self, align: usize) {
+        self.pos = self.pos.div_ceil(align) * align;
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.data.len());
+        let end = end.ok_or_else(|| invalid("truncated message"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.align(4);
+        let bytes: [u8; 4] = self.take(4)?.try_into().unwrap();
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        self.align(8);
+        let bytes: [u8; 8] = self.take(8)?.try_into().unwrap();
+        Ok(if self.little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        })
+    }
+
+    fn string(&mut self, length: usize) -> io::Result<String> {
+        let s = String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| invalid("invalid string"))?;
+        self.take(1)?;
+        Ok(s)
+    }
+
+    // Read the values of `signature`, a sequence of complete types
+    fn values(&mut self, signature: &str) -> io::Result<Vec<Value>> {
+        let mut sig = signature.as_bytes();
+        let mut values = Vec::new();
+        while !sig.is_empty() {
+            let (value, rest) = self.value(sig)?;
+            values.push(value);
+            sig = rest;
+        }
+        Ok(values)
+    }
+
+    // Read the value of the first complete type of `sig`, returning the rest
+    fn value<'s>(&mut self, sig: &'s [u8]) -> io::Result<(Value, &'s [u8])> {
+        let rest = &sig[1..];
+        let value = match sig[0] {
+            b'y' => Value::Byte(self.take(1)?[0]),
+            b'b' => Value::Bool(self.u32()? != 0),
+            b'u' => Value::U32(self.u32()?),
+            b't' => Value::U64(self.u64()?),
+            b's' => {
+                let length = self.u32()? as usize;
+                Value::Str(self.string(length)?)
+            }
+            b'o' => {
+                let length = self.u32()? as usize;
+                Value::ObjectPath(self.string(length)?)
+            }
+            b'g' => {
+                let length = self.take(1)?[0] as usize;
+                Value::Signature(self.string(length)?)
+            }
+            b'v' => {
+                let length = self.take(1)?[0] as usize;
+                let signature = self.string(length)?;
+                let (inner, _) = self.value(signature.as_bytes())?;
+                Value::Variant(Box::new(inner))
+            }
+            b'a' => {
+                let element_len = complete_type_len(rest)?;
+                let element = &rest[..element_len];
+                let length = self.u32()? as usize;
+                self.align(alignment(element[0]));
+                let end = self.pos + length;
+                let mut items = Vec::new();
+                while self.pos < end {
+                    items.push(self.value(element)?.0);
+                }
+                let element = String::from_utf8_lossy(element).into_owned();
+                return Ok((Value::Array(element, items), &rest[element_len..]));
+            }
+            b'(' | b'{' => {
+                self.align(8);
+                let len = complete_type_len(sig)?;
+                let mut inner = &sig[1..len - 1];
+                let mut fields = Vec::new();
+                while !inner.is_empty() {
+                    let (field, more) = self.value(inner)?;
+                    fields.push(field);
+                    inner = more;
+                }
+                let value = if sig[0] == b'{' && fields.len() == 2 {
+                    let value = fields.pop().unwrap();
+                    Value::DictEntry(Box::new(fields.pop().unwrap()), Box::new(value))
+                } else {
+                    Value::Struct(fields)
+                };
+                return Ok((value, &sig[len..]));
+            }
+            _ => return Err(invalid("unsupported type")),
+        };
+        Ok((value, rest))
+    }
+}
+
+// The length of the complete type starting `sig`
+fn complete_type_len(sig: &[u8]) -> io::Result<usize> {
+    match sig.first() {
+        Some(b'a') => Ok(1 + complete_type_len(&sig[1..])?),
+        Some(open @ (b'(' | b'{')) => {
+            let close = if *open == b'(' { b')' } else { b'}' };
+            let mut depth = 0;
+            for (i, c) in sig.iter().enumerate() {
+                if *c == *open {
+                    depth += 1;
+                } else if *c == close {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(i + 1);
+                    }
+                }
+            }
+            Err(invalid("unbalanced signature"))
+        }
+        Some(_) => Ok(1),
+        None => Err(invalid("empty signature")),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub kind: MessageType,
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    fn new(kind: MessageType) -> Message {
+        Message {
+            kind,
+            serial: 0,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            destination: None,
+            sender: None,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn method_call(
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Message {
+        Message {
+            destination: Some(destination.to_string()),
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            body,
+            ..Message::new(MessageType::MethodCall)
+        }
+    }
+
+    pub fn signal(path: &str, interface: &str, member: &str, body: Vec<Value>) -> Message {
+        Message {
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            body,
+            ..Message::new(MessageType::Signal)
+        }
+    }
+
+    // The reply to this method call
+    pub fn reply(&self, body: Vec<Value>) -> Message {
+        Message {
+            reply_serial: Some(self.serial),
+            destination: self.sender.clone(),
+            body,
+            ..Message::new(MessageType::MethodReturn)
+        }
+    }
+
+    pub fn error(&self, name: &str, text: &str) -> Message {
+        Message {
+            reply_serial: Some(self.serial),
+            destination: self.sender.clone(),
+            error_name: Some(name.to_string()),
+            body: vec![Value::Str(text.to_string())],
+            ..Message::new(MessageType::Error)
+        }
+    }
+
+    fn encode(&self, serial: u32) -> Vec<u8> {
+        let mut body = Vec::new();
+        for value in &self.body {
+            marshal(&mut body, value);
+        }
+        let signature: String = self.body.iter().map(Value::signature).collect();
+
+        let mut fields = Vec::new();
+        let mut field = |code: u8, value: Value| {
+            fields.push(Value::Struct(vec![
+                Value::Byte(code),
+                Value::Variant(Box::new(value)),
+            ]));
+        };
+        let strings = [
+            (1, &self.path, true),
+            (2, &self.interface, false),
+            (3, &self.member, false),
+            (4, &self.error_name, false),
+            (6, &self.destination, false),
+            (7, &self.sender, false),
+        ];
+        for (code, value, is_path) in strings {
+            if let Some(value) = value {
+                let value = if is_path {
+                    Value::ObjectPath(value.clone())
+                } else {
+                    Value::Str(value.clone())
+                };
+                field(code, value);
+            }
+        }
+        if let Some(reply_serial) = self.reply_serial {
+            field(5, Value::U32(reply_serial));
+        }
+        if !signature.is_empty() {
+            field(8, Value::Signature(signature));
+        }
+
+        let mut message = vec![b'l', self.kind as u8, 0, 1];
+        message.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        message.extend_from_slice(&serial.to_le_bytes());
+        marshal(&mut message, &Value::Array("(yv)".to_string(), fields));
+        pad(&mut message, 8);
+        message.extend_from_slice(&body);
+        message
+    }
+
+    fn decode(data: &[u8]) -> io::Result<Message> {
+        let little_endian = match data[0] {
+            b'l' => true,
+            b'B' => false,
+            _ => return Err(invalid("unknown byte order")),
+        };
+        let kind = match data[1] {
+            1 => MessageType::MethodCall,
+            2 => MessageType::MethodReturn,
+            3 => MessageType::Error,
+            4 => MessageType::Signal,
+            _ => return Err(invalid("unknown message type")),
+        };
+        let mut reader = Reader {
+            data,
+            pos: 8,
+            little_endian,
+        };
+        let mut message = Message::new(kind);
+        message.serial = reader.u32()?;
+        let fields = match reader.value(b"a(yv)")?.0 {
+            Value::Array(_, fields) => fields,
+            _ => unreachable!(),
+        };
+        let mut signature = String::new();
+        for field in fields {
+            let (code, value) = match field {
+                Value::Struct(mut f) if f.len() == 2 => match (f.remove(0), f.remove(0)) {
+                    (Value::Byte(code), Value::Variant(value)) => (code, *value),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let string = value.as_str().map(String::from);
+            match code {
+                1 => message.path = string,
+                2 => message.interface = string,
+                3 => message.member = string,
+                4 => message.error_name = string,
+                5 => {
+                    if let Value::U32(n) = value {
+                        message.reply_serial = Some(n)
+                    }
+                }
+                6 => message.destination = string,
+                7 => message.sender = string,
+                8 => signature = string.unwrap_or_default(),
+                _ => {}
+            }
+        }
+        reader.align(8);
+        let mut body = Reader {
+            data: &data[reader.pos..],
+            pos: 0,
+            little_endian,
+        };
+        message.body = body.values(&signature)?;
+        Ok(message)
+    }
+}
+
+// The receiving half of a bus connection
+pub struct Receiver {
+    stream: OwnedReadHalf,
+}
+
+impl Receiver {
+    pub async fn receive(&mut self) -> io::Result<Message> {
+        let mut fixed = [0u8; 16];
+        self.stream.read_exact(&mut fixed).await?;
+        let read_u32 = |bytes: &[u8]| {
+            let bytes: [u8; 4] = bytes.try_into().unwrap();
+            if fixed[0] == b'l' {
+                u32::from_le_bytes(bytes)
+            } else {
+                u32::from_be_bytes(bytes)
+            }
+        };
+        let body_len = read_u32(&fixed[4..8]) as usize;
+        let fields_len = read_u32(&fixed[12..16]) as usize;
+        let header_len = (16 + fields_len).div_ceil(8) * 8;
+        let mut data = fixed.to_vec();
+        data.resize(header_len + body_len, 0);
+        self.stream.read_exact(&mut data[16..]).await?;
+        Message::decode(&data)
+    }
+}
+
+// The sending half of a bus connection
+pub struct Sender {
+    stream: OwnedWriteHalf,
+    serial: u32,
+}
+
+impl Sender {
+    // Send `message`, returning its serial
+    pub async fn send(&mut self, message: &Message) -> io::Result<u32> {
+        self.serial += 1;
+        self.stream.write_all(&message.encode(self.serial)).await?;
+        Ok(self.serial)
+    }
+}
+
+// Connect to the session bus and authenticate, returning both halves of the
+// connection. `Hello` still needs to be called.
+pub async fn session_bus() -> io::Result<(Sender, Receiver)> {
+    let address = std::env::var("DBUS_SESSION_BUS_ADDRESS")
+        .map_err(|_| invalid("DBUS_SESSION_BUS_ADDRESS is not set"))?;
+    let mut stream = None;
+    // Several addresses may be given, separated by `;`
+    for candidate in address.split(';') {
+        let params: HashMap<&str, &str> = match candidate.strip_prefix("unix:") {
+            Some(params) => params
+                .split(',')
+                .filter_map(|p| p.split_once('='))
+                .collect(),
+            None => continue,
+        };
+        let connected = if let Some(path) = params.get("path") {
+            UnixStream::connect(unescape(path)).await
+        } else if let Some(name) = params.get("abstract") {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(unescape(name).as_bytes())
+                .and_then(|addr| std::os::unix::net::UnixStream::connect_addr(&addr))
+                .and_then(|stream| {
+                    stream.set_nonblocking(true)?;
+                    UnixStream::from_std(stream)
+                })
+        } else {
+            continue;
+        };
+        if let Ok(connected) = connected {
+            stream = Some(connected);
+            break;
+        }
+    }
+    let mut stream = stream.ok_or_else(|| invalid("could not connect to the session bus"))?;
+
+    let uid = unsafe { libc::getuid() }.to_string();
+    let hex: String = uid.bytes().map(|b| format!("{:02x}", b)).collect();
+    stream
+        .write_all(format!("\0AUTH EXTERNAL {}\r\n", hex).as_bytes())
+        .await?;
+    let mut reply = Vec::new();
+    while !reply.ends_with(b"\r\n") {
+        let mut byte = [0u8];
+        stream.read_exact(&mut byte).await?;
+        reply.push(byte[0]);
+    }
+    if !reply.starts_with(b"OK ") {
+        return Err(invalid("the session bus refused the authentication"));
+    }
+    stream.write_all(b"BEGIN\r\n").await?;
+
+    let (read, write) = stream.into_split();
+    Ok((
+        Sender {
+            stream: write,
+            serial: 0,
+        },
+        Receiver { stream: read },
+    ))
+}
+
+// Undo the `%xx` escapes of a D-Bus address value
+fn unescape(value: &str) -> String {
+    let mut out = Vec::new();
+    let bytes = value.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(b) = u8::from_str_radix(&value[i + 1..i + 3], 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marshalled(values: &[Value]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for value in values {
+            marshal(&mut buf, value);
+        }
+        buf
+    }
+
+    fn round_trip(values: Vec<Value>) {
+        let signature: String = values.iter().map(Value::signature).collect();
+        let data = marshalled(&values);
+        let mut reader = Reader {
+            data: &data,
+            pos: 0,
+            little_endian: true,
+        };
+        assert_eq!(reader.values(&signature).unwrap(), values);
+        assert_eq!(reader.pos, data.len());
+    }
+
+    #[test]
+    fn pads_values_to_their_alignment() {
+        let data = marshalled(&[
+            Value::Byte(7),
+            Value::U32(1),
+            Value::Byte(8),
+            Value::U64(2),
+            Value::Signature("s".to_string()),
+            Value::Str("ab".to_string()),
+        ]);
+        let mut expected = vec![7, 0, 0, 0, 1, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0];
+        expected.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[1, b's', 0, 0, 2, 0, 0, 0, b'a', b'b', 0]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn leaves_the_padding_out_of_array_lengths() {
+        // The padding to the first element is still there when there is none
+        let empty = marshalled(&[Value::Array("t".to_string(), Vec::new())]);
+        assert_eq!(empty, [0; 8]);
+        let data = marshalled(&[Value::Array("t".to_string(), vec![Value::U64(3)])]);
+        assert_eq!(data, [8, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn marshals_string_variant_dictionaries() {
+        let dict = Value::dict(vec![("a".to_string(), Value::U32(1))]);
+        assert_eq!(dict.signature(), "a{sv}");
+        let data = marshalled(std::slice::from_ref(&dict));
+        let expected = [
+            16, 0, 0, 0, 0, 0, 0, 0, // length and padding to the entry
+            1, 0, 0, 0, b'a', 0, // key
+            1, b'u', 0, 0, 0, 0, 1, 0, 0, 0, // variant and padding to its value
+        ];
+        assert_eq!(data, expected);
+        round_trip(vec![
+            Value::Byte(1),
+            dict,
+            Value::dict(vec![
+                ("Version".to_string(), Value::Str("1.2_1".to_string())),
+                ("Count".to_string(), Value::U64(u64::MAX)),
+                ("Held".to_string(), Value::Bool(true)),
+                ("Names".to_string(), Value::strings(vec!["vim".to_string()])),
+            ]),
+            Value::dict(Vec::new()),
+        ]);
+    }
+
+    #[test]
+    fn round_trips_arrays_of_structs() {
+        let update = |fields: [&str; 4]| {
+            Value::Struct(fields.iter().map(|f| Value::Str(f.to_string())).collect())
+        };
+        let updates = Value::Array(
+            "(ssss)".to_string(),
+            vec![
+                update(["vim", "9.0.1_1", "9.1.0", "installed"]),
+                update(["gtk+3", "3.24.41_1", "3.24.42", "watched"]),
+            ],
+        );
+        assert_eq!(updates.signature(), "a(ssss)");
+        round_trip(vec![Value::Bool(false), updates]);
+        round_trip(vec![
+            Value::Byte(2),
+            Value::Array("(ssss)".to_string(), Vec::new()),
+            Value::ObjectPath("/org/voidlinux/vupdate".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn encodes_the_message_header() {
+        let message = Message::signal("/a", "b.c", "D", vec![Value::U32(5)]);
+        let data = message.encode(3);
+        // Byte order, type, flags, version, body length and serial
+        assert_eq!(data[..12], [b'l', 4, 0, 1, 4, 0, 0, 0, 3, 0, 0, 0]);
+        // The fields: path, interface, member and signature
+        let fields_len = u32::from_le_bytes(data[12..16].try_into().unwrap()) as usize;
+        assert_eq!(fields_len, 55);
+        assert_eq!(data[16..27], [1, 1, b'o', 0, 2, 0, 0, 0, b'/', b'a', 0]);
+        assert_eq!(data[64..71], [8, 1, b'g', 0, 1, b'u', 0]);
+        // The body starts 8-aligned after the fields
+        assert_eq!(data[71], 0);
+        assert_eq!(data.len(), 72 + 4);
+        assert_eq!(data[72..], [5, 0, 0, 0]);
+    }
+
+    #[test]
+    fn round_trips_messages() {
+        let mut call = Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "RequestName",
+            vec![
+                Value::Str("org.voidlinux.vupdate".to_string()),
+                Value::U32(4),
+            ],
+        );
+        let decoded = Message::decode(&call.encode(9)).unwrap();
+        call.serial = 9;
+        assert_eq!(decoded, call);
+
+        call.sender = Some(":1.42".to_string());
+        let mut reply = call.reply(vec![Value::dict(vec![(
+            "Updates".to_string(),
+            Value::U32(2),
+        )])]);
+        let decoded = Message::decode(&reply.encode(10)).unwrap();
+        reply.serial = 10;
+        assert_eq!(decoded, reply);
+        assert_eq!(decoded.reply_serial, Some(9));
+        assert_eq!(decoded.destination.as_deref(), Some(":1.42"));
+
+        let mut error = call.error("org.freedesktop.DBus.Error.Failed", "no");
+        let decoded = Message::decode(&error.encode(11)).unwrap();
+        error.serial = 11;
+        assert_eq!(decoded, error);
+
+        let mut empty = Message::signal("/", "a.b", "C", Vec::new());
+        let decoded = Message::decode(&empty.encode(12)).unwrap();
+        empty.serial = 12;
+        assert_eq!(decoded, empty);
+    }
+
+    #[test]
+    fn decodes_big_endian_messages() {
+        let mut data = Message::signal("/a", "b.c", "D", vec![Value::U32(5)]).encode(3);
+        // Swap the byte order of every number: the lengths and serial in the
+        // fixed header, the field array's length and the body
+        data[0] = b'B';
+        for at in [4, 8, 12, 72] {
+            data[at..at + 4].reverse();
+        }
+        // The string lengths of the path, interface and member fields
+        let mut at = 16;
+        for _ in 0..3 {
+            at += 4;
+            data[at..at + 4].reverse();
+            let len = u32::from_be_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+            at = (at + 4 + len + 1).div_ceil(8) * 8;
+        }
+        let message = Message::decode(&data).unwrap();
+        assert_eq!(message.serial, 3);
+        assert_eq!(message.path.as_deref(), Some("/a"));
+        assert_eq!(message.member.as_deref(), Some("D"));
+        assert_eq!(message.body, [Value::U32(5)]);
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        let data = Message::signal("/a", "b.c", "D", vec![Value::U32(5)]).encode(3);
+        assert!(Message::decode(&data[..data.len() - 1]).is_err());
+        assert!(Message::decode(&data[..20]).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+// End-of-life dates of well-known products from endoflife.date. A bump within
+// a release branch that is no longer supported hides the bigger migration to
+// a supported one.
+
+use crate::error::{Error, Result};
+use crate::fetch::Fetcher;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+pub const EOL_URL: &str = "https://endoflife.date";
+
+// Void's packages for the products endoflife.date follows, a trailing `*`
+// matching versioned packages such as `postgresql16`
+static PRODUCTS: &[(&str, &str)] = &[
+    ("python3", "python"),
+    ("nodejs", "nodejs"),
+    ("nodejs-lts", "nodejs"),
+    ("postgresql*", "postgresql"),
+    ("php*", "php"),
+    ("ruby", "ruby"),
+    ("go", "go"),
+    ("perl", "perl"),
+    ("openssl", "openssl"),
+    ("mariadb", "mariadb"),
+    ("redis", "redis"),
+    ("nginx", "nginx"),
+    ("qt5", "qt"),
+    ("qt6", "qt"),
+    ("openjdk*", "openjdk-builds-from-oracle"),
+    ("linux*", "linux"),
+    ("llvm*", "llvm"),
+];
+
+// A release branch that is no longer supported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eol {
+    // The branch, e.g. `3.8`
+    pub cycle: String,
+    // When support ended, if endoflife.date says
+    pub date: Option<String>,
+    // The newest branch
+    pub latest: Option<String>,
+}
+
+// The endoflife.date product of `pkg_name`, if it is a well-known one
+pub fn product(pkg_name: &str) -> Option<&'static str> {
+    PRODUCTS.iter().find_map(|(pattern, product)| {
+        let matches = match pattern.strip_suffix('*') {
+            Some(prefix) => pkg_name
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.bytes().all(|b| b.is_ascii_digit() || b == b'.')),
+            None => pkg_name == *pattern,
+        };
+        Some(*product).filter(|_| matches)
+    })
+}
+
+// The release branches of a product, newest first, as endoflife.date lists
+// them
+pub struct Lifecycle(Vec<Value>);
+
+// The release branches of `product`, `None` if endoflife.date does not know it
+pub async fn lifecycle(fetcher: &Fetcher, product: &str) -> Result<Option<Lifecycle>> {
+    let url = format!("{}/api/{}.json", EOL_URL, product);
+    let text = match fetcher.cached(&format!("eol/{}.json", product), &url).await {
+        Ok(text) => text,
+        Err(Error::Status { status, .. }) if status == StatusCode::NOT_FOUND => return Ok(None),
+        Err(Error::NotCached(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match serde_json::from_str(&text) {
+        Ok(Value::Array(cycles)) => Ok(Some(Lifecycle(cycles))),
+        Ok(_) => Err(Error::Parse(
+            "unexpected endoflife.date response".to_string(),
+        )),
+        Err(e) => Err(Error::Parse(format!(
+            "invalid endoflife.date response: {}",
+            e
+        ))),
+    }
+}
+
+impl Lifecycle {
+    // Whether the branch `version` belongs to is past its end of life by
+    // `today`, `None` if it is supported or unknown
+    pub fn eol(&self, version: &str, today: &str) -> Option<Eol> {
+        branch_eol(&self.0, version, today)
+    }
+}
+
+// The name of a release branch, which endoflife.date sometimes gives as a
+// number
+fn cycle_name(cycle: &Value) -> Option<String> {
+    match cycle.get("cycle")? {
+        Value::String(name) => Some(name.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// The branch of `version` among `cycles`, newest first, if it reached its end
+// of life by `today`. The longest branch name that is a prefix of the version
+// wins, so that `3.10.4` is in `3.10` rather than `3.1`.
+fn branch_eol(cycles: &[Value], version: &str, today: &str) -> Option<Eol> {
+    let (cycle, info) = cycles
+        .iter()
+        .filter_map(|info| Some((cycle_name(info)?, info)))
+        .filter(|(cycle, _)| {
+            version == cycle
+                || version
+                    .strip_prefix(cycle.as_str())
+                    .is_some_and(|rest| rest.starts_with(['.', '_', '-', '+']))
+        })
+        .max_by_key(|(cycle, _)| cycle.len())?;
+    let date = match info.get("eol")? {
+        Value::Bool(true) => None,
+        Value::String(date) if date.as_str() <= today => Some(date.clone()),
+        _ => return None,
+    };
+    Some(Eol {
+        cycle,
+        date,
+        latest: cycles.first().and_then(cycle_name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn maps_packages_to_products() {
+        assert_eq!(product("python3"), Some("python"));
+        assert_eq!(product("postgresql16"), Some("postgresql"));
+        assert_eq!(product("php8.3"), Some("php"));
+        assert_eq!(product("python3-mock"), None);
+        assert_eq!(product("linux-firmware"), None);
+    }
+
+    #[test]
+    fn finds_the_branch_of_a_version() {
+        let cycles = json!([
+            {"cycle": "3.12", "eol": "2028-10-31"},
+            {"cycle": "3.10", "eol": "2026-10-31"},
+            {"cycle": "3.1", "eol": true},
+            {"cycle": 16, "eol": "2023-11-09"},
+        ]);
+        let cycles = cycles.as_array().unwrap();
+        let eol = branch_eol(cycles, "3.10.4", "2026-11-01").unwrap();
+        assert_eq!(eol.cycle, "3.10");
+        assert_eq!(eol.date.as_deref(), Some("2026-10-31"));
+        assert_eq!(eol.latest.as_deref(), Some("3.12"));
+        assert_eq!(branch_eol(cycles, "3.10.4", "2026-10-30"), None);
+        assert_eq!(
+            branch_eol(cycles, "3.1.5", "2020-01-01").unwrap().date,
+            None
+        );
+        assert_eq!(
+            branch_eol(cycles, "16.2", "2024-01-01").unwrap().cycle,
+            "16"
+        );
+        assert_eq!(branch_eol(cycles, "3.11.0", "2030-01-01"), None);
+    }
+}
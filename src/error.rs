@@ -0,0 +1,91 @@
+// The error type of the library functions
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    // The HTTP client could not be set up
+    Client(reqwest::Error),
+    // The request failed or its body could not be read
+    Network {
+        url: String,
+        source: reqwest::Error,
+    },
+    // The server answered with an error status
+    Status {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+    // A local file could not be read
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    // A file needed in offline mode that has never been fetched
+    NotCached(String),
+    // Data that could not be parsed, with a description of the problem
+    Parse(String),
+    // The xbps package database could not be read, or an xbps command failed
+    Xbps(String),
+    // The GitHub API refused a request, with its explanation
+    GitHub(String),
+    // The GitLab API refused a request, with its explanation
+    GitLab(String),
+    // A git command failed
+    Git(String),
+    // A mail could not be sent, with the server's explanation
+    Smtp(String),
+    // A notification service could not be reached
+    Notify(String),
+    // The `on_new_update` hook could not be run or failed
+    Hook(String),
+    // A plugin could not be run, failed or printed something unexpected
+    Plugin(String),
+}
+
+impl Error {
+    // Whether the error may go away when the request is retried: timeouts,
+    // connection failures and server errors
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Network { source, .. } => source.is_timeout() || source.is_connect(),
+            Error::Status { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Client(source) => write!(f, "could not set up the HTTP client: {}", source),
+            Error::Network { url, source } => write!(f, "could not fetch {}: {}", url, source),
+            Error::Status { url, status } => {
+                write!(f, "could not fetch {}: server returned {}", url, status)
+            }
+            Error::Io { path, source } => write!(f, "could not read {}: {}", path, source),
+            Error::NotCached(file) => write!(f, "no cached copy of {} to use offline", file),
+            Error::Parse(msg) => write!(f, "{}", msg),
+            Error::Xbps(msg) => write!(f, "{}", msg),
+            Error::GitHub(msg) => write!(f, "GitHub: {}", msg),
+            Error::GitLab(msg) => write!(f, "GitLab: {}", msg),
+            Error::Git(msg) => write!(f, "{}", msg),
+            Error::Smtp(msg) => write!(f, "could not send the mail: {}", msg),
+            Error::Notify(msg) => write!(f, "could not notify {}", msg),
+            Error::Hook(msg) => write!(f, "{}", msg),
+            Error::Plugin(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Client(source) | Error::Network { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
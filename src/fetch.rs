@@ -0,0 +1,389 @@
+// Fetching the updates.txt files published by void-updates
+
+use crate::cache::{self, Entry};
+use crate::error::{Error, Result};
+use crate::parse::{response_to_hashmap, UpdateMap};
+use crate::template::{self, Template};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Directories holding void-updates.txt and the per-maintainer lists under
+// void-updates/, tried in this order
+pub static DEFAULT_MIRRORS: &[&str] = &[
+    "https://alpha.de.repo.voidlinux.org/void-updates",
+    "https://repo-default.voidlinux.org/void-updates",
+    "https://repo-fastly.voidlinux.org/void-updates",
+];
+
+// The templates of void-packages' master branch, as `<TEMPLATES_URL>/<pkg>/template`
+pub static TEMPLATES_URL: &str =
+    "https://raw.githubusercontent.com/void-linux/void-packages/master/srcpkgs";
+
+// Delay before the first retry, doubled for every further one
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+// Settings for fetching, from the configuration file and the command line
+#[derive(Clone, Debug)]
+pub struct Options {
+    // How often a request failing with a transient error is retried
+    pub retries: u32,
+    // Limit for connecting and for each request as a whole
+    pub timeout: Duration,
+    // Proxy for all requests, `none` to ignore the proxy environment variables
+    pub proxy: Option<String>,
+    // Base URLs to fetch from, falling back to the next one on failure. The
+    // default mirrors are used if this is empty.
+    pub mirrors: Vec<String>,
+    // Directory caching the fetched files, `None` to always download them
+    pub cache: Option<PathBuf>,
+    // Use only the cached files, without any network access
+    pub offline: bool,
+    // Local copy of void-updates.txt to read instead of fetching it, `-` for
+    // stdin
+    pub from_file: Option<PathBuf>,
+    // Sent with every request so that server operators can tell who is asking
+    pub user_agent: String,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            retries: 2,
+            timeout: Duration::from_secs(30),
+            proxy: None,
+            mirrors: DEFAULT_MIRRORS.iter().map(|m| m.to_string()).collect(),
+            cache: cache::cache_dir(),
+            offline: false,
+            from_file: None,
+            user_agent: format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+pub struct Fetcher {
+    client: reqwest::Client,
+    options: Options,
+    // When the oldest cached file used offline was fetched
+    oldest_cached: Mutex<Option<u64>>,
+}
+
+impl Fetcher {
+    pub fn new(mut options: Options) -> Result<Fetcher> {
+        if options.mirrors.is_empty() {
+            options.mirrors = Options::default().mirrors;
+        }
+        let mut builder = reqwest::Client::builder()
+            .user_agent(options.user_agent.as_str())
+            .connect_timeout(options.timeout)
+            .timeout(options.timeout);
+        // reqwest itself picks up HTTP_PROXY and HTTPS_PROXY
+        match options.proxy.clone().or_else(all_proxy_from_env).as_deref() {
+            Some("none") => builder = builder.no_proxy(),
+            Some(url) => builder = builder.proxy(reqwest::Proxy::all(url).map_err(Error::Client)?),
+            None => {}
+        }
+        let client = builder.build().map_err(Error::Client)?;
+        Ok(Fetcher {
+            client,
+            options,
+            oldest_cached: Mutex::new(None),
+        })
+    }
+
+    // Make an HTTP request, retrying on timeouts, connection failures and
+    // server errors. With a cached copy, the request is conditional and the
+    // copy is returned if it is still current.
+    async fn get_http_response(&self, url: &str, cached: Option<&Entry>) -> Result<Entry> {
+        let mut attempt = 0;
+        loop {
+            match self.try_get(url, cached).await {
+                Err(e) if e.is_transient() && attempt < self.options.retries => {
+                    tokio::time::sleep(backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Fetch `path` from the first mirror that has it
+    async fn get_file(&self, path: &str) -> Result<String> {
+        let urls: Vec<String> = self
+            .options
+            .mirrors
+            .iter()
+            .map(|mirror| format!("{}/{}", mirror.trim_end_matches('/'), path))
+            .collect();
+        self.get_cached(path, &urls).await
+    }
+
+    // Fetch the first of `urls` that answers, cached under the name `key`. If
+    // all of them fail, the error from the last one is returned.
+    async fn get_cached(&self, key: &str, urls: &[String]) -> Result<String> {
+        let cached = self
+            .options
+            .cache
+            .as_deref()
+            .and_then(|dir| cache::load(dir, key));
+        if self.options.offline {
+            let cached = cached.ok_or_else(|| Error::NotCached(key.to_string()))?;
+            let mut oldest = self.oldest_cached.lock().unwrap();
+            *oldest = Some(oldest.map_or(cached.fetched, |t| t.min(cached.fetched)));
+            return Ok(cached.body);
+        }
+        let mut last_error = None;
+        for url in urls {
+            match self.get_http_response(url, cached.as_ref()).await {
+                Ok(entry) => {
+                    // The cache only saves bandwidth, so failing to write it
+                    // is not an error
+                    if let Some(dir) = &self.options.cache {
+                        let _ = cache::store(dir, key, &entry);
+                    }
+                    return Ok(entry.body);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.expect("there is at least one URL"))
+    }
+
+    async fn try_get(&self, url: &str, cached: Option<&Entry>) -> Result<Entry> {
+        let network = |source| Error::Network {
+            url: url.to_string(),
+            source,
+        };
+        let mut request = self.client.get(url);
+        if let Some(cached) = cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await.map_err(network)?;
+        let status = response.status();
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (status, cached) {
+            return Ok(Entry {
+                body: cached.body.clone(),
+                etag: cached.etag.clone(),
+                last_modified: cached.last_modified.clone(),
+                fetched: cache::now(),
+            });
+        }
+        if !status.is_success() {
+            return Err(Error::Status {
+                url: url.to_string(),
+                status,
+            });
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        Ok(Entry {
+            body: response.text().await.map_err(network)?,
+            etag,
+            last_modified,
+            fetched: cache::now(),
+        })
+    }
+
+    // Fetch `url` from another service, cached under the name `key` like the
+    // updates lists, so that it can be read offline
+    pub async fn cached(&self, key: &str, url: &str) -> Result<String> {
+        self.get_cached(key, &[url.to_string()]).await
+    }
+
+    // Whether only cached files are read
+    pub fn is_offline(&self) -> bool {
+        self.options.offline
+    }
+
+    // The HTTP client, set up with the configured proxy, timeout and
+    // User-Agent, for requests to other services
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    // In offline mode, when the oldest of the cached files used so far was
+    // fetched, in seconds since the Unix epoch
+    pub fn offline_data_time(&self) -> Option<u64> {
+        *self.oldest_cached.lock().unwrap()
+    }
+
+    // Get the names of packages for which updates are available and for which
+    // `email` is the maintainer.
+    pub async fn maintainer_updates(&self, email: &str) -> Result<UpdateMap> {
+        let body = self
+            .get_file(&format!("void-updates/updates_{}.txt", email))
+            .await?;
+        Ok(response_to_hashmap(&body))
+    }
+
+    // Get the names of all packages for which updates are available.
+    pub async fn all_updates(&self) -> Result<UpdateMap> {
+        let body = match &self.options.from_file {
+            Some(path) => read_file(path)?,
+            None => self.get_file("void-updates.txt").await?,
+        };
+        Ok(response_to_hashmap(&body))
+    }
+
+    // Download `url` without caching it, passing each piece of the body to
+    // `on_data` as it arrives along with the total size if the server told it.
+    // This is meant for large files like distfiles, which are not kept in
+    // memory.
+    pub async fn download(
+        &self,
+        url: &str,
+        mut on_data: impl FnMut(&[u8], Option<u64>),
+    ) -> Result<()> {
+        if self.options.offline {
+            return Err(Error::NotCached(url.to_string()));
+        }
+        let network = |source| Error::Network {
+            url: url.to_string(),
+            source,
+        };
+        // Downloads can take far longer than the request timeout allows
+        let mut response = self
+            .client
+            .get(url)
+            .timeout(Duration::from_secs(24 * 60 * 60))
+            .send()
+            .await
+            .map_err(network)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::Status {
+                url: url.to_string(),
+                status,
+            });
+        }
+        let total = response.content_length();
+        while let Some(chunk) = response.chunk().await.map_err(network)? {
+            on_data(&chunk, total);
+        }
+        Ok(())
+    }
+
+    // Whether `url` exists, asked with a HEAD request. Servers that do not
+    // allow HEAD requests are assumed to have the file.
+    pub async fn exists(&self, url: &str) -> Result<bool> {
+        let response = self
+            .client
+            .head(url)
+            .send()
+            .await
+            .map_err(|source| Error::Network {
+                url: url.to_string(),
+                source,
+            })?;
+        match response.status() {
+            StatusCode::NOT_FOUND | StatusCode::GONE => Ok(false),
+            StatusCode::METHOD_NOT_ALLOWED => Ok(true),
+            status if status.is_success() => Ok(true),
+            status => Err(Error::Status {
+                url: url.to_string(),
+                status,
+            }),
+        }
+    }
+
+    // The template building `pkg_name` on void-packages' master branch, or
+    // `None` if there is no such package. Offline, a template that was never
+    // fetched is unknown too.
+    pub async fn master_template(&self, pkg_name: &str) -> Result<Option<Template>> {
+        Ok(self
+            .master_template_text(pkg_name)
+            .await?
+            .map(|text| template::parse(&text)))
+    }
+
+    // The text of the template building `pkg_name` on void-packages' master
+    // branch, see `master_template`
+    pub async fn master_template_text(&self, pkg_name: &str) -> Result<Option<String>> {
+        let mut name = pkg_name.to_string();
+        // Subpackage directories are symlinks, which GitHub serves as a file
+        // holding the name of the main package, so follow one of them
+        for _ in 0..2 {
+            let url = format!("{}/{}/template", TEMPLATES_URL, name);
+            let text = match self
+                .get_cached(&format!("srcpkgs/{}/template", name), &[url])
+                .await
+            {
+                Ok(text) => text,
+                Err(Error::Status { status, .. }) if status == StatusCode::NOT_FOUND => {
+                    return Ok(None)
+                }
+                Err(Error::NotCached(_)) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let target = text.trim();
+            if target.contains(|c: char| c == '=' || c.is_whitespace()) {
+                return Ok(Some(text));
+            }
+            name = target.trim_end_matches("/template").to_string();
+        }
+        Ok(None)
+    }
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    let result = if path == Path::new("-") {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        std::fs::read_to_string(path)
+    };
+    result.map_err(|source| Error::Io {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+// The proxy from `ALL_PROXY`, unless a more specific proxy variable is set
+// since those take precedence
+fn all_proxy_from_env() -> Option<String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let specific = ["HTTP_PROXY", "http_proxy", "HTTPS_PROXY", "https_proxy"];
+    if specific.iter().any(|name| var(name).is_some()) {
+        return None;
+    }
+    var("ALL_PROXY").or_else(|| var("all_proxy"))
+}
+
+// The delay before retry number `attempt` (counting from 0): exponential, with
+// up to 50% random jitter so that clients started together spread out. The
+// clock's sub-second part is random enough for this.
+fn backoff(attempt: u32) -> Duration {
+    let delay = RETRY_DELAY * 2u32.saturating_pow(attempt);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    delay + delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+}
+
+// Get the updates maintained by `email`, with the default options
+pub async fn fetch_maintainer_updates(email: &str) -> Result<UpdateMap> {
+    Fetcher::new(Options::default())?
+        .maintainer_updates(email)
+        .await
+}
+
+// Get all updates, with the default options
+pub async fn fetch_all_updates() -> Result<UpdateMap> {
+    Fetcher::new(Options::default())?.all_updates().await
+}
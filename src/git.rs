@@ -0,0 +1,79 @@
+// Running git in the void-packages checkout
+
+use crate::error::{Error, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Run git with `args` in the repository at `dir` and return its output, or
+// its error message if it fails
+pub fn run(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| Error::Git(format!("could not run git: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Git(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+// Like `run`, but with git's output going to the terminal, for commands such
+// as `push` whose progress the user wants to see
+pub fn run_visibly(dir: &Path, args: &[&str]) -> Result<()> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .status()
+        .map_err(|e| Error::Git(format!("could not run git: {}", e)))?;
+    if !status.success() {
+        return Err(Error::Git(format!(
+            "git {} failed",
+            args.first().unwrap_or(&"")
+        )));
+    }
+    Ok(())
+}
+
+// The branch checked out in the repository at `dir`, `None` if the HEAD is
+// detached
+pub fn current_branch(dir: &Path) -> Result<Option<String>> {
+    let branch = run(dir, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    Ok(Some(branch).filter(|b| b != "HEAD"))
+}
+
+pub struct Worktree {
+    pub path: PathBuf,
+    // `None` if the HEAD of the worktree is detached
+    pub branch: Option<String>,
+}
+
+// The worktrees of the repository at `dir`, the main one first
+pub fn worktrees(dir: &Path) -> Result<Vec<Worktree>> {
+    let output = run(dir, &["worktree", "list", "--porcelain"])?;
+    let mut worktrees = Vec::new();
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            worktrees.push(Worktree {
+                path: PathBuf::from(path),
+                branch: None,
+            });
+        } else if let (Some(branch), Some(worktree)) =
+            (line.strip_prefix("branch "), worktrees.last_mut())
+        {
+            let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+            worktree.branch = Some(branch.to_string());
+        }
+    }
+    Ok(worktrees)
+}
@@ -0,0 +1,330 @@
+// A small client for the parts of the GitHub REST API vupdate uses, on the
+// void-packages repository and on upstream repositories for their release
+// notes. Requests work without a token, but anonymous ones are limited to a
+// few searches per minute.
+
+use crate::changelog::{self, Commit, Release};
+use crate::error::{Error, Result};
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde_json::Value;
+use std::process::{Command, Stdio};
+
+pub static API_URL: &str = "https://api.github.com";
+pub static REPOSITORY: &str = "void-linux/void-packages";
+
+// Search results come in pages of at most this many items
+const PER_PAGE: usize = 100;
+// GitHub's search API returns no more than 1000 results
+const MAX_PAGES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    // The login of the user who opened it
+    pub author: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullRequestState {
+    Open,
+    Merged,
+    // Closed without being merged through GitHub. Void maintainers often push
+    // the commits themselves and close the pull request, so this does not
+    // mean the change was rejected.
+    Closed,
+}
+
+pub struct Client {
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl Client {
+    // A client sending its requests with `client`, which should set a
+    // User-Agent since GitHub rejects requests without one
+    pub fn new(client: reqwest::Client, token: Option<String>) -> Client {
+        Client { client, token }
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .request(method, url)
+            .header(ACCEPT, "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request
+    }
+
+    // Send a request and return the decoded JSON response, turning GitHub's
+    // error responses into errors with its message
+    async fn send(&self, request: reqwest::RequestBuilder, url: &str) -> Result<Value> {
+        let network = |source| Error::Network {
+            url: url.to_string(),
+            source,
+        };
+        let response = request.send().await.map_err(network)?;
+        let status = response.status();
+        let text = response.text().await.map_err(network)?;
+        // Error pages in front of the API, e.g. from a proxy, are not JSON
+        let body: Option<Value> = serde_json::from_str(&text).ok();
+        if !status.is_success() {
+            let message = body.as_ref().and_then(|b| b.get("message")?.as_str());
+            return Err(match message {
+                Some(message) => Error::GitHub(format!("{} ({})", message, status)),
+                None => Error::Status {
+                    url: url.to_string(),
+                    status,
+                },
+            });
+        }
+        body.ok_or_else(|| Error::GitHub(format!("{} did not answer with JSON", url)))
+    }
+
+    async fn get(&self, url: &str) -> Result<Value> {
+        self.send(self.request(reqwest::Method::GET, url), url)
+            .await
+    }
+
+    // Open a pull request on void-packages from `head` (`<owner>:<branch>`)
+    // against master
+    pub async fn create_pull_request(
+        &self,
+        title: &str,
+        head: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let url = format!("{}/repos/{}/pulls", API_URL, REPOSITORY);
+        let request = self
+            .request(reqwest::Method::POST, &url)
+            .json(&serde_json::json!({
+                "title": title,
+                "head": head,
+                "base": "master",
+                "body": body,
+                "maintainer_can_modify": true,
+            }));
+        let response = self.send(request, &url).await?;
+        pull_request(&response)
+            .ok_or_else(|| Error::GitHub("unexpected pull request response".to_string()))
+    }
+
+    // The state of pull request `number` on void-packages
+    pub async fn pull_request_state(&self, number: u64) -> Result<PullRequestState> {
+        let url = format!("{}/repos/{}/pulls/{}", API_URL, REPOSITORY, number);
+        let response = self.get(&url).await?;
+        let merged = response.get("merged").and_then(Value::as_bool);
+        let state = response.get("state").and_then(Value::as_str);
+        match (merged, state) {
+            (Some(true), _) => Ok(PullRequestState::Merged),
+            (Some(false), Some("closed")) => Ok(PullRequestState::Closed),
+            (Some(false), Some(_)) => Ok(PullRequestState::Open),
+            _ => Err(Error::GitHub(
+                "unexpected pull request response".to_string(),
+            )),
+        }
+    }
+
+    // The open pull requests on void-packages whose title follows the
+    // `<pkg>: update to <version>.` convention
+    pub async fn open_update_prs(&self) -> Result<Vec<PullRequest>> {
+        let query = format!("repo:{} is:pr is:open in:title \"update to\"", REPOSITORY);
+        let mut prs = Vec::new();
+        for page in 1..=MAX_PAGES {
+            let url = reqwest::Url::parse_with_params(
+                &format!("{}/search/issues", API_URL),
+                &[
+                    ("q", query.as_str()),
+                    ("per_page", &PER_PAGE.to_string()),
+                    ("page", &page.to_string()),
+                ],
+            )
+            .map_err(|e| Error::Parse(e.to_string()))?;
+            let body = self.get(url.as_str()).await?;
+            let items = body
+                .get("items")
+                .and_then(Value::as_array)
+                .ok_or_else(|| Error::GitHub("unexpected search response".to_string()))?;
+            prs.extend(items.iter().filter_map(pull_request));
+            if items.len() < PER_PAGE {
+                break;
+            }
+        }
+        Ok(prs)
+    }
+
+    // The latest releases of `repo` (`<owner>/<name>`), drafts left out
+    pub async fn releases(&self, repo: &str) -> Result<Vec<Release>> {
+        let url = format!("{}/repos/{}/releases?per_page={}", API_URL, repo, PER_PAGE);
+        let body = self.get(&url).await?;
+        let items = body
+            .as_array()
+            .ok_or_else(|| Error::GitHub("unexpected releases response".to_string()))?;
+        Ok(items
+            .iter()
+            .filter(|item| item.get("draft").and_then(Value::as_bool) != Some(true))
+            .filter_map(|item| {
+                let field = |key| item.get(key).and_then(Value::as_str).unwrap_or("");
+                Some(Release {
+                    tag: item.get("tag_name")?.as_str()?.to_string(),
+                    name: field("name").to_string(),
+                    body: field("body").to_string(),
+                    date: item
+                        .get("published_at")
+                        .and_then(Value::as_str)
+                        .and_then(|date| date.get(..10))
+                        .map(String::from),
+                    url: field("html_url").to_string(),
+                })
+            })
+            .collect())
+    }
+
+    // The release of `repo` for `version`, tagged with the version and maybe
+    // a prefix like `v`
+    pub async fn release(&self, repo: &str, version: &str) -> Result<Option<Release>> {
+        let releases = self.releases(repo).await?;
+        Ok(releases
+            .into_iter()
+            .find(|release| changelog::tag_version(&release.tag) == version))
+    }
+
+    // The names of the latest tags of `repo`
+    pub async fn tags(&self, repo: &str) -> Result<Vec<String>> {
+        let url = format!("{}/repos/{}/tags?per_page={}", API_URL, repo, PER_PAGE);
+        let body = self.get(&url).await?;
+        let items = body
+            .as_array()
+            .ok_or_else(|| Error::GitHub("unexpected tags response".to_string()))?;
+        Ok(items
+            .iter()
+            .filter_map(|item| Some(item.get("name")?.as_str()?.to_string()))
+            .collect())
+    }
+
+    // The commits of `repo` from tag `base` to tag `head`, oldest first, and
+    // where the comparison can be seen
+    pub async fn compare(
+        &self,
+        repo: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<(Vec<Commit>, String)> {
+        let url = format!("{}/repos/{}/compare/{}...{}", API_URL, repo, base, head);
+        let body = self.get(&url).await?;
+        let commits = body
+            .get("commits")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::GitHub("unexpected compare response".to_string()))?
+            .iter()
+            .filter_map(|item| {
+                let message = item.get("commit")?.get("message")?.as_str()?;
+                Some(Commit {
+                    id: item.get("sha")?.as_str()?.chars().take(7).collect(),
+                    title: message.lines().next().unwrap_or("").to_string(),
+                })
+            })
+            .collect();
+        let web = body.get("html_url").and_then(Value::as_str).map_or_else(
+            || format!("https://github.com/{}/compare/{}...{}", repo, base, head),
+            String::from,
+        );
+        Ok((commits, web))
+    }
+}
+
+// Where a GitHub token can come from, tried in this order: the GITHUB_TOKEN
+// environment variable, the configuration file, the output of a command
+// (e.g. `pass show github/void`) and the system keyring
+#[derive(Debug, Clone, Default)]
+pub struct TokenSource {
+    pub token: Option<String>,
+    pub command: Option<String>,
+}
+
+impl TokenSource {
+    // The token, or None to go anonymous. Only an error running
+    // `token_command` is reported; a missing keyring is not an error.
+    pub fn token(&self) -> Result<Option<String>> {
+        let env = std::env::var("GITHUB_TOKEN").ok();
+        if let Some(token) = env.or_else(|| self.token.clone()) {
+            return Ok(Some(token).filter(|t| !t.is_empty()));
+        }
+        if let Some(command) = &self.command {
+            return token_command(command).map(Some);
+        }
+        Ok(keyring_token())
+    }
+}
+
+// Run `command` with the shell and return the first line it prints. stdin
+// and stderr are left alone so that a password manager can prompt.
+fn token_command(command: &str) -> Result<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdout(Stdio::piped())
+        .output()
+        .map_err(|e| Error::GitHub(format!("could not run token_command: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::GitHub(format!(
+            "token_command failed with {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let token = stdout.lines().next().unwrap_or("").trim();
+    if token.is_empty() {
+        return Err(Error::GitHub("token_command printed no token".to_string()));
+    }
+    Ok(token.to_string())
+}
+
+// The token stored in the Secret Service keyring (GNOME Keyring, KWallet...)
+// under `service=vupdate`, looked up with libsecret's secret-tool
+fn keyring_token() -> Option<String> {
+    let output = Command::new("secret-tool")
+        .args(["lookup", "service", "vupdate"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(token).filter(|t| !t.is_empty())
+}
+
+// Read a pull request from an item of the search or pulls API
+fn pull_request(item: &Value) -> Option<PullRequest> {
+    Some(PullRequest {
+        number: item.get("number")?.as_u64()?,
+        title: item.get("title")?.as_str()?.to_string(),
+        author: item.get("user")?.get("login")?.as_str()?.to_string(),
+        url: item.get("html_url")?.as_str()?.to_string(),
+    })
+}
+
+// The owner of the GitHub repository a git remote URL points at, for both
+// `https://github.com/<owner>/<repo>` and `git@github.com:<owner>/<repo>`
+pub fn remote_owner(url: &str) -> Option<&str> {
+    let path = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.split_once("github.com/").map(|(_, path)| path))?;
+    let owner = path.split('/').next()?;
+    Some(owner).filter(|o| !o.is_empty())
+}
+
+// The package and version named by a pull request title following the
+// void-packages conventions, e.g. `gtk+3: update to 3.24.42.`
+pub fn parse_update_title(title: &str) -> Option<(&str, &str)> {
+    let (pkg_name, version) = title.split_once(": update to ")?;
+    let version = version.trim().trim_end_matches('.');
+    if pkg_name.is_empty() || pkg_name.contains(char::is_whitespace) || version.is_empty() {
+        return None;
+    }
+    Some((pkg_name, version))
+}
@@ -0,0 +1,145 @@
+// A small client for the parts of the GitLab REST API `vupdate changelog`
+// uses, on gitlab.com or a self-hosted instance. Only public projects are
+// read, so no token is sent.
+
+use crate::changelog::{Commit, Release};
+use crate::error::{Error, Result};
+use serde_json::Value;
+
+// How many releases and tags are asked for
+const PER_PAGE: usize = 100;
+
+pub struct Client {
+    client: reqwest::Client,
+    // The instance, e.g. `https://gitlab.com`
+    origin: String,
+}
+
+impl Client {
+    pub fn new(client: reqwest::Client, origin: &str) -> Client {
+        Client {
+            client,
+            origin: origin.trim_end_matches('/').to_string(),
+        }
+    }
+
+    // The API URL of `project`'s `endpoint`, the project being its path
+    // like `group/subgroup/name`
+    fn url(&self, project: &str, endpoint: &str) -> String {
+        let id: String = project
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect();
+        format!("{}/api/v4/projects/{}/{}", self.origin, id, endpoint)
+    }
+
+    async fn get(&self, url: &str) -> Result<Value> {
+        let network = |source| Error::Network {
+            url: url.to_string(),
+            source,
+        };
+        let response = self.client.get(url).send().await.map_err(network)?;
+        let status = response.status();
+        let text = response.text().await.map_err(network)?;
+        // Error pages in front of the API, e.g. from a proxy, are not JSON
+        let body: Option<Value> = serde_json::from_str(&text).ok();
+        if !status.is_success() {
+            let message = body.as_ref().and_then(|b| b.get("message")?.as_str());
+            return Err(match message {
+                Some(message) => Error::GitLab(format!("{} ({})", message, status)),
+                None => Error::Status {
+                    url: url.to_string(),
+                    status,
+                },
+            });
+        }
+        body.ok_or_else(|| Error::GitLab(format!("{} did not answer with JSON", url)))
+    }
+
+    // The latest releases of `project`
+    pub async fn releases(&self, project: &str) -> Result<Vec<Release>> {
+        let url = self.url(project, &format!("releases?per_page={}", PER_PAGE));
+        let body = self.get(&url).await?;
+        let items = body
+            .as_array()
+            .ok_or_else(|| Error::GitLab("unexpected releases response".to_string()))?;
+        Ok(items
+            .iter()
+            .filter_map(|item| {
+                let field = |key| item.get(key).and_then(Value::as_str).unwrap_or("");
+                let tag = item.get("tag_name")?.as_str()?.to_string();
+                let web = item
+                    .get("_links")
+                    .and_then(|links| links.get("self"))
+                    .and_then(Value::as_str)
+                    .map_or_else(
+                        || format!("{}/{}/-/releases/{}", self.origin, project, tag),
+                        String::from,
+                    );
+                Some(Release {
+                    name: field("name").to_string(),
+                    body: field("description").to_string(),
+                    date: item
+                        .get("released_at")
+                        .and_then(Value::as_str)
+                        .and_then(|date| date.get(..10))
+                        .map(String::from),
+                    url: web,
+                    tag,
+                })
+            })
+            .collect())
+    }
+
+    // The names of the latest tags of `project`
+    pub async fn tags(&self, project: &str) -> Result<Vec<String>> {
+        let url = self.url(project, &format!("repository/tags?per_page={}", PER_PAGE));
+        let body = self.get(&url).await?;
+        let items = body
+            .as_array()
+            .ok_or_else(|| Error::GitLab("unexpected tags response".to_string()))?;
+        Ok(items
+            .iter()
+            .filter_map(|item| Some(item.get("name")?.as_str()?.to_string()))
+            .collect())
+    }
+
+    // The commits of `project` from tag `from` to tag `to`, oldest first, and
+    // where the comparison can be seen
+    pub async fn compare(
+        &self,
+        project: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<(Vec<Commit>, String)> {
+        let endpoint = reqwest::Url::parse_with_params(
+            "http://x/repository/compare",
+            &[("from", from), ("to", to)],
+        )
+        .map_err(|e| Error::Parse(e.to_string()))?;
+        let url = self.url(
+            project,
+            &format!("repository/compare?{}", endpoint.query().unwrap_or("")),
+        );
+        let body = self.get(&url).await?;
+        let commits = body
+            .get("commits")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::GitLab("unexpected compare response".to_string()))?
+            .iter()
+            .filter_map(|item| {
+                Some(Commit {
+                    id: item.get("short_id")?.as_str()?.to_string(),
+                    title: item.get("title")?.as_str()?.to_string(),
+                })
+            })
+            .collect();
+        let web = format!("{}/{}/-/compare/{}...{}", self.origin, project, from, to);
+        Ok((commits, web))
+    }
+}
@@ -0,0 +1,212 @@
+// The updates found by each run of `vupdate check`, kept in
+// `$XDG_DATA_HOME/vupdate/history.jsonl` with one run per line:
+//
+// ```
+// {"time":1760400000,"updates":{"foo":{"section":"installed","current":"1.0","new":"1.1"}}}
+// ```
+//
+// A run finding the same updates as the one before is not recorded again, so
+// that running vupdate often does not grow the file.
+
+use crate::config::data_dir;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recorded {
+    // The name of the section the update was listed in
+    pub section: String,
+    pub current_version: String,
+    pub new_version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    // Unix timestamp of the run
+    pub time: u64,
+    pub updates: BTreeMap<String, Recorded>,
+}
+
+impl Run {
+    pub fn to_json(&self) -> Value {
+        let updates: Map<String, Value> = self
+            .updates
+            .iter()
+            .map(|(name, u)| {
+                let update = json!({
+                    "section": u.section,
+                    "current": u.current_version,
+                    "new": u.new_version,
+                });
+                (name.clone(), update)
+            })
+            .collect();
+        json!({"time": self.time, "updates": updates})
+    }
+
+    fn from_json(value: &Value) -> Option<Run> {
+        let mut updates = BTreeMap::new();
+        for (name, update) in value.get("updates")?.as_object()? {
+            let field = |key| update.get(key).and_then(Value::as_str).map(String::from);
+            let recorded = Recorded {
+                section: field("section")?,
+                current_version: field("current")?,
+                new_version: field("new")?,
+            };
+            updates.insert(name.clone(), recorded);
+        }
+        Some(Run {
+            time: value.get("time")?.as_u64()?,
+            updates,
+        })
+    }
+}
+
+pub fn path() -> io::Result<PathBuf> {
+    data_dir()
+        .map(|dir| dir.join("history.jsonl"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+}
+
+// The recorded runs, oldest first. A missing file means no runs; lines that
+// cannot be read are skipped.
+pub fn load() -> io::Result<Vec<Run>> {
+    let text = match std::fs::read_to_string(path()?) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter_map(|value| Run::from_json(&value))
+        .collect())
+}
+
+// Append `run` to the history unless it found the same updates as `last`,
+// the last recorded run
+pub fn record(run: &Run, last: Option<&Run>) -> io::Result<()> {
+    if last.is_some_and(|last| last.updates == run.updates) {
+        return Ok(());
+    }
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", run.to_json())
+}
+
+// When an update was first seen, as Unix timestamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seen {
+    // Since when the package has been listed with its current new version
+    pub version: u64,
+    // Since when the package has been listed at all, whatever the version
+    pub package: u64,
+}
+
+// When each update of `current` was first seen in the history `runs`. An
+// update counts from the earliest of the runs that listed it without a break
+// up to `current`, so that one that went away and came back starts again.
+pub fn first_seen(runs: &[Run], current: &Run) -> HashMap<String, Seen> {
+    let mut seen = HashMap::new();
+    for (name, update) in &current.updates {
+        let mut first = Seen {
+            version: current.time,
+            package: current.time,
+        };
+        let mut same_version = true;
+        for run in runs.iter().rev().filter(|run| run.time <= current.time) {
+            let recorded = match run.updates.get(name) {
+                Some(recorded) => recorded,
+                None => break,
+            };
+            first.package = run.time;
+            same_version = same_version && recorded.new_version == update.new_version;
+            if same_version {
+                first.version = run.time;
+            }
+        }
+        seen.insert(name.clone(), first);
+    }
+    seen
+}
+
+// How the updates changed from one run to another
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Diff<'a> {
+    // Updates only in the newer run
+    pub added: Vec<(&'a str, &'a Recorded)>,
+    // Updates only in the older run, because they were packaged, ignored or
+    // withdrawn
+    pub removed: Vec<(&'a str, &'a Recorded)>,
+    // Updates in both runs but to another version, with the older one
+    pub changed: Vec<(&'a str, &'a Recorded, &'a Recorded)>,
+}
+
+impl Diff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn diff<'a>(old: &'a Run, new: &'a Run) -> Diff<'a> {
+    let mut diff = Diff::default();
+    for (name, update) in &new.updates {
+        match old.updates.get(name) {
+            None => diff.added.push((name.as_str(), update)),
+            Some(before) if before.new_version != update.new_version => {
+                diff.changed.push((name.as_str(), before, update))
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, update) in &old.updates {
+        if !new.updates.contains_key(name) {
+            diff.removed.push((name.as_str(), update));
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(updates: &[(&str, &str)]) -> Run {
+        let updates = updates
+            .iter()
+            .map(|(name, new)| {
+                let recorded = Recorded {
+                    section: "installed".to_string(),
+                    current_version: "1.0".to_string(),
+                    new_version: new.to_string(),
+                };
+                (name.to_string(), recorded)
+            })
+            .collect();
+        Run { time: 0, updates }
+    }
+
+    #[test]
+    fn diffs_added_removed_and_changed_updates() {
+        let old = run(&[("foo", "1.1"), ("bar", "2.0"), ("baz", "3.0")]);
+        let new = run(&[("foo", "1.2"), ("baz", "3.0"), ("qux", "0.2")]);
+        let diff = diff(&old, &new);
+        let names = |list: &[(&str, &Recorded)]| -> Vec<String> {
+            list.iter().map(|(name, _)| name.to_string()).collect()
+        };
+        assert_eq!(names(&diff.added), ["qux"]);
+        assert_eq!(names(&diff.removed), ["bar"]);
+        assert_eq!(diff.changed.len(), 1);
+        let (name, before, after) = diff.changed[0];
+        assert_eq!((name, before.new_version.as_str()), ("foo", "1.1"));
+        assert_eq!(after.new_version, "1.2");
+    }
+}
@@ -0,0 +1,196 @@
+// A minimal HTTP/1.1 server for `vupdate serve`: GET and HEAD requests, one
+// per connection, each answered by a function of the request. It only needs
+// to serve a dashboard or a scraper on the local network, not the internet.
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// How large the request line and headers may be
+const MAX_HEAD: usize = 16 * 1024;
+// How long a client may take to send them
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Request {
+    pub method: String,
+    // The path without the query string, e.g. `/updates`
+    pub path: String,
+    pub query: Option<String>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: u16, value: &serde_json::Value) -> Response {
+        let mut body = serde_json::to_string_pretty(value).unwrap();
+        body.push('\n');
+        Response {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    // A JSON object telling what went wrong
+    pub fn error(status: u16, message: &str) -> Response {
+        Response::json(status, &serde_json::json!({ "error": message }))
+    }
+}
+
+// Answer the connections to `listener` with `handler` until the process ends
+pub async fn serve<F>(listener: TcpListener, handler: F)
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    loop {
+        // Failures like running out of file descriptors pass
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(_) => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            // A client going away is its own business
+            let _ = respond(stream, &*handler).await;
+        });
+    }
+}
+
+async fn respond<F>(mut stream: TcpStream, handler: &F) -> io::Result<()>
+where
+    F: Fn(&Request) -> Response,
+{
+    let head = match tokio::time::timeout(READ_TIMEOUT, read_head(&mut stream)).await {
+        Ok(head) => head?,
+        Err(_) => return Ok(()),
+    };
+    let (request, response) = match parse_request(&head) {
+        None => (None, Response::error(400, "bad request")),
+        Some(request) if request.method != "GET" && request.method != "HEAD" => (
+            Some(request),
+            Response::error(405, "only GET and HEAD are supported"),
+        ),
+        Some(request) => {
+            let response = handler(&request);
+            (Some(request), response)
+        }
+    };
+
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    if response.status == 405 {
+        out.push_str("Allow: GET, HEAD\r\n");
+    }
+    out.push_str("\r\n");
+    if request.is_none_or(|request| request.method != "HEAD") {
+        out.push_str(&response.body);
+    }
+    stream.write_all(out.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// Read up to the end of the headers. Request bodies are not supported.
+async fn read_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_HEAD {
+            break;
+        }
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn parse_request(head: &str) -> Option<Request> {
+    let line = head.lines().next()?;
+    let mut parts = line.split(' ');
+    let method = parts.next()?;
+    let target = parts.next()?;
+    if !parts.next()?.starts_with("HTTP/1.") || !target.starts_with('/') {
+        return None;
+    }
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+    Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+    })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_request_line() {
+        let request =
+            parse_request("GET /updates?section=installed HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/updates");
+        assert_eq!(request.query.as_deref(), Some("section=installed"));
+        let request = parse_request("HEAD /metrics HTTP/1.0\r\n\r\n").unwrap();
+        assert_eq!(request.method, "HEAD");
+        assert_eq!(request.path, "/metrics");
+        assert_eq!(request.query, None);
+        let request = parse_request("GET /?a=1?b=2 HTTP/1.1\n\n").unwrap();
+        assert_eq!(request.path, "/");
+        assert_eq!(request.query.as_deref(), Some("a=1?b=2"));
+        // The method is checked when answering
+        assert_eq!(parse_request("POST / HTTP/1.1\r\n").unwrap().method, "POST");
+    }
+
+    #[test]
+    fn rejects_malformed_request_lines() {
+        assert!(parse_request("").is_none());
+        assert!(parse_request("GET /updates").is_none());
+        assert!(parse_request("GET /updates HTTP/2\r\n").is_none());
+        assert!(parse_request("GET updates HTTP/1.1\r\n").is_none());
+        assert!(parse_request("GET http://a/updates HTTP/1.1\r\n").is_none());
+        assert!(parse_request("GET  /updates HTTP/1.1\r\n").is_none());
+    }
+
+    #[test]
+    fn answers_errors_as_json() {
+        let response = Response::error(404, "no such endpoint");
+        assert_eq!(response.status, 404);
+        assert_eq!(response.content_type, "application/json");
+        let body: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body, serde_json::json!({"error": "no such endpoint"}));
+        assert_eq!(reason(404), "Not Found");
+    }
+}
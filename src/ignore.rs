@@ -0,0 +1,266 @@
+// The ignore file lists packages whose updates should not be reported. It
+// lives next to the configuration file, at `$XDG_CONFIG_HOME/vupdate/ignore`,
+// and holds one package name per line. Blank lines and lines starting with
+// `#` are kept as they are when the file is rewritten.
+//
+// An entry can be limited so that the package shows up again later:
+//
+// ```
+// foo until_version=2.0   # hidden until an update newer than 2.0 appears
+// bar until=2025-01-01    # hidden until the given date
+// ```
+
+use crate::config::config_dir;
+use crate::{date, version};
+use std::cmp::Ordering;
+use std::io;
+use std::path::PathBuf;
+
+pub struct IgnoreEntry {
+    pub pkg_name: String,
+    pub until_version: Option<String>,
+    pub until: Option<String>,
+}
+
+impl IgnoreEntry {
+    pub fn new(pkg_name: &str) -> IgnoreEntry {
+        IgnoreEntry {
+            pkg_name: pkg_name.to_string(),
+            until_version: None,
+            until: None,
+        }
+    }
+
+    // Parse a line of the ignore file, returning `None` for blank lines and
+    // comments
+    fn parse(line: &str) -> Option<Result<IgnoreEntry, String>> {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut words = line.split_whitespace();
+        let mut entry = IgnoreEntry::new(words.next()?);
+        for word in words {
+            match word.split_once('=') {
+                Some(("until_version", version)) if !version.is_empty() => {
+                    entry.until_version = Some(version.to_string())
+                }
+                Some(("until", day)) if date::is_valid(day) => entry.until = Some(day.to_string()),
+                _ => return Some(Err(format!("invalid attribute '{}'", word))),
+            }
+        }
+        Some(Ok(entry))
+    }
+
+    // Whether this entry hides an update of its package to `new_version` on
+    // the date `today`
+    pub fn hides(&self, new_version: &str, today: &str) -> bool {
+        if let Some(until) = &self.until {
+            if today >= until.as_str() {
+                return false;
+            }
+        }
+        if let Some(until_version) = &self.until_version {
+            if version::compare(new_version, until_version) == Ordering::Greater {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl std::fmt::Display for IgnoreEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.pkg_name)?;
+        if let Some(until_version) = &self.until_version {
+            write!(f, " until_version={}", until_version)?;
+        }
+        if let Some(until) = &self.until {
+            write!(f, " until={}", until)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct IgnoreList {
+    path: PathBuf,
+    lines: Vec<String>,
+    entries: Vec<Option<IgnoreEntry>>,
+}
+
+impl IgnoreList {
+    pub fn path() -> io::Result<PathBuf> {
+        config_dir()
+            .map(|dir| dir.join("ignore"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+    }
+
+    // Load the ignore file. A missing file is an empty list.
+    pub fn load() -> io::Result<IgnoreList> {
+        let path = IgnoreList::path()?;
+        let lines: Vec<String> = match std::fs::read_to_string(&path) {
+            Ok(text) => text.lines().map(String::from).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let entries = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                IgnoreEntry::parse(line).transpose().map_err(|msg| {
+                    let msg = format!("{}:{}: {}", path.display(), i + 1, msg);
+                    io::Error::new(io::ErrorKind::InvalidData, msg)
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(IgnoreList {
+            path,
+            lines,
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut text = self.lines.join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        std::fs::write(&self.path, text)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &IgnoreEntry> {
+        self.entries.iter().flatten()
+    }
+
+    fn position(&self, pkg_name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|e| matches!(e, Some(e) if e.pkg_name == pkg_name))
+    }
+
+    // Whether the update of `pkg_name` to `new_version` should be hidden today
+    pub fn hides(&self, pkg_name: &str, new_version: &str, today: &str) -> bool {
+        self.entries()
+            .any(|e| e.pkg_name == pkg_name && e.hides(new_version, today))
+    }
+
+    // Add an entry, replacing any existing entry for the same package.
+    // Returns false if the package was already ignored.
+    pub fn add(&mut self, entry: IgnoreEntry) -> bool {
+        let line = entry.to_string();
+        match self.position(&entry.pkg_name) {
+            Some(i) => {
+                self.lines[i] = line;
+                self.entries[i] = Some(entry);
+                false
+            }
+            None => {
+                self.lines.push(line);
+                self.entries.push(Some(entry));
+                true
+            }
+        }
+    }
+
+    // Remove a package, returning false if it was not ignored
+    pub fn remove(&mut self, pkg_name: &str) -> bool {
+        let mut removed = false;
+        while let Some(i) = self.position(pkg_name) {
+            self.lines.remove(i);
+            self.entries.remove(i);
+            removed = true;
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(text: &str) -> IgnoreList {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let entries = lines
+            .iter()
+            .map(|line| IgnoreEntry::parse(line).transpose().unwrap())
+            .collect();
+        IgnoreList {
+            path: PathBuf::from("ignore"),
+            lines,
+            entries,
+        }
+    }
+
+    #[test]
+    fn parses_names_and_skips_comments() {
+        assert!(IgnoreEntry::parse("").is_none());
+        assert!(IgnoreEntry::parse("   # just a comment").is_none());
+        let entry = IgnoreEntry::parse("  foo   # broken upstream")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.pkg_name, "foo");
+        assert_eq!(entry.until_version, None);
+        assert_eq!(entry.until, None);
+    }
+
+    #[test]
+    fn hides_every_update_of_listed_packages() {
+        let ignored = list("# pinned\nfoo\n\nbar");
+        assert!(ignored.hides("foo", "1.0", "2024-06-01"));
+        assert!(ignored.hides("bar", "99", "2099-01-01"));
+        assert!(!ignored.hides("baz", "1.0", "2024-06-01"));
+        assert!(!ignored.hides("fo", "1.0", "2024-06-01"));
+    }
+
+    #[test]
+    fn adds_and_removes_entries_keeping_the_comments() {
+        let mut ignored = list("# pinned\nfoo\n\nbar");
+        assert!(ignored.add(IgnoreEntry::new("baz")));
+        assert!(!ignored.add(IgnoreEntry::new("foo")));
+        assert!(ignored.remove("bar"));
+        assert!(!ignored.remove("bar"));
+        assert_eq!(ignored.lines, ["# pinned", "foo", "", "baz"]);
+        let names: Vec<&str> = ignored.entries().map(|e| e.pkg_name.as_str()).collect();
+        assert_eq!(names, ["foo", "baz"]);
+    }
+
+    #[test]
+    fn parses_snoozed_entries() {
+        let entry = IgnoreEntry::parse("foo until_version=2.0 until=2025-01-01")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.until_version.as_deref(), Some("2.0"));
+        assert_eq!(entry.until.as_deref(), Some("2025-01-01"));
+        assert_eq!(entry.to_string(), "foo until_version=2.0 until=2025-01-01");
+        let error = |line: &str| IgnoreEntry::parse(line).unwrap().err().unwrap();
+        assert_eq!(error("foo until=soon"), "invalid attribute 'until=soon'");
+        assert_eq!(
+            error("foo until_version="),
+            "invalid attribute 'until_version='"
+        );
+        assert_eq!(error("foo forever"), "invalid attribute 'forever'");
+    }
+
+    #[test]
+    fn stops_hiding_after_the_version_or_date() {
+        let version = list("foo until_version=2.0").entries.remove(0).unwrap();
+        assert!(version.hides("1.9", "2024-06-01"));
+        assert!(version.hides("2.0", "2024-06-01"));
+        assert!(!version.hides("2.0.1", "2024-06-01"));
+        assert!(!version.hides("10.0", "2024-06-01"));
+
+        let date = list("foo until=2025-01-01").entries.remove(0).unwrap();
+        assert!(date.hides("9.9", "2024-12-31"));
+        assert!(!date.hides("9.9", "2025-01-01"));
+        assert!(!date.hides("9.9", "2025-02-01"));
+
+        // Either limit running out is enough to show the package again
+        let both = list("foo until_version=2.0 until=2025-01-01");
+        assert!(both.hides("foo", "1.9", "2024-12-31"));
+        assert!(!both.hides("foo", "2.1", "2024-12-31"));
+        assert!(!both.hides("foo", "1.9", "2025-01-01"));
+    }
+}
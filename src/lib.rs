@@ -0,0 +1,56 @@
+// The library half of vupdate: fetching and parsing the void-updates lists,
+// querying the installed packages and the filters and renderers built on top.
+// The `vupdate` binary is a thin command line interface over this crate, and
+// other programs (e.g. a status bar) can embed the same logic:
+//
+// ```
+// let updates = vupdate::fetch_maintainer_updates("me@example.org").await?;
+// let installed = vupdate::installed_packages();
+// for (name, update) in updates.sorted() { ... }
+// ```
+
+pub mod builds;
+pub mod cache;
+pub mod changelog;
+pub mod config;
+pub mod date;
+pub mod dbus;
+pub mod duration;
+pub mod eol;
+pub mod error;
+pub mod fetch;
+pub mod git;
+pub mod github;
+pub mod gitlab;
+pub mod history;
+pub mod http;
+pub mod ignore;
+#[cfg(feature = "libxbps")]
+pub mod libxbps;
+pub mod mangle;
+pub mod metrics;
+pub mod notes;
+pub mod notify;
+pub mod osv;
+pub mod output;
+pub mod parse;
+pub mod pattern;
+pub mod plist;
+pub mod plugin;
+pub mod popcorn;
+pub mod repology;
+pub mod sha256;
+pub mod smtp;
+pub mod sources;
+pub mod status;
+pub mod template;
+pub mod toml;
+pub mod upstream;
+pub mod version;
+pub mod watchlist;
+pub mod xbps;
+
+pub use error::Error;
+pub use fetch::{fetch_all_updates, fetch_maintainer_updates};
+pub use parse::{PackageUpdate, UpdateMap};
+pub use xbps::installed_packages;
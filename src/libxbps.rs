@@ -0,0 +1,24 @@
+// Bindings to libxbps, enabled with the `libxbps` cargo feature. Only the
+// version comparison is bound: it is a plain function of two strings, while
+// the package database API needs an initialized `struct xbps_handle` whose
+// layout changes between xbps releases.
+
+use libc::{c_char, c_int};
+use std::cmp::Ordering;
+use std::ffi::CString;
+
+#[link(name = "xbps")]
+extern "C" {
+    fn xbps_cmpver(pkg1: *const c_char, pkg2: *const c_char) -> c_int;
+}
+
+// Compare two versions the way xbps does, or `None` if one of them cannot be
+// passed to C
+pub fn cmpver(a: &str, b: &str) -> Option<Ordering> {
+    let a = CString::new(a).ok()?;
+    let b = CString::new(b).ok()?;
+    // SAFETY: both arguments are valid NUL-terminated strings that outlive
+    // the call, and xbps_cmpver does not keep them
+    let result = unsafe { xbps_cmpver(a.as_ptr(), b.as_ptr()) };
+    Some(result.cmp(&0))
+}
@@ -28,160 +28,109 @@
 * - Packages for which the user is the maintainer.
 * - Packages which are installed on the system.
 *
-* TODO:
-*   - Don't hardcode user email.
-*   - Support an ignore file to prevent clutter.
+* The maintainer email and other settings are read from a configuration file,
+* see `config.rs`, and can be overridden from the command line, see `cli.rs`.
+* Each subcommand lives in its own module under `commands/`; listing the
+* updates is `check`, which runs when no command is given.
+*
+* Updates for packages listed in the ignore file are not reported, see
+* `ignore.rs`. Packages in the watchlist are reported in a section of their
+* own, see `watchlist.rs`.
+*
+* Everything but the command line interface (this file, `cli.rs` and
+* `commands/`) lives in the library crate, see `lib.rs`.
 */
 
-use colored::Colorize;
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::{HashMap, HashSet};
-use std::process::Command;
-
-// Will be using this to construct URLs for making HTTP requests
-static VOID_URL: &str = "https://alpha.de.repo.voidlinux.org/void-updates/void-updates";
-static EMAIL: &str = "kartik.ynwa@gmail.com";
+mod cli;
+mod commands;
 
-// Data type for storing package update information
-struct PackageUpdate {
-    current_version: String,
-    new_version: String,
-}
-
-// Type alias for storing a directory of packages and their update information
-struct UpdateMap(HashMap<String, PackageUpdate>);
+use vupdate::config::{ColorChoice, Config, Section};
 
-impl UpdateMap {
-    fn new() -> UpdateMap {
-        UpdateMap(HashMap::new())
+#[tokio::main]
+async fn main() {
+    let args = cli::parse(std::env::args().skip(1)).unwrap_or_else(|e| cli::usage_error(e));
+    if args.flag("help") {
+        let command = Some(args.command).filter(|_| args.explicit_command);
+        print!("{}", cli::help(command));
+        return;
+    }
+    if args.flag("version") {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        return;
     }
-}
 
-impl std::fmt::Display for UpdateMap {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut keys: Vec<&String> = self.0.keys().collect();
-        keys.sort();
-        for key in keys {
-            let update = self.0.get(key).unwrap();
-            writeln!(
-                f,
-                "{}\t{} -> {}",
-                key, update.current_version, update.new_version
-            )?;
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            cli::error(e);
+            std::process::exit(1);
         }
-        Ok(())
+    };
+    let emails = args.values("email");
+    if !emails.is_empty() {
+        config.emails = emails;
     }
-}
-
-// Function to make an HTTP request and return the body as a String
-async fn get_http_response(url: &str) -> Result<String, reqwest::Error> {
-    let response = reqwest::get(url).await?;
-    let text = response.text().await.unwrap();
-    Ok(text)
-}
-
-// Get a list of installed packages by running the command `xbps-query -l` and
-// parsing the output.
-fn get_installed_packages() -> HashSet<String> {
-    let xq_output = Command::new("xbps-query")
-        .arg("-m")
-        .output()
-        .expect("Could not run xbps-query");
-    let xq_stdout = String::from_utf8(xq_output.stdout).unwrap();
-
-    let re = Regex::new(r"(\S+)-\S+?").unwrap();
-
-    xq_stdout
-        .lines()
-        .filter_map(|l| re.captures(l))
-        .map(|c| String::from(&c[1]))
-        .collect()
-}
-
-// Get the names of packages for which updates are available and for which I am
-// the maintainer.
-async fn get_maintainer_updates() -> Result<UpdateMap, reqwest::Error> {
-    let url = format!("{}/updates_{}.txt", VOID_URL, EMAIL);
-    let body = get_http_response(&url).await?;
-    Ok(response_to_hashmap(&body))
-}
-
-// Get the names of installed packages for updates are available.
-async fn get_all_updates() -> Result<UpdateMap, reqwest::Error> {
-    let url = format!("{}{}", VOID_URL, ".txt");
-
-    let body = get_http_response(&url).await?;
-    Ok(response_to_hashmap(&body))
-}
-
-// Parse the response body from updates.txt files into an UpdateMap
-fn response_to_hashmap(body: &str) -> UpdateMap {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"(\S+)\s+(\S+)\s+->\s+(\S+)").unwrap();
+    // Someone else's queue replaces one's own report
+    let maintainers = args.values("maintainer");
+    if !maintainers.is_empty() {
+        config.emails = maintainers;
+        config.sections = vec![Section::Maintainer];
     }
-
-    let mut pkg_updates = UpdateMap::new();
-
-    for cap in body.lines().filter_map(|l| RE.captures(l)) {
-        let pkg_name = String::from(&cap[1]);
-        let pkg_update = PackageUpdate {
-            current_version: String::from(&cap[2]),
-            new_version: String::from(&cap[3]),
-        };
-        let mut insert = true;
-        if let Some(existing_pkg_update) = pkg_updates.0.get(&pkg_name) {
-            if pkg_update.new_version <= existing_pkg_update.new_version {
-                insert = false;
-            }
-        }
-        if insert {
-            pkg_updates.0.insert(pkg_name, pkg_update);
-        }
+    if let Some(when) = args.value("color") {
+        config.color = ColorChoice::from_name(&when)
+            .unwrap_or_else(|| cli::usage_error(cli::CliError::invalid_value("color", when)));
     }
-    pkg_updates
-}
-
-#[tokio::main]
-async fn main() {
-    let (maintainer_updates_result, installed_updates_result) =
-        tokio::join!(get_maintainer_updates(), get_all_updates());
-
-    let maintainer_updates = match maintainer_updates_result {
-        Ok(updates) => updates,
-        _ => {
-            let error_msg = format!("Could not fetch updates_{}.txt", EMAIL);
-            println!("{}", &error_msg.red());
-            UpdateMap::new()
-        }
-    };
-
-    let mut installed_updates = match installed_updates_result {
-        Ok(updates) => updates,
-        _ => {
-            println!("{}", &"Could not fetch void-updates.txt".red());
-            UpdateMap::new()
-        }
-    };
-
-    // Only keep updates for packages that are: a) Installed, b) Not being maintained by me
-    let installed_pkgs = get_installed_packages();
-    installed_updates
-        .0
-        .retain(|k, _| installed_pkgs.contains(k) && !maintainer_updates.0.contains_key(k));
-
-    // Print packages for which I am the maintainer
-    if !maintainer_updates.0.is_empty() {
-        println!("{}", &"Maintainer updates:".bold().blue().underline());
-        println!("{}", &maintainer_updates);
+    if let Some(timeout) = args.value("timeout") {
+        config.fetch.timeout = vupdate::duration::parse(&timeout)
+            .filter(|d| !d.is_zero())
+            .unwrap_or_else(|| cli::usage_error(cli::CliError::invalid_value("timeout", timeout)));
     }
-
-    // Print packages which are currently installed
-    if !installed_updates.0.is_empty() {
-        println!(
-            "{}",
-            &"Updates for installed packages:".bold().blue().underline()
-        );
-        print!("{}", &installed_updates);
+    if let Some(url) = args.value("url") {
+        config.fetch.mirrors = vec![url];
     }
+    config.fetch.offline = args.flag("offline");
+    if let Some(path) = args.value("from-file") {
+        config.fetch.from_file = Some(path.into());
+        // Only the full list can be read from a file
+        config.emails.clear();
+    }
+    if let Some(command) = args.value("exec") {
+        config.on_new_update = Some(command);
+    }
+    if let Some(path) = args.value("distdir") {
+        config.distdir = Some(path.into());
+    }
+    if args.flag("quiet") {
+        config.color = ColorChoice::Never;
+    }
+    colored::control::set_override(config.color.enabled());
+
+    let code = match args.command {
+        "check" => commands::check::run(&config, &args).await,
+        "ignore" => commands::ignore::run(&args),
+        "info" => commands::info::run(&config, &args).await,
+        "open" => commands::open::run(&config, &args).await,
+        "changelog" => commands::changelog::run(&config, &args).await,
+        "bump" => commands::bump::run(&config, &args).await,
+        "pr" => commands::pr::run(&config, &args).await,
+        "build" => commands::build::run(&config, &args),
+        "revbump" => commands::revbump::run(&config, &args),
+        "report" => commands::report::run(&config, &args).await,
+        "diff" => commands::diff::run(&args),
+        "digest" => commands::digest::run(&config, &args).await,
+        "stats" => commands::stats::run(&config, &args),
+        "maintainers" => commands::maintainers::run(&config, &args).await,
+        "status" => commands::status::run(&config, &args).await,
+        "note" => commands::note::run(&args),
+        "worktree" => commands::worktree::run(&config, &args),
+        "watch" => commands::watch::run(&config, &args).await,
+        "serve" => commands::serve::run(&config, &args).await,
+        "pick" => commands::pick::run(&config, &args).await,
+        "systemd-install" => commands::systemd_install::run(&args),
+        "completions" => commands::completions::run(&args),
+        "mangen" => commands::mangen::run(&args),
+        "__complete" => commands::completions::helper(&config, &args),
+        _ => unreachable!("commands are validated by the parser"),
+    };
+    std::process::exit(code);
 }
@@ -0,0 +1,90 @@
+// Rules rewriting the upstream versions of packages with unconventional
+// versioning before they are compared with the packaged version, configured
+// per package in the `[mangle]` table:
+//
+// ```toml
+// [mangle]
+// foo = ["strip-v", "underscores"]   # v1_2_3 -> 1.2.3
+// bar = ["drop-date"]                # 2.1-20240115 -> 2.1
+// ```
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // A date at the end of a version, e.g. `-20240115`, `+git20240115` or
+    // `_2024-01-15`
+    static ref DATE_SUFFIX: Regex =
+        Regex::new(r"[-_.+~]?(git|svn|hg|snapshot)?\d{4}-?\d{2}-?\d{2}$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    // `v1.2` -> `1.2`
+    StripV,
+    // `1_2_3` -> `1.2.3`
+    Underscores,
+    // `1.2-20240115` -> `1.2`
+    DropDate,
+}
+
+impl Rule {
+    pub fn from_name(name: &str) -> Option<Rule> {
+        match name {
+            "strip-v" => Some(Rule::StripV),
+            "underscores" => Some(Rule::Underscores),
+            "drop-date" => Some(Rule::DropDate),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, version: &str) -> String {
+        match self {
+            Rule::StripV => match version.strip_prefix(['v', 'V']) {
+                Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest.to_string(),
+                _ => version.to_string(),
+            },
+            Rule::Underscores => version.replace('_', "."),
+            Rule::DropDate => match DATE_SUFFIX.find(version) {
+                // A version that is nothing but a date stays one
+                Some(date) if date.start() > 0 => version[..date.start()].to_string(),
+                _ => version.to_string(),
+            },
+        }
+    }
+}
+
+// `version` with `rules` applied in order
+pub fn apply(rules: &[Rule], version: &str) -> String {
+    rules
+        .iter()
+        .fold(version.to_string(), |version, rule| rule.apply(&version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_v_before_a_number_only() {
+        assert_eq!(Rule::StripV.apply("v1.2"), "1.2");
+        assert_eq!(Rule::StripV.apply("V2"), "2");
+        assert_eq!(Rule::StripV.apply("vim-9.0"), "vim-9.0");
+    }
+
+    #[test]
+    fn drops_date_suffixes() {
+        assert_eq!(Rule::DropDate.apply("2.1-20240115"), "2.1");
+        assert_eq!(Rule::DropDate.apply("2.1+git20240115"), "2.1");
+        assert_eq!(Rule::DropDate.apply("2.1_2024-01-15"), "2.1");
+        assert_eq!(Rule::DropDate.apply("20240115"), "20240115");
+        assert_eq!(Rule::DropDate.apply("2.1.3"), "2.1.3");
+    }
+
+    #[test]
+    fn applies_rules_in_order() {
+        let rules = [Rule::StripV, Rule::Underscores, Rule::DropDate];
+        assert_eq!(apply(&rules, "v1_2_3-20240115"), "1.2.3");
+        assert_eq!(apply(&[], "v1_2"), "v1_2");
+    }
+}
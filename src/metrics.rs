@@ -0,0 +1,109 @@
+// Metrics about the checks of `vupdate serve` and `vupdate watch --listen`,
+// served at `/metrics` in Prometheus' text format:
+//
+// ```
+// vupdate_pending_updates{section="maintainer"} 3
+// vupdate_check_errors_total 1
+// ```
+
+use crate::config::Section;
+use crate::parse::UpdateMap;
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    checks: u64,
+    // Checks that ran into errors, e.g. fetching a list, or found nothing
+    errors: u64,
+    last_check: Option<u64>,
+    last_success: Option<u64>,
+    // The updates and the new ones per section, as of the last check that
+    // found any
+    pending: Vec<(Section, usize, usize)>,
+}
+
+impl Metrics {
+    // Count a check finished at `time` that found `sections`, or nothing at
+    // all
+    pub fn record(&mut self, time: u64, sections: Option<&[(Section, UpdateMap)]>, failed: bool) {
+        self.checks += 1;
+        self.last_check = Some(time);
+        if failed || sections.is_none() {
+            self.errors += 1;
+        } else {
+            self.last_success = Some(time);
+        }
+        if let Some(sections) = sections {
+            self.pending = sections
+                .iter()
+                .map(|(section, updates)| {
+                    let new = updates
+                        .0
+                        .values()
+                        .filter(|u| u.tags.iter().any(|t| t == "NEW"))
+                        .count();
+                    (*section, updates.0.len(), new)
+                })
+                .collect();
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, u64)>| {
+            out.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n",
+                name, help, name, kind
+            ));
+            for (labels, value) in samples {
+                out.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+        let per_section = |value: fn(&(Section, usize, usize)) -> usize| {
+            self.pending
+                .iter()
+                .map(|entry| {
+                    let labels = format!("{{section=\"{}\"}}", entry.0.name());
+                    (labels, value(entry) as u64)
+                })
+                .collect()
+        };
+        metric(
+            "vupdate_pending_updates",
+            "gauge",
+            "Updates listed by the last check",
+            per_section(|(_, pending, _)| *pending),
+        );
+        metric(
+            "vupdate_new_updates",
+            "gauge",
+            "Updates the last check listed for the first time",
+            per_section(|(_, _, new)| *new),
+        );
+        metric(
+            "vupdate_checks_total",
+            "counter",
+            "Checks run",
+            vec![(String::new(), self.checks)],
+        );
+        metric(
+            "vupdate_check_errors_total",
+            "counter",
+            "Checks that ran into errors, e.g. fetching an updates list",
+            vec![(String::new(), self.errors)],
+        );
+        let time = |time: Option<u64>| time.map(|t| (String::new(), t)).into_iter().collect();
+        metric(
+            "vupdate_last_check_timestamp_seconds",
+            "gauge",
+            "When the last check finished",
+            time(self.last_check),
+        );
+        metric(
+            "vupdate_last_success_timestamp_seconds",
+            "gauge",
+            "When the last check without errors finished",
+            time(self.last_success),
+        );
+        out
+    }
+}
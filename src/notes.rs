@@ -0,0 +1,104 @@
+// Free-form notes on packages, e.g. `waiting on upstream fix for musl`, kept
+// in `$XDG_DATA_HOME/vupdate/notes.jsonl` with one JSON object per line so
+// that the context of an update is not lost between sessions. Unlike the
+// status, a note is not tied to a version and stays until it is removed.
+
+use crate::config::data_dir;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    pub pkg_name: String,
+    pub text: String,
+    // The day the note was written, as `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl Note {
+    fn to_json(&self) -> Value {
+        json!({
+            "pkgname": self.pkg_name,
+            "note": self.text,
+            "date": self.date,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Note> {
+        Some(Note {
+            pkg_name: value.get("pkgname")?.as_str()?.to_string(),
+            text: value.get("note")?.as_str()?.to_string(),
+            date: value.get("date")?.as_str()?.to_string(),
+        })
+    }
+}
+
+pub struct NoteStore {
+    path: PathBuf,
+    notes: BTreeMap<String, Note>,
+}
+
+impl NoteStore {
+    pub fn path() -> io::Result<PathBuf> {
+        data_dir()
+            .map(|dir| dir.join("notes.jsonl"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+    }
+
+    // Load the notes file. A missing file means there are no notes.
+    pub fn load() -> io::Result<NoteStore> {
+        let path = NoteStore::path()?;
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut notes = BTreeMap::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let note = serde_json::from_str(line)
+                .ok()
+                .and_then(|value| Note::from_json(&value))
+                .ok_or_else(|| {
+                    let msg = format!("{}:{}: invalid note", path.display(), i + 1);
+                    io::Error::new(io::ErrorKind::InvalidData, msg)
+                })?;
+            notes.insert(note.pkg_name.clone(), note);
+        }
+        Ok(NoteStore { path, notes })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .notes
+            .values()
+            .map(|note| format!("{}\n", note.to_json()))
+            .collect();
+        std::fs::write(&self.path, text)
+    }
+
+    pub fn get(&self, pkg_name: &str) -> Option<&Note> {
+        self.notes.get(pkg_name)
+    }
+
+    pub fn notes(&self) -> impl Iterator<Item = &Note> {
+        self.notes.values()
+    }
+
+    // Record `note`, replacing the package's previous one
+    pub fn set(&mut self, note: Note) {
+        self.notes.insert(note.pkg_name.clone(), note);
+    }
+
+    // Forget the note on `pkg_name`, returning whether there was one
+    pub fn remove(&mut self, pkg_name: &str) -> bool {
+        self.notes.remove(pkg_name).is_some()
+    }
+}
@@ -0,0 +1,397 @@
+// Push notifications about new updates, sent by `vupdate check` to the
+// services configured with `[[notify]]` tables:
+//
+// ```toml
+// [[notify]]
+// service = "ntfy"
+// url = "https://ntfy.sh/my-updates"
+// sections = ["maintainer"]
+// ```
+//
+// A `webhook` gets the new updates as JSON:
+//
+// ```
+// {"updates": [{"package": "vim", "section": "installed", "current": "9.0.1", "new": "9.1.0", "previous": null, "url": "https://github.com/vim/vim"}], "text": "...", "content": "..."}
+// ```
+//
+// Any other service is a plugin, see `plugin`, which gets the same JSON with
+// the table's `url` and `token` when given.
+
+use crate::config::Section;
+use crate::error::{Error, Result};
+use crate::output::xml_escape;
+use crate::parse::PackageUpdate;
+use crate::plugin::{self, Plugin};
+use serde_json::{json, Value};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The Bot API, unless `url` points at a self-hosted one
+pub const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Service {
+    // https://ntfy.sh or a self-hosted server, `url` being the topic's URL
+    Ntfy,
+    // A Gotify server, `url` being its base URL
+    Gotify,
+    // Any URL accepting the updates as JSON
+    Webhook,
+    // A Matrix room, `url` being the homeserver
+    Matrix,
+    // A Telegram chat, messaged by a bot
+    Telegram,
+    // A `vupdate-notify-<name>` executable
+    Plugin(Plugin),
+}
+
+impl Service {
+    // The built-in service `name`, or else the plugin by that name
+    pub fn from_name(name: &str) -> Service {
+        match name {
+            "ntfy" => Service::Ntfy,
+            "gotify" => Service::Gotify,
+            "webhook" => Service::Webhook,
+            "matrix" => Service::Matrix,
+            "telegram" => Service::Telegram,
+            _ => Service::Plugin(Plugin::new(plugin::Kind::Notify, name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notifier {
+    pub service: Service,
+    // Optional for plugins, which get an empty one when it is not given
+    pub url: String,
+    // ntfy's access token, Gotify's application token, Matrix's access token,
+    // the Telegram bot's token or a bearer token for a webhook
+    pub token: Option<String>,
+    // The Matrix room to post to, e.g. `!abcdef:matrix.org`
+    pub room: Option<String>,
+    // The Telegram chat to message
+    pub chat_id: Option<String>,
+    // The sections whose updates are sent, all of them if empty
+    pub sections: Vec<Section>,
+    // Further HTTP headers to send, e.g. for authentication
+    pub headers: Vec<(String, String)>,
+}
+
+// A new update to tell about
+pub struct Notice<'a> {
+    pub pkg_name: &'a str,
+    pub section: Section,
+    pub update: &'a PackageUpdate,
+    // The new version the last recorded run listed, if the update changed
+    pub previous: Option<&'a str>,
+}
+
+impl Notifier {
+    pub fn wants(&self, section: Section) -> bool {
+        self.sections.is_empty() || self.sections.contains(&section)
+    }
+
+    // Send one notification listing the notices of the wanted sections, if
+    // there are any
+    pub async fn send(&self, client: &reqwest::Client, notices: &[Notice<'_>]) -> Result<()> {
+        let notices: Vec<&Notice> = notices.iter().filter(|n| self.wants(n.section)).collect();
+        if notices.is_empty() {
+            return Ok(());
+        }
+        let title = match notices.len() {
+            1 => "1 new update".to_string(),
+            n => format!("{} new updates", n),
+        };
+        let message: Vec<String> = notices
+            .iter()
+            .map(|n| {
+                format!(
+                    "{} {} -> {}",
+                    n.pkg_name, n.update.current_version, n.update.new_version
+                )
+            })
+            .collect();
+        let message = message.join("\n");
+
+        let (url, request) = match &self.service {
+            Service::Ntfy => {
+                let mut request = client
+                    .post(&self.url)
+                    .header("Title", &title)
+                    .header("Tags", "package")
+                    .body(message);
+                if let Some(token) = &self.token {
+                    request = request.bearer_auth(token);
+                }
+                (self.url.clone(), request)
+            }
+            Service::Gotify => {
+                let url = format!("{}/message", self.url.trim_end_matches('/'));
+                let mut request = client
+                    .post(&url)
+                    .json(&json!({"title": title, "message": message}));
+                if let Some(token) = &self.token {
+                    request = request.header("X-Gotify-Key", token);
+                }
+                (url, request)
+            }
+            Service::Webhook => {
+                let body = webhook_body(&notices, &title, &message);
+                let mut request = client.post(&self.url).json(&body);
+                if let Some(token) = &self.token {
+                    request = request.bearer_auth(token);
+                }
+                (self.url.clone(), request)
+            }
+            Service::Matrix => {
+                let room = self.room.as_deref().unwrap_or_default();
+                let url = matrix_message_url(&self.url, room)?;
+                let items: String = notices
+                    .iter()
+                    .map(|n| {
+                        format!(
+                            "<li><b>{}</b> {} → {}</li>",
+                            xml_escape(n.pkg_name),
+                            xml_escape(&n.update.current_version),
+                            xml_escape(&n.update.new_version)
+                        )
+                    })
+                    .collect();
+                let mut request = client.put(&url).json(&json!({
+                    "msgtype": "m.notice",
+                    "body": format!("{}:\n{}", title, message),
+                    "format": "org.matrix.custom.html",
+                    "formatted_body": format!("{}:<ul>{}</ul>", title, items),
+                }));
+                if let Some(token) = &self.token {
+                    request = request.bearer_auth(token);
+                }
+                (url, request)
+            }
+            Service::Telegram => {
+                let token = self.token.as_deref().unwrap_or_default();
+                let url = format!(
+                    "{}/bot{}/sendMessage",
+                    self.url.trim_end_matches('/'),
+                    token
+                );
+                let lines: Vec<String> = notices
+                    .iter()
+                    .map(|n| {
+                        format!(
+                            "<b>{}</b> {} → {}",
+                            xml_escape(n.pkg_name),
+                            xml_escape(&n.update.current_version),
+                            xml_escape(&n.update.new_version)
+                        )
+                    })
+                    .collect();
+                let request = client.post(&url).json(&json!({
+                    "chat_id": self.chat_id,
+                    "text": format!("{}:\n{}", title, lines.join("\n")),
+                    "parse_mode": "HTML",
+                    "disable_web_page_preview": true,
+                }));
+                // The URL holds the token, which is not for error messages
+                let shown = format!("{}/bot.../sendMessage", self.url.trim_end_matches('/'));
+                (shown, request)
+            }
+            Service::Plugin(plugin) => {
+                let mut input = webhook_body(&notices, &title, &message);
+                if !self.url.is_empty() {
+                    input["url"] = json!(self.url);
+                }
+                if let Some(token) = &self.token {
+                    input["token"] = json!(token);
+                }
+                plugin.call(&input).await?;
+                return Ok(());
+            }
+        };
+        let request = self.headers.iter().fold(request, |request, (name, value)| {
+            request.header(name, value)
+        });
+        let response = request.send().await.map_err(|source| {
+            // reqwest's message would show the URL with the bot's token
+            if self.service == Service::Telegram {
+                let reason = if source.is_timeout() {
+                    "timed out"
+                } else if source.is_connect() {
+                    "could not connect"
+                } else {
+                    "request failed"
+                };
+                Error::Notify(format!("{}: {}", url, reason))
+            } else {
+                Error::Network {
+                    url: url.clone(),
+                    source,
+                }
+            }
+        })?;
+        if !response.status().is_success() {
+            return Err(Error::Status {
+                url,
+                status: response.status(),
+            });
+        }
+        Ok(())
+    }
+}
+
+// What a `webhook` is sent about `notices`. `text` and `content` are what
+// Slack's and Discord's incoming webhooks show.
+fn webhook_body(notices: &[&Notice], title: &str, message: &str) -> Value {
+    let updates: Vec<_> = notices
+        .iter()
+        .map(|n| {
+            json!({
+                "package": n.pkg_name,
+                "section": n.section.name(),
+                "current": n.update.current_version,
+                "new": n.update.new_version,
+                "previous": n.previous,
+                "url": n.update.url,
+            })
+        })
+        .collect();
+    json!({
+        "updates": updates,
+        "text": format!("{}:\n{}", title, message),
+        "content": format!("{}:\n{}", title, message),
+    })
+}
+
+// Run the `on_new_update` hook `command` with the shell for `notice`.
+// `{pkg}`, `{current}`, `{new}` and `{section}` are replaced by the update's,
+// already quoted for the shell, and are also in the environment as
+// `VUPDATE_PKG`, `VUPDATE_CURRENT`, `VUPDATE_NEW` and `VUPDATE_SECTION`.
+pub fn run_hook(command: &str, notice: &Notice) -> Result<()> {
+    let fields = hook_fields(notice);
+    let mut hook = Command::new("sh");
+    // Its output goes to stderr, away from the report
+    hook.arg("-c")
+        .arg(hook_script(command, &fields))
+        .stdin(Stdio::null())
+        .stdout(std::io::stderr());
+    for (name, value) in fields {
+        hook.env(format!("VUPDATE_{}", name.to_uppercase()), value);
+    }
+    let status = hook
+        .status()
+        .map_err(|e| Error::Hook(format!("could not run on_new_update: {}", e)))?;
+    if !status.success() {
+        return Err(Error::Hook(format!(
+            "on_new_update failed for {} with {}",
+            notice.pkg_name, status
+        )));
+    }
+    Ok(())
+}
+
+// The values a hook is given, by placeholder name
+fn hook_fields<'a>(notice: &Notice<'a>) -> [(&'static str, &'a str); 4] {
+    [
+        ("pkg", notice.pkg_name),
+        ("current", notice.update.current_version.as_str()),
+        ("new", notice.update.new_version.as_str()),
+        ("section", notice.section.name()),
+    ]
+}
+
+// `command` with its placeholders replaced by the quoted `fields`
+fn hook_script(command: &str, fields: &[(&str, &str)]) -> String {
+    let mut script = command.to_string();
+    for (name, value) in fields {
+        script = script.replace(&format!("{{{}}}", name), &shell_quote(value));
+    }
+    script
+}
+
+// `value` in single quotes, as one word for the shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Where a message is sent to a Matrix room. Each message needs a transaction
+// id of its own, so that the homeserver can tell retries from new messages.
+fn matrix_message_url(homeserver: &str, room: &str) -> Result<String> {
+    let mut url = reqwest::Url::parse(homeserver)
+        .map_err(|e| Error::Parse(format!("invalid homeserver URL '{}': {}", homeserver, e)))?;
+    let transaction = format!(
+        "vupdate-{}-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis()),
+        std::process::id()
+    );
+    url.path_segments_mut()
+        .map_err(|_| Error::Parse(format!("invalid homeserver URL '{}'", homeserver)))?
+        .pop_if_empty()
+        .extend(&[
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            room,
+            "send",
+            "m.room.message",
+            &transaction,
+        ]);
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::response_to_hashmap;
+
+    fn update(new_version: &str) -> PackageUpdate {
+        let mut update = response_to_hashmap("foo 1.0_1 -> 2.0\n")
+            .0
+            .remove("foo")
+            .unwrap();
+        update.new_version = new_version.to_string();
+        update
+    }
+
+    #[test]
+    fn quotes_the_values_put_in_hooks() {
+        let fields = [("pkg", "foo"), ("new", "2.0 it's $HOME")];
+        assert_eq!(
+            hook_script("echo {pkg}-{new} {other}", &fields),
+            r#"echo 'foo'-'2.0 it'\''s $HOME' {other}"#
+        );
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn runs_hooks_with_the_shell() {
+        let update = update("2.0 it's $HOME; false");
+        let notice = Notice {
+            pkg_name: "foo",
+            section: Section::Installed,
+            update: &update,
+            previous: None,
+        };
+        let out = std::env::temp_dir().join(format!("vupdate-hook-test-{}", std::process::id()));
+        let out_path = shell_quote(&out.display().to_string());
+        let command = format!(
+            "printf '%s|%s|%s|%s\\n' {{pkg}} {{current}} {{new}} {{section}} > {out}; \
+             printf '%s|%s' \"$VUPDATE_PKG\" \"$VUPDATE_NEW\" >> {out}",
+            out = out_path
+        );
+        run_hook(&command, &notice).unwrap();
+        let written = std::fs::read_to_string(&out).unwrap();
+        std::fs::remove_file(&out).unwrap();
+        assert_eq!(
+            written,
+            "foo|1.0_1|2.0 it's $HOME; false|installed\nfoo|2.0 it's $HOME; false"
+        );
+
+        match run_hook("exit 3", &notice) {
+            Err(Error::Hook(msg)) => assert!(msg.starts_with("on_new_update failed for foo")),
+            _ => panic!("a failing hook should be an error"),
+        }
+    }
+}
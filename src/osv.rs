@@ -0,0 +1,107 @@
+// Known vulnerabilities from OSV (https://osv.dev), to tell which updates fix
+// some. OSV has no Void Linux ecosystem, so only the packages of language
+// ecosystems it does follow can be looked up, by their upstream names.
+
+use crate::error::{Error, Result};
+use crate::parse::PackageUpdate;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+pub const OSV_URL: &str = "https://api.osv.dev";
+
+// How many versions one batch query asks about; OSV takes at most 1000
+const BATCH_SIZE: usize = 1000;
+
+// Void's package name prefixes for the ecosystems OSV follows, e.g.
+// `python3-requests` is `requests` on PyPI
+static ECOSYSTEMS: &[(&str, &str)] = &[
+    ("python3-", "PyPI"),
+    ("ruby-", "RubyGems"),
+    ("R-cran-", "CRAN"),
+];
+
+// The ecosystem and name OSV knows `pkg_name` by, if it follows it
+pub fn package(pkg_name: &str) -> Option<(&'static str, &str)> {
+    ECOSYSTEMS.iter().find_map(|(prefix, ecosystem)| {
+        let name = pkg_name.strip_prefix(prefix)?;
+        Some((*ecosystem, name)).filter(|(_, name)| !name.is_empty())
+    })
+}
+
+// The ids of the vulnerabilities affecting the current version of each update
+// but not its new one, sorted. Packages OSV cannot look up fix nothing.
+pub async fn fixed_vulnerabilities(
+    client: &reqwest::Client,
+    updates: &[(&str, &PackageUpdate)],
+) -> Result<Vec<Vec<String>>> {
+    let mut queries = Vec::new();
+    let mut asked = Vec::new();
+    for (i, (pkg_name, update)) in updates.iter().enumerate() {
+        if let Some((ecosystem, name)) = package(pkg_name) {
+            for version in [&update.current_version, &update.new_version] {
+                queries.push(json!({
+                    "package": {"ecosystem": ecosystem, "name": name},
+                    "version": version,
+                }));
+            }
+            asked.push(i);
+        }
+    }
+
+    let mut results = Vec::new();
+    for batch in queries.chunks(BATCH_SIZE) {
+        results.extend(query_batch(client, batch).await?);
+    }
+    let mut fixed = vec![Vec::new(); updates.len()];
+    for (i, pair) in asked.into_iter().zip(results.chunks(2)) {
+        if let [current, new] = pair {
+            let mut ids: Vec<String> = current.difference(new).cloned().collect();
+            ids.sort();
+            fixed[i] = ids;
+        }
+    }
+    Ok(fixed)
+}
+
+// The ids of the vulnerabilities affecting each queried version
+async fn query_batch(client: &reqwest::Client, queries: &[Value]) -> Result<Vec<HashSet<String>>> {
+    let url = format!("{}/v1/querybatch", OSV_URL);
+    let network = |source| Error::Network {
+        url: url.clone(),
+        source,
+    };
+    let response = client
+        .post(&url)
+        .json(&json!({ "queries": queries }))
+        .send()
+        .await
+        .map_err(network)?;
+    if !response.status().is_success() {
+        return Err(Error::Status {
+            url,
+            status: response.status(),
+        });
+    }
+    let body: Value = response.json().await.map_err(network)?;
+    let results = body
+        .get("results")
+        .and_then(Value::as_array)
+        .filter(|results| results.len() == queries.len())
+        .ok_or_else(|| Error::Parse("unexpected OSV response".to_string()))?;
+    Ok(results
+        .iter()
+        .map(|result| {
+            result
+                .get("vulns")
+                .and_then(Value::as_array)
+                .map(|vulns| {
+                    vulns
+                        .iter()
+                        .filter_map(|vuln| vuln.get("id")?.as_str())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect())
+}
@@ -0,0 +1,898 @@
+// Rendering of the report in the supported output formats
+
+use crate::config::Section;
+use crate::date;
+use crate::parse::{PackageUpdate, UpdateMap};
+use crate::version;
+use colored::Colorize;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Where the templates can be browsed, as `<SRCPKGS_URL>/<pkg>`
+const SRCPKGS_URL: &str = "https://github.com/void-linux/void-packages/tree/master/srcpkgs";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Yaml,
+    // A GitHub flavored Markdown table per section
+    Markdown,
+    // An Atom feed with an entry per update
+    Atom,
+    // The line of JSON status bars like waybar read
+    Statusbar,
+    // One line per update shaped by a user supplied template, without headers
+    Template(Template),
+    // Only the package names, each followed by the terminator
+    Names(char),
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "yaml" => Some(Format::Yaml),
+            "markdown" => Some(Format::Markdown),
+            "atom" => Some(Format::Atom),
+            "statusbar" => Some(Format::Statusbar),
+            _ => None,
+        }
+    }
+}
+
+// The order of the updates within each section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Current,
+    New,
+    // Largest version jump first
+    Delta,
+    // Most installed reverse dependencies first
+    Impact,
+    // Most installed according to PopCorn first
+    Installs,
+}
+
+impl SortKey {
+    pub fn from_name(name: &str) -> Option<SortKey> {
+        match name {
+            "name" => Some(SortKey::Name),
+            "current" => Some(SortKey::Current),
+            "new" => Some(SortKey::New),
+            "delta" => Some(SortKey::Delta),
+            "impact" => Some(SortKey::Impact),
+            "installs" => Some(SortKey::Installs),
+            _ => None,
+        }
+    }
+
+    // The updates in this order, ties broken by package name
+    pub fn sort(self, updates: &UpdateMap) -> Vec<Row<'_>> {
+        let mut rows = updates.sorted();
+        match self {
+            SortKey::Name => {}
+            SortKey::Current => {
+                rows.sort_by(|a, b| version::compare(&a.1.current_version, &b.1.current_version))
+            }
+            SortKey::New => {
+                rows.sort_by(|a, b| version::compare(&a.1.new_version, &b.1.new_version))
+            }
+            SortKey::Delta => rows.sort_by_key(|(_, u)| {
+                std::cmp::Reverse(version::delta(&u.current_version, &u.new_version))
+            }),
+            SortKey::Impact => rows.sort_by_key(|(_, u)| std::cmp::Reverse(u.impact)),
+            SortKey::Installs => rows.sort_by_key(|(_, u)| std::cmp::Reverse(u.installs)),
+        }
+        // Security fixes come first whatever the order
+        rows.sort_by_key(|(_, u)| !u.is_security_fix());
+        rows
+    }
+}
+
+// An update together with its package name
+pub type Row<'a> = (&'a String, &'a PackageUpdate);
+
+// Render the given sections of the report
+pub fn render(sections: &[(Section, &UpdateMap)], format: &Format, sort: SortKey) -> String {
+    let sections: Vec<(Section, Vec<Row>)> = sections
+        .iter()
+        .map(|(section, updates)| (*section, sort.sort(updates)))
+        .collect();
+
+    match format {
+        Format::Text => text(&sections),
+        Format::Json => json(&sections),
+        Format::Yaml => yaml(&sections),
+        Format::Markdown => markdown(&sections),
+        Format::Atom => atom(&sections),
+        Format::Statusbar => statusbar(&sections),
+        Format::Template(template) => sections
+            .iter()
+            .flat_map(|(section, rows)| {
+                rows.iter()
+                    .map(move |(pkg_name, update)| template.render(pkg_name, update, *section))
+            })
+            .map(|line| line + "\n")
+            .collect(),
+        Format::Names(terminator) => sections
+            .iter()
+            .flat_map(|(_, rows)| rows)
+            .map(|(pkg_name, _)| format!("{}{}", pkg_name, terminator))
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Current,
+    New,
+    Section,
+    Maintainer,
+    Tags,
+    Age,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Field(Field),
+}
+
+// A line template such as `{name} {current} -> {new}`. The placeholders are
+// `{name}`, `{current}`, `{new}`, `{section}`, `{maintainer}`, `{tags}`
+// (comma separated) and `{age}`; `{{` and `}}` produce literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Piece>);
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed placeholder '{{{}'", name)),
+                        }
+                    }
+                    let field = match name.as_str() {
+                        "name" => Field::Name,
+                        "current" => Field::Current,
+                        "new" => Field::New,
+                        "section" => Field::Section,
+                        "maintainer" => Field::Maintainer,
+                        "tags" => Field::Tags,
+                        "age" => Field::Age,
+                        _ => return Err(format!("unknown placeholder '{{{}}}'", name)),
+                    };
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Field(field));
+                }
+                '}' => return Err("unmatched '}', use '}}' for a literal brace".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Template(pieces))
+    }
+
+    pub fn render(&self, pkg_name: &str, update: &PackageUpdate, section: Section) -> String {
+        let mut line = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Literal(text) => line.push_str(text),
+                Piece::Field(Field::Name) => line.push_str(pkg_name),
+                Piece::Field(Field::Current) => line.push_str(&update.current_version),
+                Piece::Field(Field::New) => line.push_str(&update.new_version),
+                Piece::Field(Field::Section) => line.push_str(section.name()),
+                Piece::Field(Field::Maintainer) => {
+                    line.push_str(update.maintainer.as_deref().unwrap_or(""))
+                }
+                Piece::Field(Field::Tags) => line.push_str(&update.tags.join(",")),
+                Piece::Field(Field::Age) => line.push_str(&age(update).unwrap_or_default()),
+            }
+        }
+        line
+    }
+}
+
+// Colored text for terminals, with a header per non-empty section
+fn text(sections: &[(Section, Vec<Row>)]) -> String {
+    let width = terminal_width();
+    sections
+        .iter()
+        .filter(|(_, rows)| !rows.is_empty())
+        .map(|(section, rows)| {
+            format!(
+                "{}\n{}",
+                section.title().bold().blue().underline(),
+                columns(rows, width, true)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Width of the terminal stdout is connected to, if it is one
+fn terminal_width() -> Option<usize> {
+    use std::io::IsTerminal;
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return Some(columns);
+    }
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if ret == 0 && size.ws_col > 0 {
+        Some(size.ws_col as usize)
+    } else {
+        None
+    }
+}
+
+// Version columns are never truncated below this width
+const MIN_VERSION_WIDTH: usize = 8;
+
+// Shorten `s` to at most `width` characters, marking the cut with an ellipsis
+fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_string();
+    }
+    let mut short: String = s.chars().take(width.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+// How long the update has been pending, in days, e.g. `14d`
+fn age(update: &PackageUpdate) -> Option<String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let first_seen = update.first_seen?;
+    Some(format!("{}d", now.saturating_sub(first_seen) / 86_400))
+}
+
+// How many installed packages depend on the package, e.g. `12 rdeps`
+fn impact(update: &PackageUpdate) -> Option<String> {
+    match update.impact? {
+        1 => Some("1 rdep".to_string()),
+        n => Some(format!("{} rdeps", n)),
+    }
+}
+
+// How many systems reporting to PopCorn have the package, e.g. `712 installs`
+fn installs(update: &PackageUpdate) -> Option<String> {
+    match update.installs? {
+        1 => Some("1 install".to_string()),
+        n => Some(format!("{} installs", n)),
+    }
+}
+
+// Render updates as aligned columns: package, current version, new version,
+// the age, impact, installs and maintainer if known, the tags and the
+// description, with the note on a line of its own. When `width` is given, the
+// version columns, descriptions and notes are truncated as needed to fit
+// lines into it.
+pub fn table(rows: &[Row], width: Option<usize>) -> String {
+    columns(rows, width, false)
+}
+
+// The color of a new version in the terminal, by the level of the update
+fn level_color(update: &PackageUpdate, text: &str) -> String {
+    let text = match version::level(&update.current_version, &update.new_version) {
+        version::Level::Major => text.red(),
+        version::Level::Minor => text.yellow(),
+        version::Level::Patch => text.green(),
+    };
+    text.to_string()
+}
+
+// The table, with the new versions colored by level if `colored`
+fn columns(rows: &[Row], width: Option<usize>, colored: bool) -> String {
+    let column = |f: &dyn Fn(&PackageUpdate) -> usize| rows.iter().map(|(_, u)| f(u)).max();
+    let name_w = rows
+        .iter()
+        .map(|(n, _)| n.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut current_w = column(&|u| u.current_version.chars().count()).unwrap_or(0);
+    let mut new_w = column(&|u| u.new_version.chars().count()).unwrap_or(0);
+    let age_w = column(&|u| age(u).map_or(0, |a| a.len()));
+    let impact_w = column(&|u| impact(u).map_or(0, |i| i.len()));
+    let installs_w = column(&|u| installs(u).map_or(0, |i| i.len()));
+    let maintainer_w = column(&|u| u.maintainer.as_ref().map_or(0, |m| m.chars().count() + 2));
+    let tags_w = column(&|u| match u.tags.len() {
+        0 => 0,
+        n => u.tags.iter().map(|t| t.chars().count()).sum::<usize>() + 2 * (n - 1) + 2,
+    });
+
+    let line_w = |current_w: usize, new_w: usize| {
+        let extra = |w: Option<usize>| w.filter(|w| *w > 0).map_or(0, |w| w + 2);
+        name_w
+            + 2
+            + current_w
+            + 4
+            + new_w
+            + extra(age_w)
+            + extra(impact_w)
+            + extra(installs_w)
+            + extra(maintainer_w)
+            + extra(tags_w)
+    };
+    if let Some(width) = width {
+        // Take one column off the wider version column at a time
+        while line_w(current_w, new_w) > width {
+            if current_w >= new_w && current_w > MIN_VERSION_WIDTH {
+                current_w -= 1;
+            } else if new_w > MIN_VERSION_WIDTH {
+                new_w -= 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (pkg_name, update) in rows.iter().copied() {
+        // Padded apart from the coloring, as escape codes would count towards
+        // the width
+        let new_version = truncate(&update.new_version, new_w);
+        let padding = " ".repeat(new_w.saturating_sub(new_version.chars().count()));
+        let new_version = if colored {
+            level_color(update, &new_version)
+        } else {
+            new_version
+        };
+        let mut line = format!(
+            "{:name_w$}  {:current_w$} -> {}{}",
+            pkg_name,
+            truncate(&update.current_version, current_w),
+            new_version,
+            padding,
+            name_w = name_w,
+            current_w = current_w,
+        );
+        if let Some(age_w) = age_w.filter(|w| *w > 0) {
+            let age = age(update).unwrap_or_default();
+            line.push_str(&format!("  {:>w$}", age, w = age_w));
+        }
+        if let Some(impact_w) = impact_w.filter(|w| *w > 0) {
+            let impact = impact(update).unwrap_or_default();
+            line.push_str(&format!("  {:>w$}", impact, w = impact_w));
+        }
+        if let Some(installs_w) = installs_w.filter(|w| *w > 0) {
+            let installs = installs(update).unwrap_or_default();
+            line.push_str(&format!("  {:>w$}", installs, w = installs_w));
+        }
+        // Pad the maintainer column so that the tags line up
+        if let Some(maintainer_w) = maintainer_w.filter(|w| *w > 0) {
+            let maintainer = update
+                .maintainer
+                .as_ref()
+                .map_or(String::new(), |m| format!("({})", m));
+            line.push_str(&format!("  {:w$}", maintainer, w = maintainer_w));
+        }
+        // The descriptions come last, with the tags padded to line them up,
+        // and take what is left of the width
+        let tags = match update.tags.len() {
+            0 => String::new(),
+            _ => format!("[{}]", update.tags.join(", ")),
+        };
+        match (&update.description, tags_w.filter(|w| *w > 0)) {
+            (Some(_), Some(tags_w)) => line.push_str(&format!("  {:w$}", tags, w = tags_w)),
+            _ if !tags.is_empty() => line.push_str(&format!("  {}", tags)),
+            _ => {}
+        }
+        if let Some(description) = &update.description {
+            let left = width.map_or(usize::MAX, |width| {
+                width.saturating_sub(line_w(current_w, new_w) + 2)
+            });
+            if left > 0 {
+                line.push_str(&format!("  {}", truncate(description, left)));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+        // A note goes on a line of its own, under the package name
+        if let Some(note) = &update.note {
+            let note = format!("note: {}", note);
+            let note = match width {
+                Some(width) => truncate(&note, width.saturating_sub(2)),
+                None => note,
+            };
+            let note = if colored {
+                note.dimmed().to_string()
+            } else {
+                note
+            };
+            out.push_str(&format!("  {}\n", note));
+        }
+    }
+    out
+}
+
+// One object per update, shared by the structured output formats:
+//
+// ```
+// {"package": "python3-mock", "current": "3.0.5", "new": "4.0.3", "section": "maintainer", "maintainer": null, "tags": [], "first_seen": 1718236800, "level": "major", "impact": null, "installs": null, "description": null, "note": null}
+// ```
+fn entries(sections: &[(Section, Vec<Row>)]) -> Vec<serde_json::Value> {
+    sections
+        .iter()
+        .flat_map(|(section, rows)| {
+            rows.iter().map(move |(pkg_name, update)| {
+                json!({
+                    "package": pkg_name,
+                    "current": update.current_version,
+                    "new": update.new_version,
+                    "section": section.name(),
+                    "maintainer": update.maintainer,
+                    "tags": update.tags,
+                    "first_seen": update.first_seen,
+                    "level": version::level(&update.current_version, &update.new_version).name(),
+                    "impact": update.impact,
+                    "installs": update.installs,
+                    "description": update.description,
+                    "note": update.note,
+                })
+            })
+        })
+        .collect()
+}
+
+// A JSON array of the entries
+fn json(sections: &[(Section, Vec<Row>)]) -> String {
+    let mut out = serde_json::to_string_pretty(&entries(sections)).unwrap();
+    out.push('\n');
+    out
+}
+
+// A YAML sequence of the entries. Strings are always double quoted (using
+// JSON's escaping, which YAML accepts) so that versions like `3.10` are not
+// read back as numbers.
+fn yaml(sections: &[(Section, Vec<Row>)]) -> String {
+    let entries = entries(sections);
+    if entries.is_empty() {
+        return "[]\n".to_string();
+    }
+    let mut out = String::new();
+    for entry in &entries {
+        let fields = entry.as_object().unwrap();
+        for (i, (key, value)) in fields.iter().enumerate() {
+            let prefix = if i == 0 { "- " } else { "  " };
+            out.push_str(&format!("{}{}: {}\n", prefix, key, value));
+        }
+    }
+    out
+}
+
+// How many updates the status bar tooltip lists at most
+const TOOLTIP_LINES: usize = 20;
+
+// One line of JSON for waybar's custom modules (and i3blocks or polybar
+// scripts reading it): the number of updates, a tooltip listing them and a
+// class to style by, `urgent` when updates of one's own packages wait.
+//
+// ```
+// {"text": "3", "tooltip": "foo 1.0 -> 1.1\n...", "class": "urgent", "alt": "urgent"}
+// ```
+fn statusbar(sections: &[(Section, Vec<Row>)]) -> String {
+    let rows: Vec<&Row> = sections.iter().flat_map(|(_, rows)| rows).collect();
+    let class = if rows.is_empty() {
+        "none"
+    } else if sections
+        .iter()
+        .any(|(section, rows)| *section == Section::Maintainer && !rows.is_empty())
+    {
+        "urgent"
+    } else {
+        "updates"
+    };
+    let mut tooltip: Vec<String> = rows
+        .iter()
+        .take(TOOLTIP_LINES)
+        .map(|(pkg_name, update)| {
+            format!(
+                "{} {} -> {}",
+                pkg_name, update.current_version, update.new_version
+            )
+        })
+        .collect();
+    if rows.len() > TOOLTIP_LINES {
+        tooltip.push(format!("and {} more", rows.len() - TOOLTIP_LINES));
+    }
+    let line = json!({
+        "text": rows.len().to_string(),
+        "tooltip": tooltip.join("\n"),
+        "class": class,
+        "alt": class,
+    });
+    format!("{}\n", line)
+}
+
+// Escape what would end a Markdown table cell or be read as formatting
+fn markdown_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// A table per section under a heading, for pasting into issues or wikis.
+// Packages link to their templates and the upstream column to where the new
+// version was found, if that is a web page; other URLs are shown as text so
+// that they cannot turn into links like `javascript:`.
+fn markdown(sections: &[(Section, Vec<Row>)]) -> String {
+    sections
+        .iter()
+        .filter(|(_, rows)| !rows.is_empty())
+        .map(|(section, rows)| {
+            let mut out = format!("### {}\n\n", section.title().trim_end_matches(':'));
+            out.push_str("| Package | Current | New | Upstream | Tags |\n");
+            out.push_str("|---|---|---|---|---|\n");
+            for (pkg_name, update) in rows {
+                let upstream = match &update.url {
+                    Some(url) if is_web_url(url) => {
+                        format!("<{}>", url.replace('>', "%3E").replace('|', "%7C"))
+                    }
+                    Some(url) => markdown_escape(url),
+                    None => String::new(),
+                };
+                out.push_str(&format!(
+                    "| [{}]({}/{}) | {} | {} | {} | {} |\n",
+                    markdown_escape(pkg_name),
+                    SRCPKGS_URL,
+                    pkg_name,
+                    markdown_escape(&update.current_version),
+                    markdown_escape(&update.new_version),
+                    upstream,
+                    markdown_escape(&update.tags.join(", "))
+                ));
+            }
+            out
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Escape the characters that are special in XML text and attributes
+pub(crate) fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// An Atom feed with one entry per update, identified by the package and its
+// new version. An entry is dated from when the history first listed that
+// version, so feed readers show an update as new once and again only when a
+// newer version turns up.
+fn atom(sections: &[(Section, Vec<Row>)]) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut entries: Vec<(u64, String)> = Vec::new();
+    for (section, rows) in sections {
+        for (pkg_name, update) in rows {
+            let updated = update.version_seen.unwrap_or(now);
+            // Upstream's page if known, or else the template
+            let link = update
+                .url
+                .as_deref()
+                .filter(|url| is_web_url(url))
+                .map_or_else(|| format!("{}/{}", SRCPKGS_URL, pkg_name), String::from);
+            let title = format!(
+                "{}: {} -> {}",
+                pkg_name, update.current_version, update.new_version
+            );
+            let mut summary = format!("{} update", section.name());
+            if let Some(maintainer) = &update.maintainer {
+                summary.push_str(&format!(" for {}", maintainer));
+            }
+            if !update.tags.is_empty() {
+                summary.push_str(&format!(" [{}]", update.tags.join(", ")));
+            }
+            let mut entry = String::from("  <entry>\n");
+            entry.push_str(&format!("    <title>{}</title>\n", xml_escape(&title)));
+            entry.push_str(&format!(
+                "    <id>urn:vupdate:{}:{}</id>\n",
+                xml_escape(pkg_name),
+                xml_escape(&update.new_version)
+            ));
+            entry.push_str(&format!(
+                "    <updated>{}</updated>\n",
+                date::rfc3339(updated)
+            ));
+            entry.push_str(&format!("    <link href=\"{}\"/>\n", xml_escape(&link)));
+            entry.push_str(&format!(
+                "    <summary>{}</summary>\n",
+                xml_escape(&summary)
+            ));
+            entry.push_str("  </entry>\n");
+            entries.push((updated, entry));
+        }
+    }
+    // Newest first, as feeds usually are
+    entries.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    let updated = entries.first().map_or(now, |(time, _)| *time);
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    out.push_str("  <title>vupdate</title>\n");
+    out.push_str("  <id>urn:vupdate:updates</id>\n");
+    out.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        date::rfc3339(updated)
+    ));
+    out.push_str("  <author><name>vupdate</name></author>\n");
+    for (_, entry) in entries {
+        out.push_str(&entry);
+    }
+    out.push_str("</feed>\n");
+    out
+}
+
+// Whether `url` is an http or https URL, the only ones worth linking to
+fn is_web_url(url: &str) -> bool {
+    let scheme = url.split_once(':').map_or("", |(scheme, _)| scheme);
+    scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+}
+
+// Percent-encode `s` for use in a URL's query
+fn query_escape(s: &str) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+// The CSS class giving a tag its badge color, so that where the work on an
+// update stands shows at a glance
+fn badge_class(tag: &str) -> &'static str {
+    match tag {
+        "NEW" => "new",
+        "in-progress" | "bumped" | "pr-filed" | "applied" | "built" => "progress",
+        "merged" | "packaged" => "done",
+        "held" | "orphan" | "build failed" | "no-distfile" => "warn",
+        tag if tag.starts_with("PR #") => "progress",
+        _ => "other",
+    }
+}
+
+const HTML_STYLE: &str = r#"body { font-family: sans-serif; margin: 2em auto; max-width: 70em; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; }
+th { cursor: pointer; user-select: none; background: #f4f4f4; }
+th[data-order="asc"]::after { content: " \25B2"; }
+th[data-order="desc"]::after { content: " \25BC"; }
+td.age { text-align: right; }
+a { color: #2a6db0; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.badge { display: inline-block; padding: 0 0.5em; margin-right: 0.3em; border-radius: 0.8em; font-size: 85%; color: #fff; background: #888; }
+.badge.new { background: #2a6db0; }
+.badge.progress { background: #c98a00; }
+.badge.done { background: #388e3c; }
+.badge.warn { background: #c62828; }
+.note { color: #555; font-style: italic; }
+footer { color: #888; font-size: 85%; }"#;
+
+// Sort a table by the clicked column, numerically when both cells are
+// numbers and otherwise comparing digits as numbers, which suits versions
+const HTML_SCRIPT: &str = r#"document.querySelectorAll("th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var body = th.closest("table").tBodies[0];
+    var column = th.cellIndex;
+    var ascending = th.dataset.order !== "asc";
+    th.parentNode.querySelectorAll("th").forEach(function (other) { delete other.dataset.order; });
+    th.dataset.order = ascending ? "asc" : "desc";
+    var key = function (row) {
+      var cell = row.cells[column];
+      return cell.dataset.sort !== undefined ? cell.dataset.sort : cell.textContent;
+    };
+    Array.from(body.rows).sort(function (a, b) {
+      var x = key(a), y = key(b);
+      var order = x !== "" && y !== "" && !isNaN(x) && !isNaN(y)
+        ? x - y
+        : x.localeCompare(y, undefined, { numeric: true });
+      return ascending ? order : -order;
+    }).forEach(function (row) { body.appendChild(row); });
+  });
+});"#;
+
+// A standalone HTML page with a sortable table per section. Packages link to
+// their templates, new versions to where they were found and each row to the
+// pull requests mentioning the package.
+pub fn html(sections: &[(Section, &UpdateMap)], sort: SortKey) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut out = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+    out.push_str("<meta charset=\"utf-8\">\n<title>Package updates</title>\n");
+    out.push_str(&format!(
+        "<style>\n{}\n</style>\n</head>\n<body>\n",
+        HTML_STYLE
+    ));
+    out.push_str("<h1>Package updates</h1>\n");
+
+    let mut any = false;
+    for (section, updates) in sections {
+        let rows = sort.sort(updates);
+        if rows.is_empty() {
+            continue;
+        }
+        any = true;
+        out.push_str(&format!(
+            "<h2>{} ({})</h2>\n<table>\n<thead><tr><th>Package</th><th>Current</th>\
+             <th>New</th><th>Age</th><th>Maintainer</th><th>Status</th><th>Pull requests</th>\
+             </tr></thead>\n<tbody>\n",
+            xml_escape(section.title().trim_end_matches(':')),
+            rows.len()
+        ));
+        for (pkg_name, update) in rows {
+            let name = xml_escape(pkg_name);
+            let new_version = xml_escape(&update.new_version);
+            // The URLs come from the updates list, and a `javascript:` one
+            // would run on the published page
+            let new = match &update.url {
+                Some(url) if is_web_url(url) => {
+                    format!("<a href=\"{}\">{}</a>", xml_escape(url), new_version)
+                }
+                _ => new_version,
+            };
+            let age = update
+                .first_seen
+                .map(|first| now.saturating_sub(first) / 86_400);
+            let badges: String = update
+                .tags
+                .iter()
+                .map(|tag| {
+                    format!(
+                        "<span class=\"badge {}\">{}</span>",
+                        badge_class(tag),
+                        xml_escape(tag)
+                    )
+                })
+                .collect();
+            let badges = match &update.note {
+                Some(note) => format!("{}<span class=\"note\">{}</span>", badges, xml_escape(note)),
+                None => badges,
+            };
+            out.push_str(&format!(
+                "<tr><td><a href=\"{srcpkgs}/{name}\">{name}</a></td><td>{current}</td>\
+                 <td>{new}</td><td class=\"age\" data-sort=\"{age_days}\">{age}</td>\
+                 <td>{maintainer}</td><td>{badges}</td><td><a href=\"https://github.com/\
+                 void-linux/void-packages/pulls?q=is%3Apr+{query}\">search</a></td></tr>\n",
+                srcpkgs = SRCPKGS_URL,
+                name = name,
+                current = xml_escape(&update.current_version),
+                new = new,
+                age_days = age.map_or(String::new(), |days| days.to_string()),
+                age = age.map_or(String::new(), |days| format!("{}d", days)),
+                maintainer = xml_escape(update.maintainer.as_deref().unwrap_or("")),
+                badges = badges,
+                query = query_escape(pkg_name),
+            ));
+        }
+        out.push_str("</tbody>\n</table>\n");
+    }
+    if !any {
+        out.push_str("<p>No updates.</p>\n");
+    }
+
+    out.push_str(&format!(
+        "<footer>Generated by vupdate on {}</footer>\n",
+        date::format_timestamp(now)
+    ));
+    out.push_str(&format!(
+        "<script>\n{}\n</script>\n</body>\n</html>\n",
+        HTML_SCRIPT
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{html, render, Format, SortKey};
+    use crate::config::Section;
+    use crate::parse::response_to_hashmap;
+
+    #[test]
+    fn keeps_the_order_of_the_json_fields() {
+        let updates = response_to_hashmap("python3-mock 3.0.5 -> 4.0.3\n");
+        let out = render(
+            &[(Section::Installed, &updates)],
+            &Format::Json,
+            SortKey::Name,
+        );
+        let expected = r#"[
+  {
+    "package": "python3-mock",
+    "current": "3.0.5",
+    "new": "4.0.3",
+    "section": "installed",
+    "maintainer": null,
+    "tags": [],
+    "first_seen": null,
+    "level": "major",
+    "impact": null,
+    "installs": null,
+    "description": null,
+    "note": null
+  }
+]
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn links_only_web_urls_in_html() {
+        let updates = response_to_hashmap(
+            "vim 9.0.1 -> 9.1.0 https://github.com/vim/vim\n\
+             evil 1.0 -> 2.0 javascript:alert(1)\n",
+        );
+        let page = html(&[(Section::Installed, &updates)], SortKey::Name);
+        assert!(page.contains("<a href=\"https://github.com/vim/vim\">9.1.0</a>"));
+        assert!(!page.contains("javascript:"));
+        assert!(page.contains("<td>2.0</td>"));
+    }
+
+    #[test]
+    fn links_only_web_urls_in_markdown() {
+        let updates = response_to_hashmap(
+            "vim 9.0.1 -> 9.1.0 https://github.com/vim/vim\n\
+             evil 1.0 -> 2.0 javascript:alert(1)\n\
+             local 1.0 -> 1.1 file:///etc/<passwd>\n",
+        );
+        let table = render(
+            &[(Section::Installed, &updates)],
+            &Format::Markdown,
+            SortKey::Name,
+        );
+        assert!(table.contains("| 9.1.0 | <https://github.com/vim/vim> |"));
+        assert!(table.contains("| 2.0 | javascript:alert(1) |"));
+        assert!(table.contains("| 1.1 | file:///etc/\\<passwd\\> |"));
+        assert!(!table.contains("<javascript:"));
+        assert!(!table.contains("<file:"));
+    }
+}
@@ -0,0 +1,114 @@
+// Data types for package updates and the parser for the updates.txt files
+
+use crate::version;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Data type for storing package update information
+#[derive(Clone, Debug)]
+pub struct PackageUpdate {
+    pub current_version: String,
+    pub new_version: String,
+    // Where the new version was found, the last column of updates.txt
+    pub url: Option<String>,
+    // Maintainer the update was listed for, shown when several maintainer
+    // emails are configured
+    pub maintainer: Option<String>,
+    // Short labels shown next to the update, e.g. `held`
+    pub tags: Vec<String>,
+    // Unix timestamp since which the history has listed an update for the
+    // package, whatever its version
+    pub first_seen: Option<u64>,
+    // Unix timestamp since which the history has listed the update to this
+    // new version
+    pub version_seen: Option<u64>,
+    // How many installed packages depend on the package, with `--impact`
+    pub impact: Option<usize>,
+    // How many systems reporting to PopCorn have the package, with
+    // `--popcorn`
+    pub installs: Option<u64>,
+    // The package's short description, with `--describe`
+    pub description: Option<String>,
+    // The user's note on the package, see `notes`
+    pub note: Option<String>,
+}
+
+impl PackageUpdate {
+    // Whether the update fixes known vulnerabilities, as tagged by
+    // `check --check-security`
+    pub fn is_security_fix(&self) -> bool {
+        self.tags.iter().any(|t| t.starts_with("fixes "))
+    }
+}
+
+// Type alias for storing a directory of packages and their update information
+#[derive(Debug, Default)]
+pub struct UpdateMap(pub HashMap<String, PackageUpdate>);
+
+impl UpdateMap {
+    pub fn new() -> UpdateMap {
+        UpdateMap(HashMap::new())
+    }
+
+    // Insert an update, unless an update to a newer version is already known
+    // for the package
+    pub fn insert(&mut self, pkg_name: String, pkg_update: PackageUpdate) {
+        if let Some(existing_pkg_update) = self.0.get(&pkg_name) {
+            if version::compare(&pkg_update.new_version, &existing_pkg_update.new_version)
+                != Ordering::Greater
+            {
+                return;
+            }
+        }
+        self.0.insert(pkg_name, pkg_update);
+    }
+
+    pub fn extend(&mut self, other: UpdateMap) {
+        for (pkg_name, pkg_update) in other.0 {
+            self.insert(pkg_name, pkg_update);
+        }
+    }
+
+    // The updates ordered by package name
+    pub fn sorted(&self) -> Vec<(&String, &PackageUpdate)> {
+        let mut updates: Vec<_> = self.0.iter().collect();
+        updates.sort_by(|a, b| a.0.cmp(b.0));
+        updates
+    }
+}
+
+impl std::fmt::Display for UpdateMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&crate::output::table(&self.sorted(), None))
+    }
+}
+
+// Parse the response body from updates.txt files into an UpdateMap
+pub fn response_to_hashmap(body: &str) -> UpdateMap {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"(\S+)\s+(\S+)\s+->\s+(\S+)(?:\s+(\S+))?").unwrap();
+    }
+
+    let mut pkg_updates = UpdateMap::new();
+
+    for cap in body.lines().filter_map(|l| RE.captures(l)) {
+        let pkg_name = String::from(&cap[1]);
+        let pkg_update = PackageUpdate {
+            current_version: String::from(&cap[2]),
+            new_version: String::from(&cap[3]),
+            url: cap.get(4).map(|m| m.as_str().to_string()),
+            maintainer: None,
+            tags: Vec::new(),
+            first_seen: None,
+            version_seen: None,
+            impact: None,
+            installs: None,
+            description: None,
+            note: None,
+        };
+        pkg_updates.insert(pkg_name, pkg_update);
+    }
+    pkg_updates
+}
@@ -0,0 +1,61 @@
+// Package name patterns given on the command line. Patterns are shell-style
+// globs matched against the whole package name (`*`, `?` and `[...]`), or
+// unanchored regular expressions with `--regex`.
+
+use regex::Regex;
+
+pub struct Patterns(Vec<Regex>);
+
+// Translate a glob into an anchored regular expression
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' => {
+                re.push('[');
+                if let Some('!') = chars.peek() {
+                    chars.next();
+                    re.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        re.push('\\');
+                    }
+                    re.push(c);
+                }
+                re.push(']');
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+impl Patterns {
+    pub fn new(patterns: &[String], regex: bool) -> Result<Patterns, regex::Error> {
+        patterns
+            .iter()
+            .map(|p| {
+                if regex {
+                    Regex::new(p)
+                } else {
+                    Regex::new(&glob_to_regex(p))
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Patterns)
+    }
+
+    // Whether `pkg_name` matches any of the patterns. No patterns match
+    // everything.
+    pub fn matches(&self, pkg_name: &str) -> bool {
+        self.0.is_empty() || self.0.iter().any(|re| re.is_match(pkg_name))
+    }
+}
@@ -0,0 +1,377 @@
+// A small parser for XML property lists, as written by xbps for its package
+// database. Only the element types xbps uses are supported: dictionaries,
+// arrays, strings, integers, booleans, and data and dates kept as text.
+
+use std::collections::BTreeMap;
+
+pub type Dict = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Dict(Dict),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Value::Dict(d) => Some(d),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Parse a property list document into its root value
+pub fn parse(input: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser { input, pos: 0 };
+    parser.skip_prolog()?;
+    match parser.open_tag()? {
+        Tag::Open("plist") => {}
+        _ => return Err(parser.error("expected <plist>")),
+    }
+    let value = parser.value()?;
+    parser.close_tag("plist")?;
+    Ok(value)
+}
+
+enum Tag<'a> {
+    Open(&'a str),
+    // An element without content, like `<true/>`
+    Empty(&'a str),
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            offset: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // Skip past `end`, failing if it does not occur
+    fn skip_past(&mut self, end: &str) -> Result<(), ParseError> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.pos += i + end.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("expected '{}'", end))),
+        }
+    }
+
+    // Skip the XML declaration, doctype and comments before the root element
+    fn skip_prolog(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_comments(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            if !self.rest().starts_with("<!--") {
+                return Ok(());
+            }
+            self.skip_past("-->")?;
+        }
+    }
+
+    fn open_tag(&mut self) -> Result<Tag<'a>, ParseError> {
+        self.skip_comments()?;
+        let rest = self.rest();
+        if !rest.starts_with('<') || rest.starts_with("</") {
+            return Err(self.error("expected an element"));
+        }
+        let end = rest
+            .find('>')
+            .ok_or_else(|| self.error("unterminated tag"))?;
+        let inner = &rest[1..end];
+        self.pos += end + 1;
+        // Attributes, like plist's version, are not needed
+        let (inner, empty) = match inner.strip_suffix('/') {
+            Some(inner) => (inner, true),
+            None => (inner, false),
+        };
+        let name = inner.split_whitespace().next().unwrap_or("");
+        Ok(if empty {
+            Tag::Empty(name)
+        } else {
+            Tag::Open(name)
+        })
+    }
+
+    fn close_tag(&mut self, name: &str) -> Result<(), ParseError> {
+        self.skip_comments()?;
+        let tag = format!("</{}>", name);
+        if !self.rest().starts_with(&tag) {
+            return Err(self.error(&format!("expected {}", tag)));
+        }
+        self.pos += tag.len();
+        Ok(())
+    }
+
+    // Whether the next tag closes the current element
+    fn at_close(&mut self) -> Result<bool, ParseError> {
+        self.skip_comments()?;
+        Ok(self.rest().starts_with("</"))
+    }
+
+    // The text content of an element, up to its closing tag
+    fn text(&mut self, name: &str) -> Result<String, ParseError> {
+        let rest = self.rest();
+        let end = rest
+            .find('<')
+            .ok_or_else(|| self.error("unterminated element"))?;
+        let text = unescape(&rest[..end]).map_err(|msg| self.error(&msg))?;
+        self.pos += end;
+        self.close_tag(name)?;
+        Ok(text)
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+        match self.open_tag()? {
+            Tag::Empty("true") => Ok(Value::Boolean(true)),
+            Tag::Empty("false") => Ok(Value::Boolean(false)),
+            Tag::Empty("dict") => Ok(Value::Dict(Dict::new())),
+            Tag::Empty("array") => Ok(Value::Array(Vec::new())),
+            Tag::Empty("string") => Ok(Value::String(String::new())),
+            Tag::Open(name @ ("string" | "data" | "date")) => Ok(Value::String(self.text(name)?)),
+            Tag::Open("integer") => {
+                let text = self.text("integer")?;
+                let text = text.trim();
+                let number = match text.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => text.parse(),
+                };
+                number.map(Value::Integer).map_err(|_| ParseError {
+                    offset: start,
+                    message: format!("invalid integer '{}'", text),
+                })
+            }
+            Tag::Open("array") => {
+                let mut items = Vec::new();
+                while !self.at_close()? {
+                    items.push(self.value()?);
+                }
+                self.close_tag("array")?;
+                Ok(Value::Array(items))
+            }
+            Tag::Open("dict") => {
+                let mut dict = Dict::new();
+                while !self.at_close()? {
+                    match self.open_tag()? {
+                        Tag::Open("key") => {}
+                        _ => return Err(self.error("expected <key>")),
+                    }
+                    let key = self.text("key")?;
+                    let value = self.value()?;
+                    dict.insert(key, value);
+                }
+                self.close_tag("dict")?;
+                Ok(Value::Dict(dict))
+            }
+            Tag::Open(name) | Tag::Empty(name) => Err(ParseError {
+                offset: start,
+                message: format!("unsupported element <{}>", name),
+            }),
+        }
+    }
+}
+
+// Replace XML entity and character references
+fn unescape(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let end = rest
+            .find(';')
+            .ok_or_else(|| "unterminated entity".to_string())?;
+        let entity = &rest[..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity.strip_prefix('#').and_then(|d| d.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        };
+        out.push(c.ok_or_else(|| format!("unknown entity '&{};'", entity))?);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    fn error_of(input: &str) -> ParseError {
+        parse(input).expect_err("the document should not parse")
+    }
+
+    #[test]
+    fn parses_every_element_type() {
+        let value = parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<!-- written by xbps -->
+<plist version="1.0">
+<dict>
+	<key>array</key>
+	<array>
+		<string>a</string>
+		<integer>-12</integer>
+		<integer>0x1F</integer>
+		<true/>
+		<false/>
+	</array>
+	<key>data</key>
+	<data>aGVsbG8=</data>
+	<key>date</key>
+	<date>2024-06-13T08:30:00Z</date>
+	<!-- between entries -->
+	<key>empty</key>
+	<dict/>
+	<key>none</key>
+	<array/>
+	<key>blank</key>
+	<string/>
+</dict>
+</plist>
+"#,
+        )
+        .unwrap();
+        let dict = value.as_dict().unwrap();
+        assert_eq!(
+            dict["array"],
+            Value::Array(vec![
+                string("a"),
+                Value::Integer(-12),
+                Value::Integer(31),
+                Value::Boolean(true),
+                Value::Boolean(false),
+            ])
+        );
+        assert_eq!(dict["data"], string("aGVsbG8="));
+        assert_eq!(dict["date"], string("2024-06-13T08:30:00Z"));
+        assert_eq!(dict["empty"], Value::Dict(Dict::new()));
+        assert_eq!(dict["none"], Value::Array(Vec::new()));
+        assert_eq!(dict["blank"], string(""));
+    }
+
+    #[test]
+    fn keeps_the_whitespace_of_strings() {
+        let value = parse("<plist><string>  two\n lines </string></plist>").unwrap();
+        assert_eq!(value, string("  two\n lines "));
+    }
+
+    #[test]
+    fn replaces_entities() {
+        assert_eq!(
+            unescape("a &amp; b &lt;c&gt; &quot;d&quot; &apos;e&apos;").unwrap(),
+            "a & b <c> \"d\" 'e'"
+        );
+        assert_eq!(
+            unescape("&#233;&#xE9;&#x1F600;").unwrap(),
+            "\u{e9}\u{e9}\u{1F600}"
+        );
+        assert_eq!(unescape("&nbsp;").unwrap_err(), "unknown entity '&nbsp;'");
+        assert_eq!(
+            unescape("&#xD800;").unwrap_err(),
+            "unknown entity '&#xD800;'"
+        );
+        assert_eq!(unescape("a & b").unwrap_err(), "unterminated entity");
+        let value =
+            parse("<plist><dict><key>a&amp;b</key><string>&lt;</string></dict></plist>").unwrap();
+        assert_eq!(value.as_dict().unwrap()["a&b"], string("<"));
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        let e = error_of("<plist><integer>12a</integer></plist>");
+        assert_eq!(e.to_string(), "byte 7: invalid integer '12a'");
+        let e = error_of("<plist><real>1.5</real></plist>");
+        assert_eq!(e.to_string(), "byte 7: unsupported element <real>");
+        let e = error_of("<plist><dict><string>a</string></dict></plist>");
+        assert_eq!(e.message, "expected <key>");
+        let e = error_of("<plist><array><string>a</string></plist>");
+        assert_eq!(e.message, "expected </array>");
+        assert_eq!(error_of("<dict></dict>").message, "expected <plist>");
+        assert_eq!(error_of("<plist><string>a").message, "unterminated element");
+        assert_eq!(error_of("<!-- open").message, "expected '-->'");
+    }
+}
@@ -0,0 +1,118 @@
+// External executables adding update sources and notification services,
+// found on `PATH` like git's subcommands: a source named `foo` in `sources` is
+// `vupdate-source-foo`, a `[[notify]]` service `foo` is `vupdate-notify-foo`.
+// A plugin gets a JSON object on stdin and answers with one on stdout:
+//
+// ```
+// $ echo '{"package": "vim"}' | vupdate-source-foo
+// {"version": "9.1.0"}
+// $ echo '{"updates": [...], "text": "..."}' | vupdate-notify-foo
+// ```
+//
+// A source answers `{"version": null}` for packages it does not know. A
+// notification service gets the same JSON as a webhook and may print nothing.
+// Exiting with an error status fails the lookup or the notification, with
+// the plugin's stderr shown as it is. Plugins are looked for when they are
+// used, so that one missing from `PATH` only fails what needs it.
+
+use crate::error::{Error, Result};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Source,
+    Notify,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Source => "source",
+            Kind::Notify => "notify",
+        }
+    }
+
+    // What a plugin of this kind is in the configuration
+    fn title(self) -> &'static str {
+        match self {
+            Kind::Source => "source",
+            Kind::Notify => "notification service",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    pub kind: Kind,
+    pub name: String,
+}
+
+impl Plugin {
+    pub fn new(kind: Kind, name: &str) -> Plugin {
+        Plugin {
+            kind,
+            name: name.to_string(),
+        }
+    }
+
+    // The name of the plugin's executable
+    pub fn program(&self) -> String {
+        format!("vupdate-{}-{}", self.kind.name(), self.name)
+    }
+
+    // Where the executable is on `PATH`, or an error naming what is missing
+    pub fn path(&self) -> Result<PathBuf> {
+        let program = self.program();
+        std::env::var_os("PATH")
+            .and_then(|path| {
+                std::env::split_paths(&path)
+                    .map(|dir| dir.join(&program))
+                    .find(|path| path.is_file())
+            })
+            .ok_or_else(|| {
+                Error::Plugin(format!(
+                    "unknown {} '{}' (no {} on PATH)",
+                    self.kind.title(),
+                    self.name,
+                    program
+                ))
+            })
+    }
+
+    // Run the plugin with `input` on stdin and return what it printed,
+    // `Value::Null` if nothing
+    pub async fn call(&self, input: &Value) -> Result<Value> {
+        let path = self.path()?;
+        let shown = path.display();
+        let mut child = Command::new(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::Plugin(format!("could not run {}: {}", shown, e)))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // A plugin that does not read its input closes the pipe early, which
+        // its exit status tells about better
+        let _ = stdin.write_all(format!("{}\n", input).as_bytes()).await;
+        drop(stdin);
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| Error::Plugin(format!("could not run {}: {}", shown, e)))?;
+        if !output.status.success() {
+            return Err(Error::Plugin(format!(
+                "{} failed with {}",
+                shown, output.status
+            )));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&stdout)
+            .map_err(|e| Error::Plugin(format!("invalid output from {}: {}", shown, e)))
+    }
+}
@@ -0,0 +1,65 @@
+// How many systems have packages installed, from Void's PopCorn statistics
+// (https://popcorn.voidlinux.org). Systems that opted in report their
+// installed packages daily, and a report of each day is published as JSON:
+//
+// ```json
+// {"UniqueInstalls": 1520, "Packages": {"gtk+3": 712, ...}, "Versions": {...}}
+// ```
+
+use crate::date;
+use crate::error::{Error, Result};
+use crate::fetch::Fetcher;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const POPCORN_URL: &str = "https://popcorn.voidlinux.org";
+
+// How many days back a report is looked for, as today's is published once the
+// day is over and publishing is sometimes late
+const DAYS_BACK: u64 = 3;
+
+// How many of the reporting systems have each package installed
+pub struct Popularity(HashMap<String, u64>);
+
+impl Popularity {
+    // How many systems have `pkg_name` installed, 0 if none reported it
+    pub fn installs(&self, pkg_name: &str) -> u64 {
+        self.0.get(pkg_name).copied().unwrap_or(0)
+    }
+}
+
+// The latest daily report, `None` if none of the last days has one
+pub async fn latest(fetcher: &Fetcher) -> Result<Option<Popularity>> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    for days in 1..=DAYS_BACK {
+        let day = date::from_timestamp(now.saturating_sub(days * 86_400));
+        let url = format!("{}/popcorn_{}.json", POPCORN_URL, day);
+        // Offline, the cached report is used whatever its day
+        match fetcher.cached("popcorn.json", &url).await {
+            Ok(text) => return parse(&text).map(Some),
+            Err(Error::Status { status, .. }) if status == StatusCode::NOT_FOUND => continue,
+            Err(Error::NotCached(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+fn parse(text: &str) -> Result<Popularity> {
+    let report: Value = serde_json::from_str(text)
+        .map_err(|e| Error::Parse(format!("invalid PopCorn report: {}", e)))?;
+    let packages = report
+        .get("Packages")
+        .and_then(Value::as_object)
+        .ok_or_else(|| Error::Parse("unexpected PopCorn report".to_string()))?;
+    Ok(Popularity(
+        packages
+            .iter()
+            .filter_map(|(name, count)| Some((name.clone(), count.as_u64()?)))
+            .collect(),
+    ))
+}
@@ -0,0 +1,68 @@
+// How widely a version has been packaged, according to repology.org, which
+// follows the repositories of hundreds of distributions. Repology asks for at
+// most one request per second, so lookups are meant to go one at a time.
+
+use crate::error::{Error, Result};
+use crate::fetch::Fetcher;
+use crate::version;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::time::Duration;
+
+pub const REPOLOGY_URL: &str = "https://repology.org";
+
+// How long to wait between two requests
+pub const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+// The distributions other than Void packaging `version` of `pkg_name` or a
+// newer one, sorted, or `None` if Repology does not know the package.
+// Distributions count once however many repositories they have, e.g.
+// `debian_12` and `debian_unstable` are both `debian`.
+pub async fn distros_with(
+    fetcher: &Fetcher,
+    pkg_name: &str,
+    version: &str,
+) -> Result<Option<Vec<String>>> {
+    // Void's source package names are mapped to Repology's project names by
+    // Repology itself, with a redirect to the project's packages
+    let url = reqwest::Url::parse_with_params(
+        &format!("{}/tools/project-by", REPOLOGY_URL),
+        &[
+            ("repo", "void_x86_64"),
+            ("name_type", "srcname"),
+            ("target_page", "api_v1_project"),
+            ("name", pkg_name),
+        ],
+    )
+    .map_err(|e| Error::Parse(e.to_string()))?;
+    let text = match fetcher
+        .cached(&format!("repology/{}.json", pkg_name), url.as_str())
+        .await
+    {
+        Ok(text) => text,
+        Err(Error::Status { status, .. }) if status == StatusCode::NOT_FOUND => return Ok(None),
+        Err(Error::NotCached(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let packages: Value = serde_json::from_str(&text)
+        .map_err(|e| Error::Parse(format!("invalid Repology response: {}", e)))?;
+    let packages = match packages.as_array() {
+        Some(packages) => packages,
+        None => return Ok(None),
+    };
+
+    let mut distros: Vec<String> = packages
+        .iter()
+        .filter_map(|package| {
+            let repo = package.get("repo")?.as_str()?;
+            let packaged = package.get("version")?.as_str()?;
+            let distro = repo.split('_').next().unwrap_or(repo);
+            let current = version::compare(packaged, version) != Ordering::Less;
+            Some(distro.to_string()).filter(|d| current && d != "void")
+        })
+        .collect();
+    distros.sort();
+    distros.dedup();
+    Ok(Some(distros))
+}
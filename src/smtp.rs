@@ -0,0 +1,363 @@
+// A minimal SMTP client for `vupdate digest`: one plain text message per
+// connection, with TLS or STARTTLS and AUTH PLAIN, configured by the `[smtp]`
+// table:
+//
+// ```toml
+// [smtp]
+// server = "smtp.example.com"
+// security = "starttls"   # or "tls" (port 465), "none" (port 25)
+// username = "me@example.com"
+// password_command = "pass show mail/example.com"
+// from = "me@example.com"
+// ```
+
+use crate::date;
+use crate::error::{Error, Result};
+use std::process::{Command, Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    // TLS from the start, usually on port 465
+    Tls,
+    // Plain text upgraded with STARTTLS, usually on port 587
+    StartTls,
+    // No encryption at all, only sensible for a local relay
+    None,
+}
+
+impl Security {
+    pub fn from_name(name: &str) -> Option<Security> {
+        match name {
+            "tls" => Some(Security::Tls),
+            "starttls" => Some(Security::StartTls),
+            "none" => Some(Security::None),
+            _ => None,
+        }
+    }
+
+    pub fn default_port(self) -> u16 {
+        match self {
+            Security::Tls => 465,
+            Security::StartTls => 587,
+            Security::None => 25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Server {
+    pub host: String,
+    // The port, the security's usual one if not set
+    pub port: Option<u16>,
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // A command printing the password, e.g. `pass show mail`
+    pub password_command: Option<String>,
+    // The sender address, the username if not set
+    pub from: Option<String>,
+}
+
+pub struct Message {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Server {
+    // The sender's address
+    pub fn from(&self) -> Option<&str> {
+        self.from.as_deref().or(self.username.as_deref())
+    }
+
+    fn password(&self) -> Result<Option<String>> {
+        if let Some(password) = &self.password {
+            return Ok(Some(password.clone()));
+        }
+        let command = match &self.password_command {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdout(Stdio::piped())
+            .output()
+            .map_err(|e| Error::Smtp(format!("could not run password_command: {}", e)))?;
+        if !output.status.success() {
+            return Err(Error::Smtp(format!(
+                "password_command failed with {}",
+                output.status
+            )));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(Some(stdout.lines().next().unwrap_or("").to_string()))
+    }
+
+    // Send `message` through the server
+    pub async fn send(&self, message: &Message) -> Result<()> {
+        let from = self
+            .from()
+            .ok_or_else(|| Error::Smtp("no sender address, set 'from'".to_string()))?;
+        let password = self.password()?;
+        let port = self.port.unwrap_or_else(|| self.security.default_port());
+        let io_error = |e: std::io::Error| Error::Smtp(format!("{}: {}", self.host, e));
+        let tcp = TcpStream::connect((self.host.as_str(), port))
+            .await
+            .map_err(io_error)?;
+        let data = format_message(from, message);
+        let envelope = Envelope {
+            username: self.username.as_deref(),
+            password: password.as_deref(),
+            from,
+            message,
+            data: &data,
+        };
+
+        match self.security {
+            Security::None => {
+                let mut session = Session::new(tcp, &self.host);
+                session.start().await?;
+                session.deliver(&envelope).await
+            }
+            Security::Tls => {
+                let tls = self.tls(tcp).await?;
+                let mut session = Session::new(tls, &self.host);
+                session.start().await?;
+                session.deliver(&envelope).await
+            }
+            Security::StartTls => {
+                let mut session = Session::new(tcp, &self.host);
+                session.start().await?;
+                session.command("STARTTLS", 220).await?;
+                let tls = self.tls(session.stream.into_inner()).await?;
+                let mut session = Session::new(tls, &self.host);
+                session.ehlo().await?;
+                session.deliver(&envelope).await
+            }
+        }
+    }
+
+    async fn tls(&self, tcp: TcpStream) -> Result<tokio_native_tls::TlsStream<TcpStream>> {
+        let tls_error = |e: tokio_native_tls::native_tls::Error| {
+            Error::Smtp(format!("{}: TLS failed: {}", self.host, e))
+        };
+        let connector = tokio_native_tls::native_tls::TlsConnector::new().map_err(tls_error)?;
+        tokio_native_tls::TlsConnector::from(connector)
+            .connect(&self.host, tcp)
+            .await
+            .map_err(tls_error)
+    }
+}
+
+// What is sent in one session
+struct Envelope<'a> {
+    username: Option<&'a str>,
+    password: Option<&'a str>,
+    from: &'a str,
+    message: &'a Message,
+    // The message with its headers, ready for DATA
+    data: &'a str,
+}
+
+struct Session<'a, S> {
+    stream: BufReader<S>,
+    host: &'a str,
+}
+
+impl<'a, S: AsyncRead + AsyncWrite + Unpin> Session<'a, S> {
+    fn new(stream: S, host: &'a str) -> Session<'a, S> {
+        Session {
+            stream: BufReader::new(stream),
+            host,
+        }
+    }
+
+    fn error(&self, msg: impl std::fmt::Display) -> Error {
+        Error::Smtp(format!("{}: {}", self.host, msg))
+    }
+
+    // Read a reply, which may span several lines, as its code and text
+    async fn reply(&mut self) -> Result<(u16, String)> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            let n = self
+                .stream
+                .read_line(&mut line)
+                .await
+                .map_err(|e| self.error(e))?;
+            if n == 0 {
+                return Err(self.error("connection closed"));
+            }
+            let line = line.trim_end();
+            let code = line.get(..3).and_then(|code| code.parse().ok());
+            let code = code.ok_or_else(|| self.error(format!("unexpected reply '{}'", line)))?;
+            text.push_str(line.get(4..).unwrap_or(""));
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, text));
+            }
+            text.push('\n');
+        }
+    }
+
+    async fn expect(&mut self, expected: u16) -> Result<()> {
+        let (code, text) = self.reply().await?;
+        if code != expected {
+            return Err(self.error(format!("{} {}", code, text)));
+        }
+        Ok(())
+    }
+
+    async fn command(&mut self, line: &str, expected: u16) -> Result<()> {
+        self.stream
+            .write_all(format!("{}\r\n", line).as_bytes())
+            .await
+            .map_err(|e| self.error(e))?;
+        self.expect(expected).await
+    }
+
+    // Take the greeting and introduce ourselves
+    async fn start(&mut self) -> Result<()> {
+        self.expect(220).await?;
+        self.ehlo().await
+    }
+
+    async fn ehlo(&mut self) -> Result<()> {
+        self.command(&format!("EHLO {}", hostname()), 250).await
+    }
+
+    async fn deliver(&mut self, envelope: &Envelope<'_>) -> Result<()> {
+        if let Some(username) = envelope.username {
+            let credentials = format!("\0{}\0{}", username, envelope.password.unwrap_or(""));
+            let auth = format!("AUTH PLAIN {}", base64(credentials.as_bytes()));
+            self.command(&auth, 235).await?;
+        }
+        self.command(&format!("MAIL FROM:<{}>", address(envelope.from)), 250)
+            .await?;
+        for to in &envelope.message.to {
+            self.command(&format!("RCPT TO:<{}>", address(to)), 250)
+                .await?;
+        }
+        self.command("DATA", 354).await?;
+        self.command(&format!("{}\r\n.", envelope.data), 250)
+            .await?;
+        self.command("QUIT", 221).await
+    }
+}
+
+// The bare address of `Name <address>`
+fn address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+// The message with its headers, with CRLF line endings and lines starting
+// with a dot escaped as DATA requires
+fn format_message(from: &str, message: &Message) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut data = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        from,
+        message.to.join(", "),
+        message.subject,
+        date::rfc2822(now)
+    );
+    for line in message.body.lines() {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    // The terminating `\r\n.` is added when sending
+    data.truncate(data.len() - 2);
+    data
+}
+
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    let ok = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } == 0;
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(0);
+    match std::str::from_utf8(&buf[..len]) {
+        Ok(name) if ok && !name.is_empty() => name.to_string(),
+        _ => "localhost".to_string(),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(body: &str) -> Message {
+        Message {
+            to: vec!["a@example.com".to_string(), "B <b@example.com>".to_string()],
+            subject: "3 updates".to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn formats_the_headers_and_line_endings() {
+        let data = format_message("me@example.com", &message("vim 9.1\nmpv 0.38\n"));
+        let (headers, body) = data.split_once("\r\n\r\n").unwrap();
+        let headers: Vec<&str> = headers.split("\r\n").collect();
+        assert_eq!(headers[0], "From: me@example.com");
+        assert_eq!(headers[1], "To: a@example.com, B <b@example.com>");
+        assert_eq!(headers[2], "Subject: 3 updates");
+        assert!(headers[3].starts_with("Date: "));
+        assert!(headers.contains(&"Content-Type: text/plain; charset=utf-8"));
+        // The last line ending comes with the terminating `.`
+        assert_eq!(body, "vim 9.1\r\nmpv 0.38");
+    }
+
+    #[test]
+    fn escapes_lines_starting_with_a_dot() {
+        let data = format_message("me@example.com", &message(".\n..a\nb.\n.c\r\nd"));
+        let (_, body) = data.split_once("\r\n\r\n").unwrap();
+        assert_eq!(body, "..\r\n...a\r\nb.\r\n..c\r\nd");
+    }
+
+    #[test]
+    fn takes_the_address_out_of_mailboxes() {
+        assert_eq!(address("Me <me@example.com>"), "me@example.com");
+        assert_eq!(address(" me@example.com "), "me@example.com");
+        assert_eq!(address("broken> <"), "broken> <");
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(b"\0me\0secret"), "AG1lAHNlY3JldA==");
+    }
+}
@@ -0,0 +1,132 @@
+// Upstream versions from sources other than void-updates, to cross-check its
+// suggestions. Each source is enabled by naming it in the `sources`
+// configuration key:
+//
+// ```toml
+// sources = ["anitya"]
+// ```
+//
+// Other names are plugins, see `plugin`.
+
+use crate::error::{Error, Result};
+use crate::fetch::Fetcher;
+use crate::plugin::{self, Plugin};
+use serde_json::{json, Value};
+
+// Anitya, the project behind release-monitoring.org
+pub const ANITYA_URL: &str = "https://release-monitoring.org";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    // release-monitoring.org, which maps the packages of many distributions
+    // to upstream projects
+    Anitya,
+    // A `vupdate-source-<name>` executable
+    Plugin(Plugin),
+}
+
+impl Source {
+    // The built-in source `name`, or else the plugin by that name
+    pub fn from_name(name: &str) -> Source {
+        match name {
+            "anitya" => Source::Anitya,
+            _ => Source::Plugin(Plugin::new(plugin::Kind::Source, name)),
+        }
+    }
+
+    // Fail if the source cannot be asked at all, i.e. is a plugin that is
+    // not installed, rather than once per package
+    pub fn available(&self) -> Result<()> {
+        match self {
+            Source::Anitya => Ok(()),
+            Source::Plugin(plugin) => plugin.path().map(|_| ()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Source::Anitya => "anitya",
+            Source::Plugin(plugin) => &plugin.name,
+        }
+    }
+
+    // The latest upstream version of `pkg_name` by this source, `None` if the
+    // source does not know the package
+    pub async fn latest_version(
+        &self,
+        fetcher: &Fetcher,
+        pkg_name: &str,
+    ) -> Result<Option<String>> {
+        let result = match self {
+            Source::Anitya => anitya_version(fetcher, pkg_name).await,
+            Source::Plugin(plugin) => plugin_version(plugin, pkg_name).await,
+        };
+        match result {
+            Err(Error::NotCached(_)) => Ok(None),
+            result => result,
+        }
+    }
+}
+
+// The latest stable version Anitya knows for `pkg_name`: that of the project
+// it maps Void's package to, or else of the only project by that name
+async fn anitya_version(fetcher: &Fetcher, pkg_name: &str) -> Result<Option<String>> {
+    let query = |endpoint: &str, params: &[(&str, &str)]| {
+        reqwest::Url::parse_with_params(&format!("{}/api/v2/{}/", ANITYA_URL, endpoint), params)
+            .map(|url| url.to_string())
+            .map_err(|e| Error::Parse(e.to_string()))
+    };
+    let items = |text: String| -> Result<Vec<Value>> {
+        let body: Value = serde_json::from_str(&text)
+            .map_err(|e| Error::Parse(format!("invalid Anitya response: {}", e)))?;
+        Ok(body
+            .get("items")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default())
+    };
+
+    let url = query(
+        "packages",
+        &[("distribution", "Void Linux"), ("name", pkg_name)],
+    )?;
+    let key = format!("anitya/packages/{}.json", pkg_name);
+    let packages = items(fetcher.cached(&key, &url).await?)?;
+    if let Some(package) = packages.first() {
+        let version = package
+            .get("stable_version")
+            .or_else(|| package.get("version"))
+            .and_then(Value::as_str);
+        return Ok(version.map(String::from));
+    }
+
+    let url = query("projects", &[("name", pkg_name)])?;
+    let key = format!("anitya/projects/{}.json", pkg_name);
+    let projects = items(fetcher.cached(&key, &url).await?)?;
+    match projects.as_slice() {
+        [project] => {
+            let stable = project
+                .get("stable_versions")
+                .and_then(Value::as_array)
+                .and_then(|versions| versions.first());
+            let version = stable
+                .or_else(|| project.get("version"))
+                .and_then(Value::as_str);
+            Ok(version.map(String::from))
+        }
+        _ => Ok(None),
+    }
+}
+
+// The version the plugin answers for `pkg_name`
+async fn plugin_version(plugin: &Plugin, pkg_name: &str) -> Result<Option<String>> {
+    let output = plugin.call(&json!({ "package": pkg_name })).await?;
+    match output.get("version") {
+        Some(Value::String(version)) => Ok(Some(version.clone())),
+        Some(Value::Null) | None => Ok(None),
+        Some(_) => Err(Error::Plugin(format!(
+            "invalid output from {}: 'version' is not a string",
+            plugin.program()
+        ))),
+    }
+}
@@ -0,0 +1,154 @@
+// Where the work on each update stands, kept in
+// `$XDG_DATA_HOME/vupdate/status.jsonl` with one JSON object per line. `bump`
+// and `pr` move packages along by themselves; `vupdate status set` does it by
+// hand. Packages without an entry are `new`.
+
+use crate::config::data_dir;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    New,
+    InProgress,
+    Bumped,
+    PrFiled,
+    Merged,
+}
+
+impl Status {
+    pub const ALL: &'static [Status] = &[
+        Status::New,
+        Status::InProgress,
+        Status::Bumped,
+        Status::PrFiled,
+        Status::Merged,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::New => "new",
+            Status::InProgress => "in-progress",
+            Status::Bumped => "bumped",
+            Status::PrFiled => "pr-filed",
+            Status::Merged => "merged",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Status> {
+        Status::ALL.iter().copied().find(|s| s.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub pkg_name: String,
+    pub status: Status,
+    // The version the work is on, `None` if it was not known
+    pub version: Option<String>,
+    // The pull request opened for it, once there is one
+    pub pr: Option<u64>,
+    // The day of the last change, as `YYYY-MM-DD`
+    pub date: String,
+}
+
+impl Entry {
+    // Whether the entry is about an update to `new_version`. One without a
+    // version applies to any.
+    pub fn applies_to(&self, new_version: &str) -> bool {
+        self.version.as_deref().is_none_or(|v| v == new_version)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "pkgname": self.pkg_name,
+            "status": self.status.name(),
+            "version": self.version,
+            "pr": self.pr,
+            "date": self.date,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Entry> {
+        Some(Entry {
+            pkg_name: value.get("pkgname")?.as_str()?.to_string(),
+            status: Status::from_name(value.get("status")?.as_str()?)?,
+            version: value
+                .get("version")
+                .and_then(Value::as_str)
+                .map(String::from),
+            pr: value.get("pr").and_then(Value::as_u64),
+            date: value.get("date")?.as_str()?.to_string(),
+        })
+    }
+}
+
+pub struct StatusStore {
+    path: PathBuf,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl StatusStore {
+    pub fn path() -> io::Result<PathBuf> {
+        data_dir()
+            .map(|dir| dir.join("status.jsonl"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+    }
+
+    // Load the status file. A missing file means every package is new.
+    pub fn load() -> io::Result<StatusStore> {
+        let path = StatusStore::path()?;
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut entries = BTreeMap::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(line)
+                .ok()
+                .and_then(|value| Entry::from_json(&value))
+                .ok_or_else(|| {
+                    let msg = format!("{}:{}: invalid entry", path.display(), i + 1);
+                    io::Error::new(io::ErrorKind::InvalidData, msg)
+                })?;
+            entries.insert(entry.pkg_name.clone(), entry);
+        }
+        Ok(StatusStore { path, entries })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .entries
+            .values()
+            .map(|entry| format!("{}\n", entry.to_json()))
+            .collect();
+        std::fs::write(&self.path, text)
+    }
+
+    pub fn get(&self, pkg_name: &str) -> Option<&Entry> {
+        self.entries.get(pkg_name)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.values()
+    }
+
+    // Record `entry`, replacing the package's previous one. Setting a package
+    // back to `new` forgets it.
+    pub fn set(&mut self, entry: Entry) {
+        if entry.status == Status::New {
+            self.entries.remove(&entry.pkg_name);
+        } else {
+            self.entries.insert(entry.pkg_name.clone(), entry);
+        }
+    }
+}
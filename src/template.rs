@@ -0,0 +1,598 @@
+// Reading the `template` files of a void-packages checkout. Templates are
+// shell scripts, but the fields vupdate needs are plain top-level assignments
+// such as `version=1.2.3` or `maintainer="Name <email>"`, which are picked
+// out without running a shell. Parameter expansions like `${version%.*}` and
+// the mirror variables of xbps-src (`$GNOME_SITE`, `$PYPI_SITE`, ...) are
+// expanded; command substitutions are kept as they are.
+
+use crate::error::{Error, Result};
+use crate::pattern::glob_to_regex;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Template {
+    pub pkgname: String,
+    pub version: String,
+    pub revision: String,
+    pub maintainer: String,
+    pub homepage: String,
+    pub short_desc: String,
+    // URLs of the source archives, each optionally followed by `>filename`
+    pub distfiles: Vec<String>,
+    // The sha256 sums of the distfiles, in the same order
+    pub checksum: Vec<String>,
+    // `restricted=yes`: the package may not be distributed as a binary, so
+    // the build servers do not build it
+    pub restricted: bool,
+    // The repository other than the main one the package goes to, e.g.
+    // `nonfree`
+    pub repository: String,
+}
+
+// The maintainer address of packages nobody looks after
+pub static ORPHAN_EMAIL: &str = "orphan@voidlinux.org";
+
+impl Template {
+    // The `<version>_<revision>` the template builds
+    pub fn full_version(&self) -> String {
+        format!("{}_{}", self.version, self.revision)
+    }
+
+    // Whether the package has no maintainer and is up for adoption
+    pub fn is_orphaned(&self) -> bool {
+        self.maintainer.contains(&format!("<{}>", ORPHAN_EMAIL))
+    }
+}
+
+// Mirror variables set by xbps-src for use in `distfiles`, from
+// common/environment/setup/misc.sh
+static SITES: &[(&str, &str)] = &[
+    ("CPAN_SITE", "https://www.cpan.org/modules/by-module"),
+    ("DEBIAN_SITE", "https://ftp.debian.org/debian/pool"),
+    ("FREEDESKTOP_SITE", "https://freedesktop.org/software"),
+    ("GNOME_SITE", "https://download.gnome.org/sources"),
+    ("GNU_SITE", "https://ftp.gnu.org/gnu"),
+    ("KDE_SITE", "https://download.kde.org/stable"),
+    ("KERNEL_SITE", "https://www.kernel.org/pub/linux"),
+    ("MOZILLA_SITE", "https://ftp.mozilla.org/pub"),
+    (
+        "NONGNU_SITE",
+        "https://download.savannah.nongnu.org/releases",
+    ),
+    (
+        "PYPI_SITE",
+        "https://files.pythonhosted.org/packages/source",
+    ),
+    (
+        "SOURCEFORGE_SITE",
+        "https://downloads.sourceforge.net/sourceforge",
+    ),
+    ("UBUNTU_SITE", "http://archive.ubuntu.com/ubuntu/pool"),
+    (
+        "VIDEOLAN_SITE",
+        "https://download.videolan.org/pub/videolan",
+    ),
+    ("XORG_SITE", "https://www.x.org/releases/individual"),
+];
+
+// Path of the template building `pkg_name` in the checkout at `distdir`.
+// Subpackages are symlinks to the directory of their main package, so their
+// name leads to the right template too.
+pub fn path(distdir: &Path, pkg_name: &str) -> PathBuf {
+    distdir.join("srcpkgs").join(pkg_name).join("template")
+}
+
+// The main package of subpackage `pkg_name` in the checkout at `distdir`, from
+// its symlink, or `None` if it is not a subpackage there
+pub fn source_package(distdir: &Path, pkg_name: &str) -> Option<String> {
+    let target = std::fs::read_link(distdir.join("srcpkgs").join(pkg_name)).ok()?;
+    let name = target.file_name()?.to_str()?;
+    Some(name.to_string()).filter(|name| name != pkg_name)
+}
+
+// The subpackages of `main` in the checkout at `distdir`: the packages whose
+// `srcpkgs` symlink points at its directory
+pub fn subpackages(distdir: &Path, main: &str) -> Result<Vec<String>> {
+    let srcpkgs = distdir.join("srcpkgs");
+    let entries = std::fs::read_dir(&srcpkgs).map_err(|source| Error::Io {
+        path: srcpkgs.display().to_string(),
+        source,
+    })?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| source_package(distdir, name).as_deref() == Some(main))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+// Read the template building `pkg_name`, or `None` if the checkout has no such
+// package
+pub fn load(distdir: &Path, pkg_name: &str) -> Result<Option<Template>> {
+    Ok(load_text(distdir, pkg_name)?.map(|text| parse(&text)))
+}
+
+// The text of the template building `pkg_name`, see `load`
+pub fn load_text(distdir: &Path, pkg_name: &str) -> Result<Option<String>> {
+    let path = path(distdir, pkg_name);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(source) => Err(Error::Io {
+            path: path.display().to_string(),
+            source,
+        }),
+    }
+}
+
+// Pick the fields out of a template's text. Fields that are missing are left
+// empty.
+pub fn parse(text: &str) -> Template {
+    let mut vars: HashMap<String, String> = SITES
+        .iter()
+        .map(|(name, url)| (name.to_string(), url.to_string()))
+        .collect();
+    for (name, value) in assignments(text) {
+        let value = value.expand(&vars);
+        vars.insert(name.to_string(), value);
+    }
+    let mut field = |name: &str| vars.remove(name).unwrap_or_default();
+    let words = |value: String| value.split_whitespace().map(String::from).collect();
+    Template {
+        pkgname: field("pkgname"),
+        version: field("version"),
+        revision: field("revision"),
+        maintainer: field("maintainer"),
+        homepage: field("homepage"),
+        short_desc: field("short_desc"),
+        distfiles: words(field("distfiles")),
+        checksum: words(field("checksum")),
+        restricted: field("restricted") == "yes",
+        repository: field("repository"),
+    }
+}
+
+// The template `text` as it would read after updating to `version`, e.g. to
+// get the distfiles of the new version
+pub fn parse_at_version(text: &str, version: &str) -> Template {
+    match set_field(text, "version", version) {
+        Some(text) => parse(&text),
+        None => parse(text),
+    }
+}
+
+// Replace the value of the top-level assignment to `name` in a template's
+// text, leaving the rest of the text as it is. Returns `None` if the template
+// has no such assignment.
+pub fn set_field(text: &str, name: &str, value: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.starts_with(&prefix) {
+            let start = offset + prefix.len();
+            let (_, len) = word(&text[start..]);
+            return Some(format!(
+                "{}{}{}",
+                &text[..start],
+                quote(value),
+                &text[start + len..]
+            ));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+// Quote a value for a template if the shell would otherwise split or expand it
+fn quote(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "+-._/:@%,".contains(c);
+    if !value.is_empty() && value.chars().all(plain) {
+        return value.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '$' | '`' | '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Part of an assigned value: text taken literally, like single quoted text,
+// or text in which `$` starts an expansion
+enum Piece {
+    Literal(String),
+    Expanding(String),
+}
+
+// The unexpanded value of an assignment
+struct Word(Vec<Piece>);
+
+impl Word {
+    fn push(&mut self, c: char, expanding: bool) {
+        match (self.0.last_mut(), expanding) {
+            (Some(Piece::Expanding(s)), true) | (Some(Piece::Literal(s)), false) => s.push(c),
+            (_, true) => self.0.push(Piece::Expanding(c.to_string())),
+            (_, false) => self.0.push(Piece::Literal(c.to_string())),
+        }
+    }
+
+    fn expand(&self, vars: &HashMap<String, String>) -> String {
+        self.0
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(s) => s.clone(),
+                Piece::Expanding(s) => expand(s, vars),
+            })
+            .collect()
+    }
+}
+
+// The top-level `name=value` assignments, in order. Indented lines belong to
+// functions and are skipped. Values may be quoted and double quoted ones may
+// span several lines.
+fn assignments(text: &str) -> Vec<(&str, Word)> {
+    let mut result = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        let assignment = rest.split_once('=').filter(|(name, _)| {
+            name.len() < line_end
+                && !name.is_empty()
+                && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        });
+        match assignment {
+            Some((name, after)) => {
+                let (value, len) = word(after);
+                result.push((name, value));
+                rest = &after[len..];
+                rest = &rest[rest.find('\n').map_or(rest.len(), |i| i + 1)..];
+            }
+            None => rest = &rest[line_end..],
+        }
+    }
+    result
+}
+
+// Read a shell word at the start of `text`, returning it without quotes and
+// the number of bytes it took up
+fn word(text: &str) -> (Word, usize) {
+    let mut word = Word(Vec::new());
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            // As in the shell, only a few characters can be
+                            // escaped and an escaped newline joins lines
+                            match chars.next() {
+                                Some((_, '\n')) => {}
+                                Some((_, escaped @ ('$' | '`' | '"' | '\\'))) => {
+                                    word.push(escaped, false)
+                                }
+                                Some((_, other)) => {
+                                    word.push('\\', false);
+                                    word.push(other, false);
+                                }
+                                None => {}
+                            }
+                        }
+                        // Quotes inside a command substitution do not end
+                        // the string, so copy it up to the closing parenthesis
+                        '$' if chars.clone().next().map(|(_, c)| c) == Some('(') => {
+                            word.push('$', false);
+                            let mut depth = 0;
+                            for (_, c) in chars.by_ref() {
+                                word.push(c, false);
+                                match c {
+                                    '(' => depth += 1,
+                                    ')' if depth == 1 => break,
+                                    ')' => depth -= 1,
+                                    _ => {}
+                                }
+                            }
+                        }
+                        _ => word.push(c, true),
+                    }
+                }
+            }
+            '\'' => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c, false);
+                }
+            }
+            c if c.is_whitespace() || c == ';' => return (word, i),
+            _ => word.push(c, true),
+        }
+    }
+    (word, text.len())
+}
+
+// Expand the `$name` and `${name...}` references in `text`. Unknown
+// variables expand to nothing, like in the shell, but unsupported forms are
+// kept as they are.
+fn expand(text: &str, vars: &HashMap<String, String>) -> String {
+    let var = |name: &str| vars.get(name).map_or("", String::as_str);
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        match rest.strip_prefix('{').and_then(|b| Some((b, b.find('}')?))) {
+            Some((braced, end)) => {
+                let inner = &braced[..end];
+                match expand_braced(inner, var(braced_name(inner))) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        out.push_str("${");
+                        out.push_str(inner);
+                        out.push('}');
+                    }
+                }
+                rest = &braced[end + 1..];
+            }
+            None => {
+                let name = braced_name(rest);
+                if name.is_empty() {
+                    out.push('$');
+                } else {
+                    out.push_str(var(name));
+                }
+                rest = &rest[name.len()..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// The variable name at the start of `text`
+fn braced_name(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+// Expand the inside of `${...}`, given the variable's value: a plain name, or
+// a name followed by one of the `%`, `%%`, `#` and `##` pattern removals or by
+// a `/` or `//` substitution of literal text
+fn expand_braced(inner: &str, value: &str) -> Option<String> {
+    let name = braced_name(inner);
+    let op = &inner[name.len()..];
+    if name.is_empty() {
+        return None;
+    }
+    if op.is_empty() {
+        return Some(value.to_string());
+    }
+    if let Some(rest) = op.strip_prefix('/') {
+        let (all, rest) = match rest.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let (from, to) = rest.split_once('/').unwrap_or((rest, ""));
+        if from.is_empty() {
+            return None;
+        }
+        return Some(if all {
+            value.replace(from, to)
+        } else {
+            value.replacen(from, to, 1)
+        });
+    }
+    let (suffix, longest, pattern) = if let Some(p) = op.strip_prefix("%%") {
+        (true, true, p)
+    } else if let Some(p) = op.strip_prefix('%') {
+        (true, false, p)
+    } else if let Some(p) = op.strip_prefix("##") {
+        (false, true, p)
+    } else {
+        (false, false, op.strip_prefix('#')?)
+    };
+    let glob = Regex::new(&glob_to_regex(pattern)).ok()?;
+    // Where the value can be cut, ordered so that the first match is the
+    // wanted one
+    let mut cuts: Vec<usize> = (0..=value.len())
+        .filter(|i| value.is_char_boundary(*i))
+        .collect();
+    if suffix != longest {
+        cuts.reverse();
+    }
+    for cut in cuts {
+        let (removed, kept) = if suffix {
+            (&value[cut..], &value[..cut])
+        } else {
+            (&value[..cut], &value[cut..])
+        };
+        if glob.is_match(removed) {
+            return Some(kept.to_string());
+        }
+    }
+    Some(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn parses_a_single_distfile_template() {
+        let template = parse(
+            r#"# Template file for 'gtk+3'
+pkgname=gtk+3
+version=3.24.42
+revision=1
+build_style=meson
+build_helper="gir"
+configure_args="-Dman=true -Dtests=false
+ $(vopt_bool gir introspection) $(vopt_bool broadway broadway_backend)"
+hostmakedepends="gettext pkg-config glib-devel perl sassc gtk-update-icon-cache
+ wayland-devel xmlto docbook-xsl"
+short_desc="GTK+ toolkit for creating graphical user interfaces"
+maintainer="Enno Boland <gottox@voidlinux.org>"
+license="LGPL-2.1-or-later"
+homepage="https://www.gtk.org/"
+distfiles="${GNOME_SITE}/gtk+/${version%.*}/gtk+-${version}.tar.xz"
+checksum=50f89f615092d4dd01bbd759719f8bd380e5f149f6fd78a94725e2de112377e2
+
+build_options="broadway cloudproviders colord cups gir wayland x11"
+
+if [ "$CROSS_BUILD" ]; then
+	hostmakedepends+=" gtk+3-devel"
+fi
+
+post_install() {
+	version=0
+	vlicense COPYING
+}
+"#,
+        );
+        assert_eq!(template.pkgname, "gtk+3");
+        assert_eq!(template.version, "3.24.42");
+        assert_eq!(template.revision, "1");
+        assert_eq!(template.full_version(), "3.24.42_1");
+        assert_eq!(template.maintainer, "Enno Boland <gottox@voidlinux.org>");
+        assert_eq!(template.homepage, "https://www.gtk.org/");
+        assert_eq!(
+            template.short_desc,
+            "GTK+ toolkit for creating graphical user interfaces"
+        );
+        assert_eq!(
+            template.distfiles,
+            ["https://download.gnome.org/sources/gtk+/3.24/gtk+-3.24.42.tar.xz"]
+        );
+        assert_eq!(
+            template.checksum,
+            ["50f89f615092d4dd01bbd759719f8bd380e5f149f6fd78a94725e2de112377e2"]
+        );
+        assert!(!template.restricted);
+        assert_eq!(template.repository, "");
+    }
+
+    #[test]
+    fn parses_restricted_and_nonfree_templates() {
+        let template = parse(
+            r#"pkgname=discord
+version=0.0.58
+revision=1
+repository=nonfree
+restricted=yes
+"#,
+        );
+        assert!(template.restricted);
+        assert_eq!(template.repository, "nonfree");
+    }
+
+    #[test]
+    fn expands_mirror_variables() {
+        let template = parse(
+            r#"# Template file for 'python3-mock'
+pkgname=python3-mock
+version=4.0.3
+revision=3
+build_style=python3-module
+hostmakedepends="python3-setuptools"
+depends="python3"
+short_desc="Mocking and Patching Library for Testing (Python3)"
+maintainer="Orphaned <orphan@voidlinux.org>"
+license="BSD-2-Clause"
+homepage="https://github.com/testing-cabal/mock"
+distfiles="${PYPI_SITE}/m/mock/mock-${version}.tar.gz"
+checksum=7d3fbbde18228f4ff2f1f119a45cdffa458b4c0dee32eb4d2bb2f82554bac7bc
+"#,
+        );
+        assert_eq!(template.pkgname, "python3-mock");
+        assert_eq!(template.full_version(), "4.0.3_3");
+        assert_eq!(template.maintainer, "Orphaned <orphan@voidlinux.org>");
+        assert_eq!(
+            template.distfiles,
+            ["https://files.pythonhosted.org/packages/source/m/mock/mock-4.0.3.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn parses_several_distfiles_and_helper_variables() {
+        let template = parse(
+            r#"# Template file for 'libjpeg-turbo'
+pkgname=libjpeg-turbo
+version=3.0.1
+revision=1
+_utilver=1_2
+build_style=cmake
+short_desc='Derivative of libjpeg which uses SIMD instructions'
+maintainer="Orphaned <orphan@voidlinux.org>"
+license="IJG, BSD-3-Clause, Zlib"
+homepage="https://libjpeg-turbo.org/"
+distfiles="${SOURCEFORGE_SITE}/${pkgname}/${pkgname}-${version}.tar.gz
+ https://github.com/example/jpeg-utils/archive/v${_utilver//_/.}.tar.gz>utils-${_utilver}.tar.gz"
+checksum="22429507714ae147b3acacd299e82099fce5d9f456882fc28e252e4579ba2a75
+ 3b4d7f1dbe5a4f3cdb98b0e711b4e8f1d2c4a5f8e42a9f7a1c1f0c6f4c4c0b57"
+"#,
+        );
+        assert_eq!(template.full_version(), "3.0.1_1");
+        assert_eq!(
+            template.distfiles,
+            [
+                "https://downloads.sourceforge.net/sourceforge/libjpeg-turbo/libjpeg-turbo-3.0.1.tar.gz",
+                "https://github.com/example/jpeg-utils/archive/v1.2.tar.gz>utils-1_2.tar.gz",
+            ]
+        );
+        assert_eq!(
+            template.checksum,
+            [
+                "22429507714ae147b3acacd299e82099fce5d9f456882fc28e252e4579ba2a75",
+                "3b4d7f1dbe5a4f3cdb98b0e711b4e8f1d2c4a5f8e42a9f7a1c1f0c6f4c4c0b57",
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_the_shortest_and_longest_patterns() {
+        let template = parse(
+            "version=1.2.3\nhomepage=${version%.*}/${version%%.*}/${version#*.}/${version##*.}\n",
+        );
+        assert_eq!(template.homepage, "1.2/1/2.3/3");
+    }
+
+    #[test]
+    fn keeps_single_quoted_text_and_unsupported_forms() {
+        let template = parse(
+            "pkgname=foo\nversion=1.2.3\nhomepage='https://foo.org/$pkgname'\ndistfiles=\"https://foo.org/${version:-1}/foo-$version.tar.gz\"\n",
+        );
+        assert_eq!(template.homepage, "https://foo.org/$pkgname");
+        assert_eq!(
+            template.distfiles,
+            ["https://foo.org/${version:-1}/foo-1.2.3.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn sets_fields_in_place() {
+        let text = "pkgname=foo\nversion=1.0\nrevision=3 # bumped\nchecksum=\"aaa\n bbb\"\n\ndo_build() {\n\tversion=0\n}\n";
+        let text = super::set_field(text, "version", "1.1").unwrap();
+        let text = super::set_field(&text, "revision", "1").unwrap();
+        let text = super::set_field(&text, "checksum", "ccc\n ddd").unwrap();
+        assert_eq!(
+            text,
+            "pkgname=foo\nversion=1.1\nrevision=1 # bumped\nchecksum=\"ccc\n ddd\"\n\ndo_build() {\n\tversion=0\n}\n"
+        );
+        assert_eq!(super::set_field(&text, "homepage", "x"), None);
+    }
+
+    #[test]
+    fn skips_indented_assignments() {
+        let template = parse("# not a template\n\tversion=1.0\n");
+        assert_eq!(template, Default::default());
+    }
+}
@@ -0,0 +1,614 @@
+// A small parser for the subset of TOML that the configuration file needs:
+// tables (including dotted and array-of-tables headers), basic and literal
+// strings, integers, floats, booleans, arrays and inline tables. Dates and
+// multi-line strings are not supported.
+
+use std::collections::BTreeMap;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    // Name of the value's type, used in configuration error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Parse a TOML document into its root table
+pub fn parse(input: &str) -> Result<Table, ParseError> {
+    Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+    }
+    .document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.bump() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => self.error(format!("expected '{}', found '{}'", expected, c)),
+            None => self.error(format!("expected '{}', found end of file", expected)),
+        }
+    }
+
+    // Skip spaces and tabs on the current line
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') = self.peek() {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    // Skip whitespace, newlines and comments
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') | Some('\r') => {
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    // Consume the remainder of a line after a header or key/value pair
+    fn end_of_line(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        self.skip_comment();
+        match self.peek() {
+            None => Ok(()),
+            Some('\r') if self.peek_at(1) == Some('\n') => {
+                self.bump();
+                self.bump();
+                Ok(())
+            }
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => self.error(format!("unexpected '{}' after value", c)),
+        }
+    }
+
+    fn document(mut self) -> Result<Table, ParseError> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.bump();
+                    let array = self.peek() == Some('[');
+                    if array {
+                        self.bump();
+                    }
+                    self.skip_whitespace();
+                    let path = self.key_path()?;
+                    self.skip_whitespace();
+                    self.expect(']')?;
+                    if array {
+                        self.expect(']')?;
+                    }
+                    // Before the end of the line, for errors to tell this line
+                    if array {
+                        self.push_table_array(&mut root, &path)?;
+                    } else {
+                        self.table_at(&mut root, &path)?;
+                    }
+                    self.end_of_line()?;
+                    current = path;
+                }
+                Some(_) => {
+                    let (path, value) = self.key_value()?;
+                    let table = self.table_at(&mut root, &current)?;
+                    self.insert(table, &path, value)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+    }
+
+    fn key_value(&mut self) -> Result<(Vec<String>, Value), ParseError> {
+        let path = self.key_path()?;
+        self.skip_whitespace();
+        self.expect('=')?;
+        self.skip_whitespace();
+        let value = self.value()?;
+        Ok((path, value))
+    }
+
+    fn key_path(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut path = vec![self.key()?];
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                return Ok(path);
+            }
+            self.bump();
+            self.skip_whitespace();
+            path.push(self.key()?);
+        }
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        key.push(c);
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+                if key.is_empty() {
+                    return self.error("expected a key");
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.basic_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(c) if c.is_ascii_alphanumeric() || c == '+' || c == '-' => self.bare_value(),
+            Some(c) => self.error(format!("unexpected '{}' where a value was expected", c)),
+            None => self.error("expected a value, found end of file"),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('u') => self.unicode_escape(4)?,
+                        Some('U') => self.unicode_escape(8)?,
+                        _ => return self.error("invalid escape sequence"),
+                    };
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, len: usize) -> Result<char, ParseError> {
+        let mut hex = String::new();
+        for _ in 0..len {
+            match self.bump() {
+                Some(c) => hex.push(c),
+                None => return self.error("unterminated unicode escape"),
+            }
+        }
+        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+            Some(c) => Ok(c),
+            None => self.error(format!("invalid unicode escape '{}'", hex)),
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, ParseError> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some('\'') => return Ok(s),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_blank();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => return Ok(Value::Array(values)),
+                _ => return self.error("expected ',' or ']' in array"),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, ParseError> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Value::Table(table));
+        }
+        loop {
+            self.skip_whitespace();
+            let (path, value) = self.key_value()?;
+            self.insert(&mut table, &path, value)?;
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::Table(table)),
+                _ => return self.error("expected ',' or '}' in inline table"),
+            }
+        }
+    }
+
+    // Booleans and numbers
+    fn bare_value(&mut self) -> Result<Value, ParseError> {
+        let mut token = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_') {
+                token.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        match token.as_str() {
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => {}
+        }
+        let digits = token.replace('_', "");
+        if let Ok(i) = digits.parse::<i64>() {
+            return Ok(Value::Integer(i));
+        }
+        if digits.chars().any(|c| c.is_ascii_digit()) {
+            if let Ok(f) = digits.parse::<f64>() {
+                return Ok(Value::Float(f));
+            }
+        }
+        self.error(format!("invalid value '{}'", token))
+    }
+
+    // Walk (and create if needed) the tables along `path`. When a path
+    // component names an array of tables, its last element is used.
+    fn table_at<'a>(
+        &self,
+        root: &'a mut Table,
+        path: &[String],
+    ) -> Result<&'a mut Table, ParseError> {
+        let mut table = root;
+        for key in path {
+            let entry = table
+                .entry(key.clone())
+                .or_insert_with(|| Value::Table(Table::new()));
+            table = match entry {
+                Value::Table(t) => t,
+                Value::Array(a) => match a.last_mut() {
+                    Some(Value::Table(t)) => t,
+                    _ => return self.error(format!("'{}' is not a table", key)),
+                },
+                _ => return self.error(format!("'{}' is not a table", key)),
+            };
+        }
+        Ok(table)
+    }
+
+    fn push_table_array(&self, root: &mut Table, path: &[String]) -> Result<(), ParseError> {
+        let (last, parents) = path.split_last().expect("key paths are never empty");
+        let parent = self.table_at(root, parents)?;
+        let entry = parent
+            .entry(last.clone())
+            .or_insert_with(|| Value::Array(Vec::new()));
+        match entry {
+            Value::Array(a) => {
+                a.push(Value::Table(Table::new()));
+                Ok(())
+            }
+            _ => self.error(format!("'{}' is not an array of tables", last)),
+        }
+    }
+
+    fn insert(&self, table: &mut Table, path: &[String], value: Value) -> Result<(), ParseError> {
+        let (last, parents) = path.split_last().expect("key paths are never empty");
+        let parent = self.table_at(table, parents)?;
+        if parent.contains_key(last) {
+            return self.error(format!("duplicate key '{}'", last));
+        }
+        parent.insert(last.clone(), value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    fn error_of(input: &str) -> ParseError {
+        parse(input).expect_err("the document should not parse")
+    }
+
+    #[test]
+    fn parses_basic_and_literal_strings() {
+        let table = parse(
+            r#"
+basic = "tab\there \"quoted\" back\\slash\u00e9\U0001F600"
+literal = 'C:\no\escapes "here"'
+empty = ""
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            table["basic"],
+            string("tab\there \"quoted\" back\\slash\u{e9}\u{1F600}")
+        );
+        assert_eq!(table["literal"], string(r#"C:\no\escapes "here""#));
+        assert_eq!(table["empty"], string(""));
+    }
+
+    #[test]
+    fn rejects_bad_strings() {
+        assert_eq!(error_of(r#"a = "\q""#).message, "invalid escape sequence");
+        assert_eq!(error_of("a = \"open\nb = 1").message, "unterminated string");
+        assert_eq!(error_of("a = 'open").message, "unterminated string");
+        assert_eq!(
+            error_of(r#"a = "\uD800""#).message,
+            "invalid unicode escape 'D800'"
+        );
+    }
+
+    #[test]
+    fn parses_bare_values() {
+        let table = parse("a = 42\nb = -1_000\nc = 1.5\nd = true\ne = false").unwrap();
+        assert_eq!(table["a"], Value::Integer(42));
+        assert_eq!(table["b"], Value::Integer(-1000));
+        assert_eq!(table["c"], Value::Float(1.5));
+        assert_eq!(table["d"], Value::Boolean(true));
+        assert_eq!(table["e"], Value::Boolean(false));
+        assert_eq!(error_of("a = yes").message, "invalid value 'yes'");
+    }
+
+    #[test]
+    fn parses_arrays_and_inline_tables() {
+        let table = parse(
+            r#"
+sections = [
+    "maintainer",  # a comment
+    'installed',
+]
+nested = [[1, 2], []]
+headers = { Title = "vupdate", "X-Tags" = 'package', a.b = 1 }
+empty = {}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            table["sections"],
+            Value::Array(vec![string("maintainer"), string("installed")])
+        );
+        assert_eq!(
+            table["nested"],
+            Value::Array(vec![
+                Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+                Value::Array(Vec::new()),
+            ])
+        );
+        let mut inner = Table::new();
+        inner.insert("b".to_string(), Value::Integer(1));
+        let mut headers = Table::new();
+        headers.insert("Title".to_string(), string("vupdate"));
+        headers.insert("X-Tags".to_string(), string("package"));
+        headers.insert("a".to_string(), Value::Table(inner));
+        assert_eq!(table["headers"], Value::Table(headers));
+        assert_eq!(table["empty"], Value::Table(Table::new()));
+        assert_eq!(
+            error_of("a = [1 2]").message,
+            "expected ',' or ']' in array"
+        );
+        assert_eq!(
+            error_of("a = { b = 1 c = 2 }").message,
+            "expected ',' or '}' in inline table"
+        );
+    }
+
+    #[test]
+    fn parses_arrays_of_tables() {
+        let table = parse(
+            r#"
+email = "me@example.com"
+
+[[notify]]
+service = "ntfy"
+url = "https://ntfy.sh/updates"
+
+[[notify]]
+service = "gotify"
+[notify.headers]
+X-Priority = "5"
+"#,
+        )
+        .unwrap();
+        let notify = table["notify"].as_array().unwrap();
+        assert_eq!(notify.len(), 2);
+        let (first, second) = match (&notify[0], &notify[1]) {
+            (Value::Table(first), Value::Table(second)) => (first, second),
+            _ => panic!("notify should hold tables"),
+        };
+        assert_eq!(first["service"], string("ntfy"));
+        assert_eq!(first["url"], string("https://ntfy.sh/updates"));
+        assert_eq!(second["service"], string("gotify"));
+        assert!(!second.contains_key("url"));
+        match &second["headers"] {
+            Value::Table(headers) => assert_eq!(headers["X-Priority"], string("5")),
+            value => panic!("headers should be a table, not {:?}", value),
+        }
+        assert_eq!(table["email"], string("me@example.com"));
+        assert_eq!(
+            error_of("notify = 1\n[[notify]]").message,
+            "'notify' is not an array of tables"
+        );
+    }
+
+    #[test]
+    fn parses_dotted_keys_and_headers() {
+        let table = parse(
+            r#"
+upstream.foo.source = "github"
+[upstream.bar]
+source = "regex"
+[upstream."baz.qux"]
+url = "https://example.com"
+"#,
+        )
+        .unwrap();
+        let upstream = match &table["upstream"] {
+            Value::Table(upstream) => upstream,
+            value => panic!("upstream should be a table, not {:?}", value),
+        };
+        let keys: Vec<&str> = upstream.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["bar", "baz.qux", "foo"]);
+        match &upstream["foo"] {
+            Value::Table(foo) => assert_eq!(foo["source"], string("github")),
+            value => panic!("foo should be a table, not {:?}", value),
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        assert_eq!(error_of("a = 1\na = 2").message, "duplicate key 'a'");
+        assert_eq!(error_of("a.b = 1\na.b = 2").message, "duplicate key 'b'");
+        assert_eq!(
+            error_of("[smtp]\nport = 1\n[smtp]\nport = 2").message,
+            "duplicate key 'port'"
+        );
+        assert_eq!(
+            error_of("h = { a = 1, a = 2 }").message,
+            "duplicate key 'a'"
+        );
+        assert_eq!(error_of("a = 1\na.b = 2").message, "'a' is not a table");
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        let e = error_of("# comment\nemail = \"a\"\n\ncolor = \ndays = 3");
+        assert_eq!(e.line, 4);
+        assert_eq!(e.message, "unexpected '\n' where a value was expected");
+        assert_eq!(error_of("a = 1 b = 2").line, 1);
+        let e = error_of("a = [\n  1,\n  2\n  3,\n]");
+        assert_eq!(e.to_string(), "line 4: expected ',' or ']' in array");
+        assert_eq!(error_of("a = 1\r\nb = 1\r\na = 2\r\n").line, 3);
+        assert_eq!(error_of("a = 1\n[a.b]\n").line, 2);
+    }
+}
@@ -0,0 +1,268 @@
+// Version checks of one's own for packages whose new versions void-updates
+// gets wrong, configured per package in `[upstream.<pkg>]` tables:
+//
+// ```toml
+// [upstream.foo]
+// source = "git"       # the tags of a git repository
+// url = "https://git.example.org/foo.git"
+// pattern = '^v(\d+\.\d+)$'
+//
+// [upstream.bar]
+// source = "json"      # a field of a JSON document
+// url = "https://pypi.org/pypi/bar/json"
+// path = "info.version"
+// mode = "override"
+//
+// [upstream.baz]
+// source = "regex"     # the matches of a regex on a web page
+// url = "https://baz.example.org/download.html"
+// pattern = 'baz-([\d.]+)\.tar\.gz'
+// ```
+
+use crate::error::{Error, Result};
+use crate::fetch::Fetcher;
+use crate::version;
+use regex::Regex;
+use serde_json::Value;
+use std::process::Stdio;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    // The tags of a git repository, `url` being the repository
+    Git,
+    // A string or number in a JSON document, found by `path`
+    Json,
+    // The matches of `pattern` on the page at `url`
+    Regex,
+}
+
+impl Kind {
+    pub fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "git" => Some(Kind::Git),
+            "json" => Some(Kind::Json),
+            "regex" => Some(Kind::Regex),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Git => "git",
+            Kind::Json => "json",
+            Kind::Regex => "regex",
+        }
+    }
+}
+
+// What the version found is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // Tag void-updates' suggestion like the other sources do
+    Supplement,
+    // Replace void-updates' suggestion
+    Override,
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Mode> {
+        match name {
+            "supplement" => Some(Mode::Supplement),
+            "override" => Some(Mode::Override),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub kind: Kind,
+    pub url: String,
+    // Which tags or matches are versions. The first capture group, if there
+    // is one, is the version, otherwise the whole match.
+    pub pattern: Option<Regex>,
+    // The dot-separated keys and array indices leading to the version in a
+    // JSON document, e.g. `info.version` or `0.name`
+    pub path: Option<String>,
+    pub mode: Mode,
+}
+
+impl Check {
+    // The latest stable version found by the check, `None` if it found none.
+    // Git repositories cannot be asked offline.
+    pub async fn latest_version(
+        &self,
+        fetcher: &Fetcher,
+        pkg_name: &str,
+    ) -> Result<Option<String>> {
+        let key = format!("upstream/{}", pkg_name);
+        let result = match self.kind {
+            Kind::Git if fetcher.is_offline() => return Ok(None),
+            Kind::Git => remote_tags(&self.url).await.map(|tags| self.latest(&tags)),
+            Kind::Json => fetcher.cached(&key, &self.url).await.and_then(|text| {
+                let document: Value = serde_json::from_str(&text)
+                    .map_err(|e| Error::Parse(format!("{}: invalid JSON: {}", self.url, e)))?;
+                let path = self.path.as_deref().unwrap_or("");
+                Ok(json_at(&document, path).and_then(|version| self.latest(&[version])))
+            }),
+            Kind::Regex => fetcher.cached(&key, &self.url).await.map(|text| {
+                let pattern = self.pattern.as_ref().expect("regex checks have a pattern");
+                let found = pattern
+                    .captures_iter(&text)
+                    .filter_map(|caps| caps.get(1).or_else(|| caps.get(0)))
+                    .map(|m| m.as_str());
+                latest(found)
+            }),
+        };
+        match result {
+            Err(Error::NotCached(_)) => Ok(None),
+            result => result,
+        }
+    }
+
+    // The newest stable version among `candidates`, which `pattern` picks
+    // the versions out of
+    fn latest(&self, candidates: &[String]) -> Option<String> {
+        let versions = candidates
+            .iter()
+            .filter_map(|candidate| match &self.pattern {
+                Some(pattern) => {
+                    let caps = pattern.captures(candidate)?;
+                    Some(caps.get(1).or_else(|| caps.get(0))?.as_str())
+                }
+                None => Some(candidate.as_str()),
+            });
+        latest(versions)
+    }
+}
+
+fn latest<'a>(versions: impl Iterator<Item = &'a str>) -> Option<String> {
+    versions
+        .filter(|v| !v.is_empty() && !version::is_prerelease(v))
+        .max_by(|a, b| version::compare(a, b))
+        .map(String::from)
+}
+
+// The tags of the remote repository at `url`, without `git clone`. The `--`
+// keeps a URL starting with a dash from being taken for an option.
+async fn remote_tags(url: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["ls-remote", "--tags", "--refs", "--", url])
+        .stdin(Stdio::null())
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .map_err(|e| Error::Git(format!("could not run git: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Git(format!(
+            "git ls-remote {} failed: {}",
+            url,
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once("refs/tags/"))
+        .map(|(_, tag)| tag.to_string())
+        .collect())
+}
+
+// The string or number at `path` in `document`
+fn json_at(document: &Value, path: &str) -> Option<String> {
+    let value = path.split('.').filter(|key| !key.is_empty()).try_fold(
+        document,
+        |value, key| match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => value.get(key),
+        },
+    )?;
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn check(pattern: Option<&str>) -> Check {
+        Check {
+            kind: Kind::Git,
+            url: String::new(),
+            pattern: pattern.map(|p| Regex::new(p).unwrap()),
+            path: None,
+            mode: Mode::Supplement,
+        }
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_values_at_json_paths() {
+        let document = json!({
+            "info": {"version": "1.2.3", "build": 7, "yanked": false},
+            "releases": [{"name": "v2.0"}, {"name": "v1.9"}],
+        });
+        assert_eq!(json_at(&document, "info.version").as_deref(), Some("1.2.3"));
+        assert_eq!(json_at(&document, "info.build").as_deref(), Some("7"));
+        assert_eq!(
+            json_at(&document, "releases.0.name").as_deref(),
+            Some("v2.0")
+        );
+        assert_eq!(
+            json_at(&document, "releases.1.name").as_deref(),
+            Some("v1.9")
+        );
+        assert_eq!(json_at(&json!("3.0"), "").as_deref(), Some("3.0"));
+        assert_eq!(json_at(&json!([["4.0"]]), "0.0").as_deref(), Some("4.0"));
+    }
+
+    #[test]
+    fn finds_nothing_at_missing_or_non_scalar_paths() {
+        let document = json!({"info": {"yanked": false, "tags": []}, "releases": [{}]});
+        assert_eq!(json_at(&document, "info.version"), None);
+        assert_eq!(json_at(&document, "info.yanked"), None);
+        assert_eq!(json_at(&document, "info.tags"), None);
+        assert_eq!(json_at(&document, "info"), None);
+        assert_eq!(json_at(&document, "releases.2.name"), None);
+        assert_eq!(json_at(&document, "releases.first"), None);
+    }
+
+    #[test]
+    fn picks_the_latest_stable_version() {
+        let found = latest(
+            ["1.9", "1.10", "2.0rc1", "1.10b", "", "1.2"]
+                .iter()
+                .copied(),
+        );
+        assert_eq!(found.as_deref(), Some("1.10b"));
+        assert_eq!(latest(["2.0-beta", "2.0a1"].iter().copied()), None);
+        assert_eq!(latest(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn picks_versions_out_of_the_candidates() {
+        let tags = strings(&["v1.2", "v1.10", "v2.0-rc1", "latest", "foo-3.0"]);
+        assert_eq!(
+            check(Some(r"^v(.+)$")).latest(&tags).as_deref(),
+            Some("1.10")
+        );
+        // Without a capture group the whole match is the version
+        assert_eq!(
+            check(Some(r"\d+\.\d+")).latest(&tags).as_deref(),
+            Some("3.0")
+        );
+        assert_eq!(
+            check(None).latest(&strings(&["1.0", "0.9"])).as_deref(),
+            Some("1.0")
+        );
+        assert_eq!(check(Some("^r(\\d+)$")).latest(&tags), None);
+    }
+}
@@ -0,0 +1,289 @@
+// Helpers for classifying and comparing upstream version strings
+
+use std::cmp::Ordering;
+
+// Words that mark a version as a pre-release wherever they appear, e.g.
+// `2.0-rc1`, `1.4beta`, `3.12.0.dev2`
+static PRERELEASE_WORDS: &[&str] = &[
+    "alpha", "beta", "rc", "pre", "preview", "dev", "snapshot", "nightly",
+];
+
+// Whether `version` looks like a pre-release rather than a final release.
+//
+// The single letters `a` and `b` only count when followed by a number
+// (Python's `1.0a1`, `2.0b3`), since a trailing letter on its own usually
+// denotes a patch release (`1.1.1b`).
+pub fn is_prerelease(version: &str) -> bool {
+    let lower = version.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_alphabetic() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+            i += 1;
+        }
+        let word = &lower[start..i];
+        let followed_by_digit = bytes.get(i).is_some_and(u8::is_ascii_digit);
+        if PRERELEASE_WORDS.contains(&word) || (matches!(word, "a" | "b") && followed_by_digit) {
+            return true;
+        }
+    }
+    false
+}
+
+// Compare two versions the way xbps does. With the `libxbps` feature this is
+// xbps's own comparison, otherwise the same algorithm implemented here.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    #[cfg(feature = "libxbps")]
+    {
+        if let Some(ordering) = crate::libxbps::cmpver(a, b) {
+            return ordering;
+        }
+    }
+    dewey_compare(a, b)
+}
+
+// The weights of the modifiers in a version, relative to the numbers
+const ALPHA: i64 = -3;
+const BETA: i64 = -2;
+const RC: i64 = -1;
+const DOT: i64 = 0;
+
+// Words with a meaning of their own, matched case-insensitively in this order
+static MODIFIERS: &[(&str, i64)] = &[
+    ("alpha", ALPHA),
+    ("beta", BETA),
+    ("pre", RC),
+    ("rc", RC),
+    ("pl", DOT),
+    (".", DOT),
+];
+
+// A version split up like xbps's dewey comparison does: its components and
+// its revision, the number after `_`
+fn dewey(version: &str) -> (Vec<i64>, i64) {
+    let bytes = version.as_bytes();
+    let mut components = Vec::new();
+    let mut revision = 0;
+    let number = |i: &mut usize| {
+        let mut n: i64 = 0;
+        while let Some(digit) = bytes.get(*i).filter(|b| b.is_ascii_digit()) {
+            n = n.saturating_mul(10).saturating_add(i64::from(digit - b'0'));
+            *i += 1;
+        }
+        n
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_digit() {
+            components.push(number(&mut i));
+            continue;
+        }
+        let rest = &bytes[i..];
+        if let Some((word, weight)) = MODIFIERS.iter().find(|(word, _)| {
+            rest.len() >= word.len() && rest[..word.len()].eq_ignore_ascii_case(word.as_bytes())
+        }) {
+            components.push(*weight);
+            i += word.len();
+        } else if bytes[i] == b'_' {
+            i += 1;
+            revision = number(&mut i);
+        } else if bytes[i].is_ascii_alphabetic() {
+            // A letter counts as a further component after a dot: `1.1b`
+            // compares like `1.1.2`, newer than `1.1` and `1.1a`
+            components.push(DOT);
+            components.push(i64::from(bytes[i].to_ascii_lowercase() - b'a') + 1);
+            i += 1;
+        } else {
+            i += 1;
+        }
+    }
+    (components, revision)
+}
+
+// xbps's dewey comparison: the components are compared one by one, missing
+// ones counting as zero, and the revisions break ties
+fn dewey_compare(a: &str, b: &str) -> Ordering {
+    let (a, a_revision) = dewey(a);
+    let (b, b_revision) = dewey(b);
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            let x = a.get(i).copied().unwrap_or(0);
+            let y = b.get(i).copied().unwrap_or(0);
+            x.cmp(&y)
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or_else(|| a_revision.cmp(&b_revision))
+}
+
+// The numeric components of a version, e.g. `[1, 2, 10]` for `1.2.10rc1`
+fn numbers(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap_or(u64::MAX))
+        .collect()
+}
+
+// A measure of how big the jump from `current` to `new` is, ordered so that
+// larger jumps compare greater: a change in an earlier component outweighs any
+// change in a later one, and within the same component the larger difference
+// wins. Versions without a differing numeric component measure as zero.
+pub fn delta(current: &str, new: &str) -> (usize, u64) {
+    let current = numbers(current);
+    let new = numbers(new);
+    let len = current.len().max(new.len());
+    for i in 0..len {
+        let a = current.get(i).copied().unwrap_or(0);
+        let b = new.get(i).copied().unwrap_or(0);
+        if a != b {
+            return (len - i, b.abs_diff(a));
+        }
+    }
+    (0, 0)
+}
+
+// How significant an update is, by the first numeric component that changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    // Anything after the second component, or no numeric change at all
+    Patch,
+    // The second component, e.g. `1.2.3` -> `1.3.0`
+    Minor,
+    // The first component, e.g. `1.9` -> `2.0`
+    Major,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "patch" => Some(Level::Patch),
+            "minor" => Some(Level::Minor),
+            "major" => Some(Level::Major),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Patch => "patch",
+            Level::Minor => "minor",
+            Level::Major => "major",
+        }
+    }
+}
+
+// The level of the update from `current` to `new`
+pub fn level(current: &str, new: &str) -> Level {
+    let current = numbers(current);
+    let new = numbers(new);
+    let changed = (0..current.len().max(new.len()))
+        .find(|i| current.get(*i).copied().unwrap_or(0) != new.get(*i).copied().unwrap_or(0));
+    match changed {
+        Some(0) => Level::Major,
+        Some(1) => Level::Minor,
+        _ => Level::Patch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_order(older: &str, newer: &str) {
+        assert_eq!(
+            dewey_compare(older, newer),
+            Ordering::Less,
+            "{} < {}",
+            older,
+            newer
+        );
+        assert_eq!(
+            dewey_compare(newer, older),
+            Ordering::Greater,
+            "{} > {}",
+            newer,
+            older
+        );
+    }
+
+    #[test]
+    fn compares_numbers_numerically() {
+        assert_order("9.0", "10.0");
+        assert_order("0.9.5", "0.10.0");
+        assert_order("1.2.9", "1.2.10");
+        assert_order("2", "2.0.1");
+        assert_order("20231231", "20240101");
+    }
+
+    #[test]
+    fn missing_components_count_as_zero() {
+        assert_eq!(dewey_compare("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(dewey_compare("1", "1.0"), Ordering::Equal);
+        assert_eq!(dewey_compare("", "0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn prereleases_come_before_the_release() {
+        assert_order("1.0alpha1", "1.0beta1");
+        assert_order("1.0beta2", "1.0pre1");
+        assert_order("1.0rc1", "1.0");
+        assert_order("1.0alpha", "1.0alpha2");
+        assert_order("1.0rc2", "1.0rc10");
+        assert_order("0.9", "1.0rc1");
+        assert_eq!(dewey_compare("1.0pre1", "1.0rc1"), Ordering::Equal);
+        assert_eq!(dewey_compare("1.0RC1", "1.0rc1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn patch_levels_come_after_the_release() {
+        assert_order("1.0", "1.0pl1");
+        assert_eq!(dewey_compare("1.0pl1", "1.0.1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn letters_are_further_components() {
+        assert_order("1.1", "1.1a");
+        assert_order("1.1a", "1.1b");
+        assert_order("1.1.1z", "1.1.2");
+        assert_eq!(dewey_compare("1.1b", "1.1.2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn revisions_break_ties() {
+        assert_order("1.0_1", "1.0_2");
+        assert_order("1.0_9", "1.0_10");
+        assert_order("1.0_5", "1.0.1_1");
+        assert_eq!(dewey_compare("1.0", "1.0_0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn other_characters_are_skipped() {
+        assert_eq!(dewey_compare("1-2", "1+2"), Ordering::Equal);
+        assert_order("1.0", "1.0+1");
+        assert_order("2023-01-05", "2023-1-10");
+    }
+
+    #[test]
+    fn classifies_levels() {
+        assert_eq!(level("1.9.3", "2.0.0"), Level::Major);
+        assert_eq!(level("1.2.3", "1.3.0"), Level::Minor);
+        assert_eq!(level("1.2.3", "1.2.4"), Level::Patch);
+        assert_eq!(level("1.2", "1.2.0.1"), Level::Patch);
+        assert_eq!(level("1.2", "1.2b"), Level::Patch);
+        assert_eq!(level("20231231", "20240101"), Level::Major);
+    }
+
+    #[test]
+    fn huge_numbers_do_not_overflow() {
+        assert_order("1.99999999999999999999", "2");
+        assert_eq!(
+            dewey_compare("99999999999999999999", "99999999999999999998"),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,27 @@
+// The watchlist names packages whose updates should be reported even though
+// they are neither maintained by the user nor installed, e.g. packages they
+// plan to adopt. It lives at `$XDG_CONFIG_HOME/vupdate/watchlist` and holds
+// one package name per line; `#` starts a comment.
+
+use crate::config::config_dir;
+use std::collections::HashSet;
+use std::io;
+
+// Load the watched package names. A missing file is an empty watchlist.
+pub fn load() -> io::Result<HashSet<String>> {
+    let path = match config_dir() {
+        Some(dir) => dir.join("watchlist"),
+        None => return Ok(HashSet::new()),
+    };
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .map(|l| l.split('#').next().unwrap().trim())
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect())
+}
@@ -0,0 +1,511 @@
+// Querying the local xbps package database. The database is a property list,
+// `/var/db/xbps/pkgdb-0.38.plist`, mapping each installed package's name to a
+// dictionary with its pkgver and flags; it is read directly so that neither
+// the xbps binaries nor a fork are needed.
+
+use crate::error::{Error, Result};
+use crate::plist;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub static DEFAULT_PKGDB: &str = "/var/db/xbps/pkgdb-0.38.plist";
+
+// How many `xbps-query` processes `repo_versions` runs at once
+const REPO_QUERIES: usize = 8;
+
+pub struct InstalledPackage {
+    pub name: String,
+    // Name, version and revision, e.g. `gtk+3-3.24.41_1`
+    pub pkgver: String,
+    // Installed as a dependency rather than explicitly
+    pub automatic: bool,
+    // Held back from updates with `xbps-pkgdb -m hold`
+    pub hold: bool,
+    // The source package the binary package was built from, e.g. `foo` for
+    // `libfoo-devel`, as recorded from the repository data
+    pub sourcepkg: Option<String>,
+    // The dependency patterns of the package, e.g. `glibc>=2.39_1`
+    pub run_depends: Vec<String>,
+    // The one-line description of the package
+    pub short_desc: Option<String>,
+}
+
+impl InstalledPackage {
+    // The installed `<version>_<revision>`
+    pub fn version(&self) -> &str {
+        split_pkgver(&self.pkgver).map_or("", |(_, version)| version)
+    }
+}
+
+// Read all packages from the package database at `path`
+pub fn read_pkgdb(path: &Path) -> Result<Vec<InstalledPackage>> {
+    let text = std::fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+    parse_pkgdb(&text).map_err(|msg| {
+        Error::Xbps(format!(
+            "invalid xbps package database {}: {}",
+            path.display(),
+            msg
+        ))
+    })
+}
+
+// The packages in the text of a package database
+fn parse_pkgdb(text: &str) -> std::result::Result<Vec<InstalledPackage>, String> {
+    let root = plist::parse(text).map_err(|e| e.to_string())?;
+    let dict = root.as_dict().ok_or("not a dictionary")?;
+    // Besides packages, the database holds entries such as
+    // `_XBPS_ALTERNATIVES_`, which have no pkgver
+    Ok(dict
+        .iter()
+        .filter_map(|(name, entry)| {
+            let entry = entry.as_dict()?;
+            let flag = |key: &str| entry.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+            Some(InstalledPackage {
+                name: name.clone(),
+                pkgver: entry.get("pkgver")?.as_str()?.to_string(),
+                automatic: flag("automatic-install"),
+                hold: flag("hold"),
+                sourcepkg: entry
+                    .get("sourcepkg")
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+                run_depends: entry
+                    .get("run_depends")
+                    .and_then(|v| v.as_array())
+                    .map(|deps| {
+                        deps.iter()
+                            .filter_map(|dep| dep.as_str())
+                            .map(String::from)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                short_desc: entry
+                    .get("short_desc")
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+            })
+        })
+        .collect())
+}
+
+// The names of the explicitly installed packages in the database at `path`,
+// as listed by `xbps-query -m`, and also of those installed as dependencies
+// if `automatic` is set
+pub fn installed_packages_in(path: &Path, automatic: bool) -> Result<HashSet<String>> {
+    Ok(read_pkgdb(path)?
+        .into_iter()
+        .filter(|pkg| automatic || !pkg.automatic)
+        .map(|pkg| pkg.name)
+        .collect())
+}
+
+// The names of the explicitly installed packages on this system
+pub fn installed_packages() -> Result<HashSet<String>> {
+    installed_packages_in(Path::new(DEFAULT_PKGDB), false)
+}
+
+// The packages for which the remote repositories already have a newer binary,
+// as `(name, pkgver)` pairs. This runs `xbps-install -Mun`, which syncs the
+// repository index in memory and prints the transaction it would perform
+// without running it.
+pub fn pending_updates() -> Result<Vec<(String, String)>> {
+    let output = Command::new("xbps-install")
+        .arg("-Mun")
+        .output()
+        .map_err(|e| Error::Xbps(format!("could not run xbps-install: {}", e)))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Each line reads `<pkgver> <action> <arch> <repository> ...`
+    let pending: Vec<(String, String)> = stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pkgver = fields.next()?;
+            if fields.next()? != "update" {
+                return None;
+            }
+            let (name, _) = split_pkgver(pkgver)?;
+            Some((name.to_string(), pkgver.to_string()))
+        })
+        .collect();
+    // xbps-install exits with an error when there is nothing to update, so
+    // only report a failure that comes with a message
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if pending.is_empty() && !output.status.success() && !stderr.trim().is_empty() {
+        return Err(Error::Xbps(format!("xbps-install: {}", stderr.trim())));
+    }
+    Ok(pending)
+}
+
+// The `<version>_<revision>` of each of `names` in the remote repositories,
+// from `xbps-query -R`. Packages the repositories do not have are left out.
+// The queries run `REPO_QUERIES` at a time.
+pub fn repo_versions<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<HashMap<String, String>> {
+    let run_error = |e: std::io::Error| Error::Xbps(format!("could not run xbps-query: {}", e));
+    let names: Vec<&str> = names.into_iter().collect();
+    let mut versions = HashMap::new();
+    for batch in names.chunks(REPO_QUERIES) {
+        let mut children = Vec::new();
+        let mut spawn_error = None;
+        for name in batch {
+            let child = Command::new("xbps-query")
+                .args(["-R", "--property=pkgver", name])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            match child {
+                Ok(child) => children.push((name, child)),
+                Err(e) => {
+                    spawn_error = Some(e);
+                    break;
+                }
+            }
+        }
+        // The queries already started are waited on even after an error, so
+        // that none is left behind as a zombie
+        let mut wait_error = None;
+        for (name, child) in children {
+            match child.wait_with_output() {
+                Ok(output) => {
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    if let Some((_, version)) = split_pkgver(stdout.trim()) {
+                        versions.insert(name.to_string(), version.to_string());
+                    }
+                }
+                Err(e) => wait_error = wait_error.or(Some(e)),
+            }
+        }
+        if let Some(e) = spawn_error.or(wait_error) {
+            return Err(run_error(e));
+        }
+    }
+    Ok(versions)
+}
+
+// The packages among `names` whose template in the void-packages checkout at
+// `distdir` is newer than the binary in the remote repositories, as reported
+// by `xbps-checkvers`. Each entry holds the name, the repository's
+// `<version>_<revision>` (`None` if it does not have the package yet) and the
+// template's.
+pub fn outdated_templates<'a>(
+    distdir: &Path,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<(String, Option<String>, String)>> {
+    let output = Command::new("xbps-checkvers")
+        .arg("-D")
+        .arg(distdir)
+        .args(names)
+        .output()
+        .map_err(|e| Error::Xbps(format!("could not run xbps-checkvers: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Xbps(format!("xbps-checkvers: {}", stderr.trim())));
+    }
+    // Each line reads `<name> <repository version> <template version>`, with
+    // `?` for a package the repository does not have
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let repo = fields.next()?;
+            let template = fields.next()?;
+            let repo = Some(repo).filter(|v| *v != "?").map(String::from);
+            Some((name.to_string(), repo, template.to_string()))
+        })
+        .collect())
+}
+
+// The sonames package `name` provides, from the `shlib-provides` of its
+// binary in the remote repositories, or in the local `repositories` only if
+// any are given, e.g. those xbps-src builds into. `None` if the repositories
+// do not have the package.
+pub fn shlib_provides(name: &str, repositories: &[PathBuf]) -> Result<Option<Vec<String>>> {
+    let mut command = Command::new("xbps-query");
+    if repositories.is_empty() {
+        command.arg("-R");
+    } else {
+        command.arg("-i");
+        for repository in repositories {
+            command.arg(format!("--repository={}", repository.display()));
+        }
+    }
+    let output = command
+        .args(["--property=shlib-provides", name])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| Error::Xbps(format!("could not run xbps-query: {}", e)))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|soname| !soname.is_empty())
+            .map(String::from)
+            .collect(),
+    ))
+}
+
+// The pkgvers of the packages in the remote repositories linking to `soname`,
+// from their `shlib-requires`
+pub fn shlib_dependents(soname: &str) -> Result<Vec<String>> {
+    let output = Command::new("xbps-query")
+        .args(["-R", "--property=shlib-requires", "--search", soname])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| Error::Xbps(format!("could not run xbps-query: {}", e)))?;
+    // Each line reads `<pkgver>: <soname> (<repository>)`, and the search
+    // also matches sonames containing this one
+    let mut dependents: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pkgver, found) = line.split_once(": ")?;
+            let found = found.split_whitespace().next()?;
+            Some(pkgver.to_string()).filter(|_| found == soname)
+        })
+        .collect();
+    dependents.dedup();
+    Ok(dependents)
+}
+
+// The package name of a dependency pattern: `foo>=1.0_1`, `foo<2`,
+// `foo-1.0_1` or `foo-[0-9]*`
+pub fn dependency_name(pattern: &str) -> &str {
+    if let Some(end) = pattern.find(['<', '>', '=']) {
+        return &pattern[..end];
+    }
+    if let Some((name, _)) = split_pkgver(pattern) {
+        return name;
+    }
+    // A glob in the version, e.g. `python3-[0-9]*`
+    match pattern.find(['*', '?', '[']) {
+        Some(glob) => pattern[..glob]
+            .rfind('-')
+            .map_or(pattern, |end| &pattern[..end]),
+        None => pattern,
+    }
+}
+
+// How many installed packages directly depend on a package built from each
+// source package, given the source package of each installed package.
+// Packages built from the same source package do not count.
+pub fn reverse_dependencies(
+    pkgdb: &[InstalledPackage],
+    source: impl Fn(&InstalledPackage) -> String,
+) -> HashMap<String, usize> {
+    let sources: HashMap<&str, String> = pkgdb
+        .iter()
+        .map(|pkg| (pkg.name.as_str(), source(pkg)))
+        .collect();
+    let mut counts = HashMap::new();
+    for pkg in pkgdb {
+        let own = &sources[pkg.name.as_str()];
+        let depended: HashSet<&String> = pkg
+            .run_depends
+            .iter()
+            .filter_map(|dep| sources.get(dependency_name(dep)))
+            .filter(|dep_source| *dep_source != own)
+            .collect();
+        for dep_source in depended {
+            *counts.entry(dep_source.clone()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// The architecture of this system as xbps names it, e.g. `x86_64` or
+// `x86_64-musl`
+pub fn native_arch() -> Option<String> {
+    let output = Command::new("xbps-uhelper")
+        .arg("arch")
+        .stdin(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let arch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(arch).filter(|arch| !arch.is_empty())
+}
+
+// Split a pkgver such as `gtk+3-3.24.41_1` into the package name and the
+// `<version>_<revision>` part. Versions never contain a dash, so the name ends
+// at the last one; the revision must be a number.
+pub fn split_pkgver(pkgver: &str) -> Option<(&str, &str)> {
+    let (name, version) = pkgver.rsplit_once('-')?;
+    let (upstream, revision) = version.rsplit_once('_')?;
+    if name.is_empty()
+        || upstream.is_empty()
+        || revision.is_empty()
+        || !revision.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    Some((name, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dependency_name, parse_pkgdb, split_pkgver};
+
+    #[test]
+    fn splits_names_with_digits_after_dashes() {
+        assert_eq!(
+            split_pkgver("gtk+3-3.24.41_1"),
+            Some(("gtk+3", "3.24.41_1"))
+        );
+        assert_eq!(
+            split_pkgver("python3-3.12.2_1"),
+            Some(("python3", "3.12.2_1"))
+        );
+        assert_eq!(
+            split_pkgver("font-adobe-100dpi-1.0.3_4"),
+            Some(("font-adobe-100dpi", "1.0.3_4"))
+        );
+        assert_eq!(
+            split_pkgver("libX11-devel-1.8.7_1"),
+            Some(("libX11-devel", "1.8.7_1"))
+        );
+    }
+
+    #[test]
+    fn rejects_names_without_a_revision() {
+        assert_eq!(split_pkgver("gtk+3"), None);
+        assert_eq!(split_pkgver("gtk+3-3.24.41"), None);
+        assert_eq!(split_pkgver("gtk+3-3.24.41_"), None);
+        assert_eq!(split_pkgver("gtk+3-3.24.41_1a"), None);
+        assert_eq!(split_pkgver("-1.0_1"), None);
+    }
+
+    #[test]
+    fn names_dependency_patterns() {
+        assert_eq!(dependency_name("glibc>=2.39_1"), "glibc");
+        assert_eq!(dependency_name("libfoo<2"), "libfoo");
+        assert_eq!(dependency_name("gtk+3-3.24.41_1"), "gtk+3");
+        assert_eq!(dependency_name("python3-[0-9]*"), "python3");
+        assert_eq!(dependency_name("python3-mock"), "python3-mock");
+    }
+
+    // Trimmed from a real database, with an entity added to a description
+    const PKGDB: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>_XBPS_ALTERNATIVES_</key>
+	<dict>
+		<key>awk</key>
+		<array>
+			<string>gawk</string>
+			<string>nawk</string>
+		</array>
+	</dict>
+	<key>gawk</key>
+	<dict>
+		<key>alternatives</key>
+		<dict>
+			<key>awk</key>
+			<array>
+				<string>awk:gawk</string>
+			</array>
+		</dict>
+		<key>automatic-install</key>
+		<true/>
+		<key>installed_size</key>
+		<integer>3460934</integer>
+		<key>pkgver</key>
+		<string>gawk-5.3.0_1</string>
+		<key>repository</key>
+		<string>https://repo-default.voidlinux.org/current</string>
+		<key>run_depends</key>
+		<array>
+			<string>glibc&gt;=2.39_1</string>
+			<string>libreadline8&gt;=8.0_1</string>
+		</array>
+		<key>short_desc</key>
+		<string>GNU awk utility</string>
+		<key>state</key>
+		<string>installed</string>
+	</dict>
+	<key>gtk+3</key>
+	<dict>
+		<key>hold</key>
+		<true/>
+		<key>install-date</key>
+		<string>2024-03-02 14:07 CET</string>
+		<key>pkgver</key>
+		<string>gtk+3-3.24.41_1</string>
+		<key>run_depends</key>
+		<array/>
+		<key>short_desc</key>
+		<string>GTK+ toolkit &amp; widgets &lt;version 3&gt; &#x2014; &#8220;gtk3&#8221;</string>
+		<key>sourcepkg</key>
+		<string>gtk+3</string>
+	</dict>
+	<key>libreadline8</key>
+	<dict>
+		<key>automatic-install</key>
+		<false/>
+		<key>pkgver</key>
+		<string>libreadline8-8.2.010_1</string>
+		<key>sourcepkg</key>
+		<string>readline</string>
+	</dict>
+</dict>
+</plist>
+"#;
+
+    #[test]
+    fn reads_a_package_database() {
+        let pkgdb = parse_pkgdb(PKGDB).unwrap();
+        let names: Vec<&str> = pkgdb.iter().map(|pkg| pkg.name.as_str()).collect();
+        assert_eq!(names, ["gawk", "gtk+3", "libreadline8"]);
+
+        let gawk = &pkgdb[0];
+        assert_eq!(gawk.pkgver, "gawk-5.3.0_1");
+        assert_eq!(gawk.version(), "5.3.0_1");
+        assert!(gawk.automatic);
+        assert!(!gawk.hold);
+        assert_eq!(gawk.sourcepkg, None);
+        assert_eq!(gawk.run_depends, ["glibc>=2.39_1", "libreadline8>=8.0_1"]);
+        assert_eq!(gawk.short_desc.as_deref(), Some("GNU awk utility"));
+
+        let gtk = &pkgdb[1];
+        assert!(!gtk.automatic);
+        assert!(gtk.hold);
+        assert!(gtk.run_depends.is_empty());
+        assert_eq!(gtk.sourcepkg.as_deref(), Some("gtk+3"));
+        assert_eq!(
+            gtk.short_desc.as_deref(),
+            Some("GTK+ toolkit & widgets <version 3> \u{2014} \u{201c}gtk3\u{201d}")
+        );
+
+        let readline = &pkgdb[2];
+        assert!(!readline.automatic);
+        assert!(readline.run_depends.is_empty());
+        assert_eq!(readline.sourcepkg.as_deref(), Some("readline"));
+    }
+
+    #[test]
+    fn rejects_invalid_databases() {
+        let error = |text: &str| {
+            parse_pkgdb(text)
+                .err()
+                .expect("the database should not parse")
+        };
+        assert_eq!(error("<plist><array/></plist>"), "not a dictionary");
+        assert_eq!(
+            error("<plist><dict><key>a</key><string>&nbsp;</string></dict></plist>"),
+            "byte 33: unknown entity '&nbsp;'"
+        );
+        assert_eq!(
+            error("<plist><dict><key>a</key><real>1.0</real></dict></plist>"),
+            "byte 25: unsupported element <real>"
+        );
+    }
+}